@@ -1,10 +1,41 @@
 use std::fmt;
 
+use crate::error::MatikaError;
+use crate::expr::Expr;
+use crate::functions::Function;
 use crate::value::Value;
 use crate::Interpreter;
 
+/// Plain `f64` implementation of a builtin, used by native closures.
+pub type NativeFn = Box<dyn Fn(&[f64]) -> f64>;
+
 pub trait Callable: fmt::Debug + fmt::Display {
-    fn call(&self, interpreter: &mut Interpreter, args: Vec<Value>) -> Value;
+    fn call(&self, interpreter: &mut Interpreter, args: Vec<Value>) -> Result<Value, MatikaError>;
 
     fn arity(&self) -> u64;
+
+    /// Whether a call with `argc` arguments is allowed; builtins with optional
+    /// arguments accept more than their `arity`.
+    fn accepts(&self, argc: usize) -> bool {
+        argc == self.arity() as usize
+    }
+
+    /// How the function is named in messages and in functions built from it.
+    fn label(&self) -> String {
+        self.to_string()
+    }
+
+    fn as_function(&self) -> Option<&Function> {
+        None
+    }
+
+    /// A call with `args` as an expression over the functions this one is
+    /// built from, for functions like `p + sin` that `d` can differentiate.
+    fn formula(&self, _args: &[Expr]) -> Option<Expr> {
+        None
+    }
+
+    fn native(&self) -> Option<NativeFn> {
+        None
+    }
 }
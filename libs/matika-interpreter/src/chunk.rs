@@ -0,0 +1,82 @@
+use crate::stmt::Fnc;
+use crate::token::{Span, TokenKind};
+use crate::value::Value;
+
+/// A single VM instruction. Operands index into the owning `Chunk`'s tables,
+/// jump targets are absolute instruction offsets.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Op {
+    Constant(usize),
+    GetLocal(usize),
+    GetName(usize),
+    DefineName(usize),
+    DefineFunction(usize),
+    /// Creates a lambda from `functions[idx]`, capturing the frame's parameters.
+    Lambda(usize),
+    Unary(TokenKind),
+    Binary(TokenKind),
+    AssertBool,
+    Dup,
+    Pop,
+    Jump(usize),
+    JumpIfFalse(usize),
+    JumpIfTrue(usize),
+    Call(usize),
+    List(usize),
+    Index,
+    /// Whether the start and end bounds were given and sit on the stack.
+    Slice(bool, bool),
+    Print,
+    Return,
+}
+
+/// Compiled bytecode together with the constants, names and function
+/// declarations it refers to. `spans[i]` locates `code[i]` in the source, and
+/// `locals` names the parameter slots of a compiled clause.
+#[derive(Debug, Default)]
+pub struct Chunk {
+    pub code: Vec<Op>,
+    pub spans: Vec<Span>,
+    pub constants: Vec<Value>,
+    pub names: Vec<String>,
+    pub functions: Vec<Fnc>,
+    pub locals: Vec<Option<String>>,
+}
+
+impl Chunk {
+    pub fn emit(&mut self, op: Op, span: Span) -> usize {
+        self.code.push(op);
+        self.spans.push(span);
+
+        self.code.len() - 1
+    }
+
+    pub fn constant(&mut self, value: Value) -> usize {
+        self.constants.push(value);
+
+        self.constants.len() - 1
+    }
+
+    pub fn name(&mut self, name: &str) -> usize {
+        match self.names.iter().position(|other| other == name) {
+            Some(idx) => idx,
+            None => {
+                self.names.push(name.to_string());
+
+                self.names.len() - 1
+            }
+        }
+    }
+
+    /// Points the jump at `at` to the next instruction to be emitted.
+    pub fn patch(&mut self, at: usize) {
+        let target = self.code.len();
+
+        self.code[at] = match self.code[at] {
+            Op::Jump(_) => Op::Jump(target),
+            Op::JumpIfFalse(_) => Op::JumpIfFalse(target),
+            Op::JumpIfTrue(_) => Op::JumpIfTrue(target),
+            op => op,
+        };
+    }
+}
@@ -0,0 +1,203 @@
+use crate::chunk::{Chunk, Op};
+use crate::expr::Expr;
+use crate::stmt::{Fnc, Pattern, Stmt};
+use crate::token::{Span, TokenKind};
+use crate::value::Value;
+
+/// Lowers statements and function clauses into bytecode for the `Vm`.
+/// Parameters live in stack slots; every other name is looked up by name in
+/// the environment of the running frame.
+pub struct Compiler {
+    chunk: Chunk,
+    locals: Vec<Option<String>>,
+}
+
+impl Compiler {
+    pub fn compile_script(stmts: &[Stmt]) -> Chunk {
+        let mut compiler = Self {
+            chunk: Chunk::default(),
+            locals: vec![],
+        };
+
+        let mut span = Span::default();
+
+        if stmts.is_empty() {
+            compiler.constant(Value::Number(0.0), span);
+        }
+
+        for (idx, stmt) in stmts.iter().enumerate() {
+            span = stmt.span();
+
+            if idx > 0 {
+                compiler.chunk.emit(Op::Pop, span);
+            }
+
+            compiler.stmt(stmt);
+        }
+
+        compiler.chunk.emit(Op::Return, span);
+
+        compiler.chunk
+    }
+
+    pub fn compile_clause(clause: &Fnc) -> Chunk {
+        let locals = clause
+            .params
+            .iter()
+            .map(|param| match param {
+                Pattern::Binding(token) => Some(token.lexeme.clone()),
+                Pattern::Literal { .. } => None,
+            })
+            .collect();
+
+        let mut compiler = Self {
+            chunk: Chunk::default(),
+            locals,
+        };
+
+        compiler.chunk.locals = compiler.locals.clone();
+        compiler.expr(&clause.body);
+        compiler.chunk.emit(Op::Return, clause.body.span());
+
+        compiler.chunk
+    }
+
+    fn stmt(&mut self, stmt: &Stmt) {
+        let span = stmt.span();
+
+        match stmt {
+            Stmt::Expression(expr) => self.expr(expr),
+            Stmt::Print(expr) => {
+                self.expr(expr);
+                self.chunk.emit(Op::Print, span);
+                self.constant(Value::Number(0.0), span);
+            }
+            Stmt::Variable { name, initializer } => {
+                self.expr(initializer);
+
+                let idx = self.chunk.name(&name.lexeme);
+
+                self.chunk.emit(Op::DefineName(idx), span);
+                self.constant(Value::Number(0.0), span);
+            }
+            Stmt::Function(fnc) => {
+                self.chunk.functions.push(fnc.clone());
+
+                let idx = self.chunk.functions.len() - 1;
+
+                self.chunk.emit(Op::DefineFunction(idx), span);
+                self.constant(Value::Number(0.0), span);
+            }
+        }
+    }
+
+    fn expr(&mut self, expr: &Expr) {
+        let span = expr.span();
+
+        match expr {
+            Expr::Literal { value, .. } => self.constant(Value::from(value), span),
+            Expr::Grouping { expr, .. } => self.expr(expr),
+            Expr::Variable { name, .. } => {
+                let local = self
+                    .locals
+                    .iter()
+                    .rposition(|local| local.as_deref() == Some(name.lexeme.as_str()));
+
+                match local {
+                    Some(slot) => self.chunk.emit(Op::GetLocal(slot), span),
+                    None => {
+                        let idx = self.chunk.name(&name.lexeme);
+
+                        self.chunk.emit(Op::GetName(idx), span)
+                    }
+                };
+            }
+            Expr::Unary { op, right } => {
+                self.expr(right);
+                self.chunk.emit(Op::Unary(op.kind), op.span);
+            }
+            Expr::Binary { left, op, right } => {
+                self.expr(left);
+                self.expr(right);
+                self.chunk.emit(Op::Binary(op.kind), op.span);
+            }
+            Expr::Logical { left, op, right } => {
+                self.expr(left);
+                self.chunk.emit(Op::Dup, op.span);
+
+                let jump = match op.kind {
+                    TokenKind::Or => self.chunk.emit(Op::JumpIfTrue(0), op.span),
+                    _ => self.chunk.emit(Op::JumpIfFalse(0), op.span),
+                };
+
+                self.chunk.emit(Op::Pop, op.span);
+                self.expr(right);
+                self.chunk.emit(Op::AssertBool, op.span);
+                self.chunk.patch(jump);
+            }
+            Expr::Conditional {
+                condition,
+                then_branch,
+                else_branch,
+            } => {
+                self.expr(condition);
+
+                let else_jump = self.chunk.emit(Op::JumpIfFalse(0), condition.span());
+
+                self.expr(then_branch);
+
+                let end_jump = self.chunk.emit(Op::Jump(0), span);
+
+                self.chunk.patch(else_jump);
+                self.expr(else_branch);
+                self.chunk.patch(end_jump);
+            }
+            Expr::Call { callee, args, span } => {
+                self.expr(callee);
+
+                for arg in args {
+                    self.expr(arg);
+                }
+
+                self.chunk.emit(Op::Call(args.len()), *span);
+            }
+            Expr::List { elements, .. } => {
+                for element in elements {
+                    self.expr(element);
+                }
+
+                self.chunk.emit(Op::List(elements.len()), span);
+            }
+            Expr::Lambda { fnc, .. } => {
+                self.chunk.functions.push(fnc.as_ref().clone());
+
+                let idx = self.chunk.functions.len() - 1;
+
+                self.chunk.emit(Op::Lambda(idx), span);
+            }
+            Expr::Index { object, index, .. } => {
+                self.expr(object);
+                self.expr(index);
+                self.chunk.emit(Op::Index, span);
+            }
+            Expr::Slice {
+                object, start, end, ..
+            } => {
+                self.expr(object);
+
+                for bound in start.iter().chain(end.iter()) {
+                    self.expr(bound);
+                }
+
+                self.chunk
+                    .emit(Op::Slice(start.is_some(), end.is_some()), span);
+            }
+        }
+    }
+
+    fn constant(&mut self, value: Value, span: Span) {
+        let idx = self.chunk.constant(value);
+
+        self.chunk.emit(Op::Constant(idx), span);
+    }
+}
@@ -0,0 +1,175 @@
+use std::{fmt, ops};
+
+/// A complex number in rectangular form. Functions with branch cuts return
+/// their principal value.
+#[derive(Debug, Clone, Copy, PartialEq, Default)]
+pub struct Complex {
+    pub re: f64,
+    pub im: f64,
+}
+
+impl Complex {
+    pub const I: Complex = Complex { re: 0.0, im: 1.0 };
+
+    pub fn new(re: f64, im: f64) -> Self {
+        Self { re, im }
+    }
+
+    pub fn abs(self) -> f64 {
+        self.re.hypot(self.im)
+    }
+
+    pub fn arg(self) -> f64 {
+        self.im.atan2(self.re)
+    }
+
+    pub fn conj(self) -> Self {
+        Self::new(self.re, -self.im)
+    }
+
+    pub fn from_polar(r: f64, theta: f64) -> Self {
+        Self::new(r * theta.cos(), r * theta.sin())
+    }
+
+    pub fn exp(self) -> Self {
+        Self::from_polar(self.re.exp(), self.im)
+    }
+
+    pub fn ln(self) -> Self {
+        Self::new(self.abs().ln(), self.arg())
+    }
+
+    /// Uses the half-angle form instead of polar coordinates, so `sqrt(-1)` is exactly `i`.
+    pub fn sqrt(self) -> Self {
+        let r = self.abs();
+        let im = ((r - self.re) / 2.0).sqrt();
+
+        Self::new(
+            ((r + self.re) / 2.0).sqrt(),
+            if self.im < 0.0 { -im } else { im },
+        )
+    }
+
+    pub fn sin(self) -> Self {
+        Self::new(
+            self.re.sin() * self.im.cosh(),
+            self.re.cos() * self.im.sinh(),
+        )
+    }
+
+    pub fn cos(self) -> Self {
+        Self::new(
+            self.re.cos() * self.im.cosh(),
+            -self.re.sin() * self.im.sinh(),
+        )
+    }
+
+    pub fn powc(self, exponent: Self) -> Self {
+        if self.re == 0.0 && self.im == 0.0 {
+            return if exponent.re == 0.0 && exponent.im == 0.0 {
+                Self::new(1.0, 0.0)
+            } else {
+                Self::default()
+            };
+        }
+
+        // Integer powers by repeated squaring stay exact, so `i^2` is exactly -1.
+        if exponent.im == 0.0 && exponent.re.fract() == 0.0 && exponent.re.abs() <= 64.0 {
+            let mut result = Self::new(1.0, 0.0);
+            let mut base = self;
+            let mut n = exponent.re.abs() as u32;
+
+            while n > 0 {
+                if n % 2 == 1 {
+                    result = result * base;
+                }
+
+                base = base * base;
+                n /= 2;
+            }
+
+            return if exponent.re < 0.0 {
+                Self::new(1.0, 0.0) / result
+            } else {
+                result
+            };
+        }
+
+        (exponent * self.ln()).exp()
+    }
+}
+
+impl From<f64> for Complex {
+    fn from(re: f64) -> Self {
+        Self::new(re, 0.0)
+    }
+}
+
+impl ops::Neg for Complex {
+    type Output = Self;
+
+    fn neg(self) -> Self {
+        Self::new(-self.re, -self.im)
+    }
+}
+
+impl ops::Add for Complex {
+    type Output = Self;
+
+    fn add(self, other: Self) -> Self {
+        Self::new(self.re + other.re, self.im + other.im)
+    }
+}
+
+impl ops::Sub for Complex {
+    type Output = Self;
+
+    fn sub(self, other: Self) -> Self {
+        Self::new(self.re - other.re, self.im - other.im)
+    }
+}
+
+impl ops::Mul for Complex {
+    type Output = Self;
+
+    fn mul(self, other: Self) -> Self {
+        Self::new(
+            self.re * other.re - self.im * other.im,
+            self.re * other.im + self.im * other.re,
+        )
+    }
+}
+
+impl ops::Div for Complex {
+    type Output = Self;
+
+    fn div(self, other: Self) -> Self {
+        let norm = other.re * other.re + other.im * other.im;
+
+        Self::new(
+            (self.re * other.re + self.im * other.im) / norm,
+            (self.im * other.re - self.re * other.im) / norm,
+        )
+    }
+}
+
+/// Prints `3+4i`, `-2i` or `1-i`.
+impl fmt::Display for Complex {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let imaginary = if self.im.abs() == 1.0 {
+            String::from("i")
+        } else {
+            format!("{}i", self.im.abs())
+        };
+
+        if self.re == 0.0 {
+            let sign = if self.im < 0.0 { "-" } else { "" };
+
+            return write!(f, "{}{}", sign, imaginary);
+        }
+
+        let sign = if self.im < 0.0 { "-" } else { "+" };
+
+        write!(f, "{}{}{}", self.re, sign, imaginary)
+    }
+}
@@ -0,0 +1,453 @@
+use crate::callable::Callable;
+use crate::environment::Environment;
+use crate::error::MatikaError;
+use crate::expr::Expr;
+use crate::functions::Function;
+use crate::simplify::simplify;
+use crate::stmt::{Fnc, Pattern};
+use crate::token::{LiteralKind, Span, Token, TokenKind};
+use crate::value::Value;
+
+/// Calls to user functions are inlined at most this deep, so differentiating a
+/// recursive function fails instead of recursing forever.
+const MAX_INLINE_DEPTH: usize = 32;
+
+/// Differentiates `function` by its parameter at `param`, returning an
+/// anonymous function of the same parameters. Calls to other user functions
+/// are inlined, so their bodies are differentiated too.
+pub fn differentiate(function: &Function, param: usize) -> Result<Function, MatikaError> {
+    let clause = match function.clauses.as_slice() {
+        [clause] => clause,
+        _ => {
+            return Err(MatikaError::type_error(format!(
+                "cannot differentiate {}, it is defined by several clauses",
+                function.label()
+            )))
+        }
+    };
+
+    let var = match clause.params.get(param) {
+        Some(Pattern::Binding(token)) => token.lexeme.as_str(),
+        _ => {
+            return Err(MatikaError::type_error(format!(
+                "cannot differentiate {} by a literal parameter",
+                function.label()
+            )))
+        }
+    };
+
+    let mut differentiator = Differentiator {
+        var,
+        closure: &function.closure,
+        inlined: 0,
+    };
+
+    let derivative = Fnc {
+        name: Token::synthetic(
+            TokenKind::Identifier,
+            &format!("{}'", function.name()),
+            clause.name.span,
+        ),
+        params: clause.params.clone(),
+        body: simplify(&differentiator.derive(&clause.body)?),
+    };
+
+    Ok(Function::lambda(derivative, function.closure.clone()))
+}
+
+struct Differentiator<'a> {
+    var: &'a str,
+    closure: &'a Environment,
+    inlined: usize,
+}
+
+impl Differentiator<'_> {
+    fn derive(&mut self, expr: &Expr) -> Result<Expr, MatikaError> {
+        if !self.depends(expr) {
+            return Ok(integer(0));
+        }
+
+        match expr {
+            Expr::Variable { .. } => Ok(integer(1)),
+            Expr::Grouping { expr, .. } => self.derive(expr),
+            Expr::Unary { op, right } if op.kind == TokenKind::Minus => {
+                Ok(neg(self.derive(right)?))
+            }
+            Expr::Binary { left, op, right } => self.binary(left, op.kind, right),
+            Expr::Conditional {
+                condition,
+                then_branch,
+                else_branch,
+            } => Ok(Expr::Conditional {
+                condition: condition.clone(),
+                then_branch: Box::new(self.derive(then_branch)?),
+                else_branch: Box::new(self.derive(else_branch)?),
+            }),
+            Expr::Call { callee, args, .. } => match callee.as_ref() {
+                Expr::Variable { name, .. } => self.call(&name.lexeme, args),
+                _ => Err(unsupported(expr)),
+            },
+            _ => Err(unsupported(expr)),
+        }
+    }
+
+    fn binary(&mut self, u: &Expr, op: TokenKind, v: &Expr) -> Result<Expr, MatikaError> {
+        let du = self.derive(u)?;
+        let dv = self.derive(v)?;
+
+        match op {
+            TokenKind::Plus => Ok(add(du, dv)),
+            TokenKind::Minus => Ok(sub(du, dv)),
+            TokenKind::Star => Ok(add(mul(du, v.clone()), mul(u.clone(), dv))),
+            TokenKind::Slash if !self.depends(v) => Ok(div(du, v.clone())),
+            TokenKind::Slash => Ok(div(
+                sub(mul(du, v.clone()), mul(u.clone(), dv)),
+                pow(v.clone(), integer(2)),
+            )),
+            // Power rule, exponential rule, and the general case for u^v.
+            TokenKind::Caret if !self.depends(v) => Ok(mul(
+                mul(v.clone(), pow(u.clone(), sub(v.clone(), integer(1)))),
+                du,
+            )),
+            TokenKind::Caret if !self.depends(u) => Ok(mul(
+                mul(pow(u.clone(), v.clone()), call("ln", u.clone())),
+                dv,
+            )),
+            TokenKind::Caret => Ok(mul(
+                pow(u.clone(), v.clone()),
+                add(
+                    mul(dv, call("ln", u.clone())),
+                    div(mul(v.clone(), du), u.clone()),
+                ),
+            )),
+            _ => Err(MatikaError::type_error(format!(
+                "cannot differentiate '{}'",
+                op
+            ))),
+        }
+    }
+
+    /// Chain rule for builtins, inlining for user functions.
+    fn call(&mut self, name: &str, args: &[Expr]) -> Result<Expr, MatikaError> {
+        if let Some(Value::Function(fnc)) = self.closure.get(name) {
+            if let Some(function) = fnc.as_function() {
+                return self.inline(function, args);
+            }
+        }
+
+        let u = match args {
+            [u] => u.clone(),
+            _ => {
+                return Err(MatikaError::type_error(format!(
+                    "cannot differentiate {}",
+                    name
+                )))
+            }
+        };
+
+        let outer = match name {
+            "sin" => call("cos", u.clone()),
+            "cos" => neg(call("sin", u.clone())),
+            "exp" => call("exp", u.clone()),
+            "ln" => div(integer(1), u.clone()),
+            "sqrt" => div(integer(1), mul(integer(2), call("sqrt", u.clone()))),
+            "abs" => div(u.clone(), call("abs", u.clone())),
+            _ => {
+                return Err(MatikaError::type_error(format!(
+                    "cannot differentiate {}",
+                    name
+                )))
+            }
+        };
+
+        Ok(mul(outer, self.derive(&u)?))
+    }
+
+    fn inline(&mut self, function: &Function, args: &[Expr]) -> Result<Expr, MatikaError> {
+        let clause = match function.clauses.as_slice() {
+            [clause] if clause.params.len() == args.len() => clause,
+            _ => {
+                return Err(MatikaError::type_error(format!(
+                    "cannot differentiate through {}, it is defined by several clauses",
+                    function.label()
+                )))
+            }
+        };
+
+        if self.inlined == MAX_INLINE_DEPTH {
+            return Err(MatikaError::type_error(format!(
+                "cannot differentiate through {}, it is recursive",
+                function.label()
+            )));
+        }
+
+        let mut params = vec![];
+
+        for param in clause.params.iter() {
+            match param {
+                Pattern::Binding(token) => params.push(token.lexeme.as_str()),
+                Pattern::Literal { .. } => {
+                    return Err(MatikaError::type_error(format!(
+                        "cannot differentiate through {}, it matches literal arguments",
+                        function.label()
+                    )))
+                }
+            }
+        }
+
+        let body = substitute(&clause.body, &params, args);
+
+        self.inlined += 1;
+
+        let derivative = self.derive(&body);
+
+        self.inlined -= 1;
+
+        derivative
+    }
+
+    /// Whether `expr` mentions the variable, that is, is not a constant.
+    fn depends(&self, expr: &Expr) -> bool {
+        match expr {
+            Expr::Variable { name, .. } => name.lexeme == self.var,
+            Expr::Literal { .. } => false,
+            Expr::Grouping { expr, .. } => self.depends(expr),
+            Expr::Unary { right, .. } => self.depends(right),
+            Expr::Binary { left, right, .. } | Expr::Logical { left, right, .. } => {
+                self.depends(left) || self.depends(right)
+            }
+            Expr::Conditional {
+                condition,
+                then_branch,
+                else_branch,
+            } => self.depends(condition) || self.depends(then_branch) || self.depends(else_branch),
+            Expr::Call { args, .. } => args.iter().any(|arg| self.depends(arg)),
+            Expr::List { elements, .. } => elements.iter().any(|element| self.depends(element)),
+            Expr::Index { object, index, .. } => self.depends(object) || self.depends(index),
+            Expr::Slice {
+                object, start, end, ..
+            } => {
+                self.depends(object)
+                    || start
+                        .iter()
+                        .chain(end.iter())
+                        .any(|bound| self.depends(bound))
+            }
+            Expr::Lambda { fnc, .. } => self.depends(&fnc.body),
+        }
+    }
+}
+
+/// Replaces `params` by `args` in an inlined body. Other names lose their
+/// resolved depth, since the body now runs in the caller's scope, and are
+/// looked up by name instead.
+fn substitute(expr: &Expr, params: &[&str], args: &[Expr]) -> Expr {
+    let sub = |expr: &Expr| Box::new(substitute(expr, params, args));
+
+    match expr {
+        Expr::Variable { name, .. } => {
+            match params.iter().position(|param| *param == name.lexeme) {
+                Some(idx) => args[idx].clone(),
+                None => Expr::Variable {
+                    name: name.clone(),
+                    depth: None,
+                },
+            }
+        }
+        Expr::Literal { .. } | Expr::Lambda { .. } => expr.clone(),
+        Expr::Grouping { expr, span } => Expr::Grouping {
+            expr: sub(expr),
+            span: *span,
+        },
+        Expr::Unary { op, right } => Expr::Unary {
+            op: op.clone(),
+            right: sub(right),
+        },
+        Expr::Binary { left, op, right } => Expr::Binary {
+            left: sub(left),
+            op: op.clone(),
+            right: sub(right),
+        },
+        Expr::Logical { left, op, right } => Expr::Logical {
+            left: sub(left),
+            op: op.clone(),
+            right: sub(right),
+        },
+        Expr::Conditional {
+            condition,
+            then_branch,
+            else_branch,
+        } => Expr::Conditional {
+            condition: sub(condition),
+            then_branch: sub(then_branch),
+            else_branch: sub(else_branch),
+        },
+        Expr::Call {
+            callee,
+            args: call_args,
+            span,
+        } => Expr::Call {
+            callee: sub(callee),
+            args: call_args
+                .iter()
+                .map(|arg| substitute(arg, params, args))
+                .collect(),
+            span: *span,
+        },
+        Expr::List { elements, span } => Expr::List {
+            elements: elements
+                .iter()
+                .map(|element| substitute(element, params, args))
+                .collect(),
+            span: *span,
+        },
+        Expr::Index {
+            object,
+            index,
+            span,
+        } => Expr::Index {
+            object: sub(object),
+            index: sub(index),
+            span: *span,
+        },
+        Expr::Slice {
+            object,
+            start,
+            end,
+            span,
+        } => Expr::Slice {
+            object: sub(object),
+            start: start.as_deref().map(sub),
+            end: end.as_deref().map(sub),
+            span: *span,
+        },
+    }
+}
+
+fn unsupported(expr: &Expr) -> MatikaError {
+    MatikaError::type_error(format!("cannot differentiate {}", expr))
+}
+
+// Constructors folding the zeros and ones the rules above produce, so that
+// `d(x -> 3*x)` reads `3` rather than `0 * x + 3 * 1`.
+
+fn integer(n: i64) -> Expr {
+    Expr::Literal {
+        value: LiteralKind::Integer(n.into()),
+        span: Span::default(),
+    }
+}
+
+fn number(expr: &Expr) -> Option<Value> {
+    match expr {
+        Expr::Literal { value, .. } if value.number().is_some() => Some(Value::from(value)),
+        Expr::Grouping { expr, .. } => number(expr),
+        _ => None,
+    }
+}
+
+fn is(expr: &Expr, n: i64) -> bool {
+    number(expr).is_some_and(|value| value.equals(&Value::integer(n)))
+}
+
+/// Folds two constants into a literal when the result is one.
+fn fold(op: TokenKind, left: &Expr, right: &Expr) -> Option<Expr> {
+    let value = Value::binary(op, number(left)?, number(right)?).ok()?;
+
+    let literal = match value {
+        Value::Number(number) => LiteralKind::Number(number),
+        value => LiteralKind::Integer(value.as_integer()?),
+    };
+
+    Some(Expr::Literal {
+        value: literal,
+        span: Span::default(),
+    })
+}
+
+fn binary(op: TokenKind, left: Expr, right: Expr) -> Expr {
+    if let Some(folded) = fold(op, &left, &right) {
+        return folded;
+    }
+
+    Expr::Binary {
+        left: Box::new(left),
+        op: Token::synthetic(op, &op.to_string(), Span::default()),
+        right: Box::new(right),
+    }
+}
+
+fn add(left: Expr, right: Expr) -> Expr {
+    match (is(&left, 0), is(&right, 0)) {
+        (true, _) => right,
+        (_, true) => left,
+        _ => binary(TokenKind::Plus, left, right),
+    }
+}
+
+fn sub(left: Expr, right: Expr) -> Expr {
+    match (is(&left, 0), is(&right, 0)) {
+        (_, true) => left,
+        (true, _) => neg(right),
+        _ => binary(TokenKind::Minus, left, right),
+    }
+}
+
+fn mul(left: Expr, right: Expr) -> Expr {
+    if is(&left, 0) || is(&right, 0) {
+        integer(0)
+    } else if is(&left, 1) {
+        right
+    } else if is(&right, 1) {
+        left
+    } else {
+        binary(TokenKind::Star, left, right)
+    }
+}
+
+fn div(left: Expr, right: Expr) -> Expr {
+    if is(&left, 0) {
+        integer(0)
+    } else if is(&right, 1) {
+        left
+    } else {
+        binary(TokenKind::Slash, left, right)
+    }
+}
+
+fn pow(base: Expr, exponent: Expr) -> Expr {
+    if is(&exponent, 0) {
+        integer(1)
+    } else if is(&exponent, 1) {
+        base
+    } else {
+        binary(TokenKind::Caret, base, exponent)
+    }
+}
+
+fn neg(expr: Expr) -> Expr {
+    if let Expr::Literal { value, span } = &expr {
+        if let Some(value) = value.negate() {
+            return Expr::Literal { value, span: *span };
+        }
+    }
+
+    match expr {
+        Expr::Unary { op, right } if op.kind == TokenKind::Minus => *right,
+        expr => Expr::Unary {
+            op: Token::synthetic(TokenKind::Minus, "-", Span::default()),
+            right: Box::new(expr),
+        },
+    }
+}
+
+fn call(name: &str, arg: Expr) -> Expr {
+    Expr::Call {
+        callee: Box::new(Expr::Variable {
+            name: Token::synthetic(TokenKind::Identifier, name, Span::default()),
+            depth: None,
+        }),
+        args: vec![arg],
+        span: Span::default(),
+    }
+}
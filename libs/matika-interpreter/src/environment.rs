@@ -1,12 +1,32 @@
-use std::{collections::HashMap, rc::Rc};
+use std::cell::RefCell;
+use std::rc::Weak;
+use std::{collections::HashMap, fmt, rc::Rc};
 
-use crate::functions::{Factors, Pi, Plot, Sin};
+use crate::complex::Complex;
+use crate::functions::{
+    AlgebraFn, Compose, Derivative, Factorial, Factors, Float, Integrate, Len, LinalgFn, ListFn,
+    MathFn, Pi, Plot,
+};
 use crate::value::Value;
 
+/// A handle to a scope. Cloning shares the underlying storage, so closures see
+/// bindings added to their defining scope after they were created.
 #[derive(Clone)]
 pub struct Environment {
-    values: HashMap<String, Value>,
-    enclosing: Option<Box<Environment>>,
+    scope: Handle,
+}
+
+// The global scope stores the functions defined in it, so closures and the
+// scopes nested in them refer to it weakly; only its owner keeps it alive.
+#[derive(Clone)]
+enum Handle {
+    Strong(Rc<Scope>),
+    Weak(Weak<Scope>),
+}
+
+struct Scope {
+    values: RefCell<HashMap<String, Value>>,
+    enclosing: Option<Environment>,
 }
 
 impl Environment {
@@ -14,36 +34,219 @@ impl Environment {
         let mut values = HashMap::new();
 
         let pi = Rc::new(Pi);
-        let sin = Rc::new(Sin);
         let factors = Rc::new(Factors);
         let plot = Rc::new(Plot);
+        let float = Rc::new(Float);
+        let factorial = Rc::new(Factorial);
+        let len = Rc::new(Len);
+        let compose = Rc::new(Compose);
 
         values.insert(String::from("pi"), Value::Function(pi));
-        values.insert(String::from("sin"), Value::Function(sin));
         values.insert(String::from("factors"), Value::Function(factors));
         values.insert(String::from("plot"), Value::Function(plot));
+        values.insert(String::from("float"), Value::Function(float));
+        values.insert(String::from("factorial"), Value::Function(factorial));
+        values.insert(String::from("len"), Value::Function(len));
+        values.insert(String::from("compose"), Value::Function(compose));
+        values.insert(String::from("i"), Value::Complex(Complex::I));
+        values.insert(String::from("inf"), Value::Number(f64::INFINITY));
 
-        Self {
-            values,
-            enclosing: None,
+        for builtin in MathFn::builtins() {
+            values.insert(
+                String::from(builtin.name),
+                Value::Function(Rc::new(builtin)),
+            );
+        }
+
+        for builtin in LinalgFn::builtins() {
+            values.insert(
+                String::from(builtin.name),
+                Value::Function(Rc::new(builtin)),
+            );
         }
+
+        for builtin in ListFn::builtins() {
+            values.insert(
+                String::from(builtin.name),
+                Value::Function(Rc::new(builtin)),
+            );
+        }
+
+        for builtin in AlgebraFn::builtins() {
+            values.insert(
+                String::from(builtin.name),
+                Value::Function(Rc::new(builtin)),
+            );
+        }
+
+        for builtin in Integrate::builtins() {
+            values.insert(
+                String::from(builtin.name),
+                Value::Function(Rc::new(builtin)),
+            );
+        }
+
+        for builtin in Derivative::builtins() {
+            values.insert(
+                String::from(builtin.name),
+                Value::Function(Rc::new(builtin)),
+            );
+        }
+
+        Self::with_values(values, None)
     }
 
     pub fn from_enclosing(enclosing: Environment) -> Self {
+        Self::with_values(HashMap::new(), Some(enclosing))
+    }
+
+    fn with_values(values: HashMap<String, Value>, enclosing: Option<Environment>) -> Self {
         Self {
-            values: HashMap::new(),
-            enclosing: Some(Box::new(enclosing)),
+            scope: Handle::Strong(Rc::new(Scope {
+                values: RefCell::new(values),
+                enclosing,
+            })),
+        }
+    }
+
+    /// A handle for a function closing over this scope. It refers to the
+    /// global scope weakly, so defining a function there does not leak both.
+    pub fn capture(&self) -> Self {
+        match &self.scope {
+            Handle::Strong(scope) if scope.enclosing.is_none() => Self {
+                scope: Handle::Weak(Rc::downgrade(scope)),
+            },
+            _ => self.clone(),
         }
     }
 
-    pub fn define(&mut self, name: String, value: Value) {
-        self.values.insert(name, value);
+    /// The scope behind this handle, `None` once a weakly held global scope
+    /// has been dropped along with its interpreter.
+    fn scope(&self) -> Option<Rc<Scope>> {
+        match &self.scope {
+            Handle::Strong(scope) => Some(scope.clone()),
+            Handle::Weak(scope) => scope.upgrade(),
+        }
+    }
+
+    pub fn define(&self, name: String, value: Value) {
+        if let Some(scope) = self.scope() {
+            scope.values.borrow_mut().insert(name, value);
+        }
     }
 
     pub fn get(&self, name: &str) -> Option<Value> {
-        match self.values.get(name) {
-            value @ Some(_) => value.cloned(),
-            _ => self.enclosing.as_ref().and_then(|env| env.get(name)),
+        let scope = self.scope()?;
+        let value = scope.values.borrow().get(name).cloned();
+
+        value.or_else(|| scope.enclosing.as_ref().and_then(|env| env.get(name)))
+    }
+
+    pub fn get_at(&self, depth: usize, name: &str) -> Option<Value> {
+        let scope = self.scope()?;
+
+        if depth == 0 {
+            return scope.values.borrow().get(name).cloned();
+        }
+
+        scope
+            .enclosing
+            .as_ref()
+            .and_then(|env| env.get_at(depth - 1, name))
+    }
+
+    /// The names visible from this scope bound to a value equal to `value`,
+    /// in alphabetical order.
+    pub fn bound_to(&self, value: &Value) -> Vec<String> {
+        let mut names: Vec<String> = self
+            .names()
+            .into_iter()
+            .filter(|name| self.get(name).is_some_and(|bound| bound.equals(value)))
+            .collect();
+
+        names.sort();
+        names.dedup();
+
+        names
+    }
+
+    /// Every name visible from this scope, innermost first.
+    pub fn names(&self) -> Vec<String> {
+        let scope = match self.scope() {
+            Some(scope) => scope,
+            None => return vec![],
+        };
+        let mut names: Vec<String> = scope.values.borrow().keys().cloned().collect();
+
+        if let Some(enclosing) = &scope.enclosing {
+            names.extend(enclosing.names());
+        }
+
+        names
+    }
+}
+
+impl fmt::Debug for Environment {
+    // Scopes hold the functions that close over them, so only names are shown.
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let scope = match self.scope() {
+            Some(scope) => scope,
+            None => return write!(f, "Environment(dropped)"),
+        };
+        let values = scope.values.borrow();
+        let mut names: Vec<&String> = values.keys().collect();
+
+        names.sort();
+
+        f.debug_struct("Environment")
+            .field("names", &names)
+            .field("enclosing", &scope.enclosing)
+            .finish()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::rc::Rc;
+
+    use super::Handle;
+    use crate::interpreter::{Backend, Interpreter};
+    use crate::{Parser, Scanner};
+
+    fn run(interpreter: &mut Interpreter, src: &str) {
+        let mut scanner = Scanner::new(src.to_string());
+        let stmts = Parser::new(scanner.scan().unwrap()).parse().unwrap();
+
+        interpreter.interpret(stmts).unwrap();
+    }
+
+    #[test]
+    fn functions_do_not_keep_the_global_scope_alive() {
+        for backend in [Backend::TreeWalker, Backend::Vm] {
+            let mut interpreter = Interpreter::new().with_backend(backend);
+
+            for src in [
+                "f(x) = 1 if x < 1 else x*f(x - 1)",
+                "g = x -> x + 1",
+                "make(a) = x -> x + a",
+                "h = make(2)",
+                "h(f(3))",
+            ] {
+                run(&mut interpreter, src);
+            }
+
+            let global = match &interpreter.environment.scope {
+                Handle::Strong(scope) => Rc::downgrade(scope),
+                Handle::Weak(_) => panic!("the interpreter holds its global scope weakly"),
+            };
+
+            drop(interpreter);
+
+            assert!(
+                global.upgrade().is_none(),
+                "{:?} leaks its global scope",
+                backend
+            );
         }
     }
 }
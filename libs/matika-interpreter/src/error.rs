@@ -0,0 +1,245 @@
+use std::fmt;
+
+use crate::token::Span;
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum MatikaError {
+    Lex {
+        message: String,
+        span: Option<Span>,
+    },
+    Parse {
+        message: String,
+        span: Option<Span>,
+    },
+    Type {
+        message: String,
+        span: Option<Span>,
+    },
+    Arity {
+        callee: String,
+        expected: u64,
+        got: usize,
+        span: Option<Span>,
+    },
+    UnknownFunction {
+        name: String,
+        suggestion: Option<String>,
+        span: Option<Span>,
+    },
+    NotCallable {
+        name: String,
+        span: Option<Span>,
+    },
+    NoMatchingClause {
+        name: String,
+        args: String,
+        span: Option<Span>,
+    },
+    RecursionLimit {
+        limit: usize,
+        span: Option<Span>,
+    },
+}
+
+impl MatikaError {
+    pub fn lex(message: impl Into<String>) -> Self {
+        MatikaError::Lex {
+            message: message.into(),
+            span: None,
+        }
+    }
+
+    pub fn parse(message: impl Into<String>) -> Self {
+        MatikaError::Parse {
+            message: message.into(),
+            span: None,
+        }
+    }
+
+    pub fn type_error(message: impl Into<String>) -> Self {
+        MatikaError::Type {
+            message: message.into(),
+            span: None,
+        }
+    }
+
+    pub fn recursion_limit(limit: usize) -> Self {
+        MatikaError::RecursionLimit { limit, span: None }
+    }
+
+    pub fn span(&self) -> Option<Span> {
+        *self.span_slot()
+    }
+
+    /// Attaches `span` unless the error already points somewhere more precise.
+    pub fn at(mut self, span: Span) -> Self {
+        let slot = self.span_slot_mut();
+
+        if slot.is_none() {
+            *slot = Some(span);
+        }
+
+        self
+    }
+
+    /// Replaces the span, used when an error escapes code parsed from another source.
+    pub fn relocate(mut self, span: Span) -> Self {
+        *self.span_slot_mut() = Some(span);
+
+        self
+    }
+
+    pub fn message(&self) -> String {
+        match self {
+            MatikaError::Lex { message, .. }
+            | MatikaError::Parse { message, .. }
+            | MatikaError::Type { message, .. } => message.clone(),
+            MatikaError::Arity {
+                callee,
+                expected,
+                got,
+                ..
+            } => format!("{} expects {} argument(s), got {}", callee, expected, got),
+            MatikaError::UnknownFunction {
+                name,
+                suggestion: Some(suggestion),
+                ..
+            } => format!(
+                "unknown function `{}`, did you mean `{}`?",
+                name, suggestion
+            ),
+            MatikaError::UnknownFunction { name, .. } => format!("unknown function `{}`", name),
+            MatikaError::NotCallable { name, .. } => format!("`{}` is not callable", name),
+            MatikaError::NoMatchingClause { name, args, .. } => {
+                format!("no clause of `{}` matches ({})", name, args)
+            }
+            MatikaError::RecursionLimit { limit, .. } => {
+                format!("maximum recursion depth of {} exceeded", limit)
+            }
+        }
+    }
+
+    /// Renders the error together with the offending line of `source` and a caret underline.
+    pub fn render(&self, source: &str) -> String {
+        render(&self.to_string(), self.span(), source)
+    }
+
+    fn kind(&self) -> &'static str {
+        match self {
+            MatikaError::Lex { .. } => "lex error",
+            MatikaError::Parse { .. } => "parse error",
+            MatikaError::Type { .. } => "type error",
+            MatikaError::Arity { .. } => "arity mismatch",
+            MatikaError::UnknownFunction { .. } => "name error",
+            MatikaError::NotCallable { .. } => "type error",
+            MatikaError::NoMatchingClause { .. } => "match error",
+            MatikaError::RecursionLimit { .. } => "recursion error",
+        }
+    }
+
+    fn span_slot(&self) -> &Option<Span> {
+        match self {
+            MatikaError::Lex { span, .. }
+            | MatikaError::Parse { span, .. }
+            | MatikaError::Type { span, .. }
+            | MatikaError::Arity { span, .. }
+            | MatikaError::UnknownFunction { span, .. }
+            | MatikaError::NotCallable { span, .. }
+            | MatikaError::NoMatchingClause { span, .. }
+            | MatikaError::RecursionLimit { span, .. } => span,
+        }
+    }
+
+    fn span_slot_mut(&mut self) -> &mut Option<Span> {
+        match self {
+            MatikaError::Lex { span, .. }
+            | MatikaError::Parse { span, .. }
+            | MatikaError::Type { span, .. }
+            | MatikaError::Arity { span, .. }
+            | MatikaError::UnknownFunction { span, .. }
+            | MatikaError::NotCallable { span, .. }
+            | MatikaError::NoMatchingClause { span, .. }
+            | MatikaError::RecursionLimit { span, .. } => span,
+        }
+    }
+}
+
+impl fmt::Display for MatikaError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self.span() {
+            Some(span) => write!(
+                f,
+                "{} at line {}, column {}: {}",
+                self.kind(),
+                span.line,
+                span.column,
+                self.message()
+            ),
+            None => write!(f, "{}: {}", self.kind(), self.message()),
+        }
+    }
+}
+
+impl std::error::Error for MatikaError {}
+
+/// A problem that does not stop evaluation, such as an unused parameter.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Warning {
+    pub message: String,
+    pub span: Option<Span>,
+}
+
+impl Warning {
+    pub fn new(message: impl Into<String>, span: Span) -> Self {
+        Self {
+            message: message.into(),
+            span: Some(span),
+        }
+    }
+
+    pub fn render(&self, source: &str) -> String {
+        render(&self.to_string(), self.span, source)
+    }
+}
+
+impl fmt::Display for Warning {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self.span {
+            Some(span) => write!(
+                f,
+                "warning at line {}, column {}: {}",
+                span.line, span.column, self.message
+            ),
+            None => write!(f, "warning: {}", self.message),
+        }
+    }
+}
+
+fn render(header: &str, span: Option<Span>, source: &str) -> String {
+    let span = match span {
+        Some(span) => span,
+        None => return header.to_string(),
+    };
+
+    let line = source.lines().nth(span.line - 1).unwrap_or("");
+    let gutter = span.line.to_string();
+    let padding = " ".repeat(gutter.len());
+
+    let width = source
+        .get(span.start..span.end)
+        .map(|text| text.chars().count())
+        .unwrap_or(0)
+        .max(1);
+
+    format!(
+        "{}\n{} |\n{} | {}\n{} | {}{}",
+        header,
+        padding,
+        gutter,
+        line,
+        padding,
+        " ".repeat(span.column - 1),
+        "^".repeat(width)
+    )
+}
@@ -1,5 +1,6 @@
 use crate::{
-    token::{LiteralKind, Token},
+    stmt::Fnc,
+    token::{LiteralKind, Span, Token},
     visitor::{Acceptor, Visitor},
 };
 
@@ -10,9 +11,30 @@ pub enum Expr {
         op: Token,
         right: Box<Expr>,
     },
-    Grouping(Box<Expr>),
-    Literal(LiteralKind),
-    Variable(Token),
+    Logical {
+        left: Box<Expr>,
+        op: Token,
+        right: Box<Expr>,
+    },
+    Conditional {
+        condition: Box<Expr>,
+        then_branch: Box<Expr>,
+        else_branch: Box<Expr>,
+    },
+    Grouping {
+        expr: Box<Expr>,
+        span: Span,
+    },
+    Literal {
+        value: LiteralKind,
+        span: Span,
+    },
+    /// `depth` is the number of scopes between the use and its binding, filled
+    /// in by the resolver; `None` means the name is looked up globally.
+    Variable {
+        name: Token,
+        depth: Option<usize>,
+    },
     Unary {
         op: Token,
         right: Box<Expr>,
@@ -20,7 +42,52 @@ pub enum Expr {
     Call {
         callee: Box<Expr>,
         args: Vec<Expr>,
+        span: Span,
     },
+    List {
+        elements: Vec<Expr>,
+        span: Span,
+    },
+    Index {
+        object: Box<Expr>,
+        index: Box<Expr>,
+        span: Span,
+    },
+    /// `xs[start:end]`, either bound may be left out.
+    Slice {
+        object: Box<Expr>,
+        start: Option<Box<Expr>>,
+        end: Option<Box<Expr>>,
+        span: Span,
+    },
+    /// `x -> body` or `(x, y) -> body`, an anonymous single-clause function.
+    Lambda {
+        fnc: Box<Fnc>,
+        span: Span,
+    },
+}
+
+impl Expr {
+    pub fn span(&self) -> Span {
+        match self {
+            Expr::Binary { left, right, .. } => left.span().to(right.span()),
+            Expr::Logical { left, right, .. } => left.span().to(right.span()),
+            Expr::Conditional {
+                then_branch,
+                else_branch,
+                ..
+            } => then_branch.span().to(else_branch.span()),
+            Expr::Grouping { span, .. } => *span,
+            Expr::Literal { span, .. } => *span,
+            Expr::Variable { name, .. } => name.span,
+            Expr::Unary { op, right } => op.span.to(right.span()),
+            Expr::Call { span, .. } => *span,
+            Expr::List { span, .. } => *span,
+            Expr::Index { span, .. } => *span,
+            Expr::Slice { span, .. } => *span,
+            Expr::Lambda { span, .. } => *span,
+        }
+    }
 }
 
 impl Acceptor<Expr> for Expr {
@@ -1,37 +1,188 @@
+use std::borrow::Cow;
+use std::cell::RefCell;
+use std::cmp::Ordering;
 use std::fmt;
+use std::rc::Rc;
 
-use crate::callable::Callable;
+use num_bigint::BigInt;
+use num_rational::BigRational;
+use num_traits::{Signed, ToPrimitive, Zero};
+
+use crate::callable::{Callable, NativeFn};
+use crate::chunk::Chunk;
+use crate::compiler::Compiler;
+use crate::complex::Complex;
+use crate::derivative;
 use crate::environment::Environment;
-use crate::stmt::Fnc;
+use crate::error::MatikaError;
+use crate::expr::Expr;
+use crate::integer;
+use crate::simplify::{expand, factor, simplify};
+use crate::solve;
+use crate::stmt::{Fnc, Pattern};
+use crate::symbolic;
+use crate::token::{Span, Token, TokenKind};
 use crate::value::Value;
-use crate::Interpreter;
+use crate::vm::Vm;
+use crate::{Backend, Interpreter};
 
+/// A user function made of one or more clauses, tried in order until the
+/// parameter patterns match the arguments. Bodies are evaluated in a fresh
+/// scope enclosed by `closure`, the environment the function was defined in.
+/// Clauses are compiled for the `Vm` on their first call.
 #[derive(Debug)]
 pub struct Function {
-    pub declaration: Fnc,
+    pub clauses: Vec<Fnc>,
+    pub closure: Environment,
+    anonymous: bool,
+    chunks: RefCell<Vec<Option<Rc<Chunk>>>>,
+}
+
+impl Function {
+    pub fn new(clauses: Vec<Fnc>, closure: Environment) -> Self {
+        let chunks = RefCell::new(vec![None; clauses.len()]);
+
+        Self {
+            clauses,
+            closure: closure.capture(),
+            anonymous: false,
+            chunks,
+        }
+    }
+
+    /// A single-clause function without a name of its own, printed as its formula.
+    pub fn lambda(clause: Fnc, closure: Environment) -> Self {
+        Self {
+            anonymous: true,
+            ..Self::new(vec![clause], closure)
+        }
+    }
+
+    /// Defines `fnc` in `env`, merging it into an existing function of the same
+    /// arity instead of replacing it.
+    pub fn define(env: &Environment, fnc: &Fnc) {
+        let clauses = match env.get(&fnc.name.lexeme) {
+            Some(Value::Function(existing)) => match existing.as_function() {
+                Some(function) if function.arity() == fnc.params.len() as u64 => {
+                    function.with_clause(fnc.clone())
+                }
+                _ => vec![fnc.clone()],
+            },
+            _ => vec![fnc.clone()],
+        };
+
+        let function = Rc::new(Function::new(clauses, env.clone()));
+
+        env.define(fnc.name.lexeme.clone(), Value::Function(function));
+    }
+
+    pub fn name(&self) -> &str {
+        &self.clauses[0].name.lexeme
+    }
+
+    /// Clauses of the same shape replace each other; otherwise the new clause goes
+    /// before the first one that would shadow it, so `fact(0)` wins over `fact(n)`.
+    pub fn with_clause(&self, clause: Fnc) -> Vec<Fnc> {
+        let mut clauses = self.clauses.clone();
+
+        if let Some(idx) = clauses.iter().position(|other| other.same_shape(&clause)) {
+            clauses[idx] = clause;
+        } else if let Some(idx) = clauses.iter().position(|other| other.subsumes(&clause)) {
+            clauses.insert(idx, clause);
+        } else {
+            clauses.push(clause);
+        }
+
+        clauses
+    }
+
+    /// Index of the first clause whose patterns match `args`.
+    pub fn select(&self, args: &[Value]) -> Result<usize, MatikaError> {
+        self.clauses
+            .iter()
+            .position(|clause| {
+                clause
+                    .params
+                    .iter()
+                    .zip(args.iter())
+                    .all(|(param, arg)| match param {
+                        Pattern::Binding(_) => true,
+                        Pattern::Literal { value, .. } => Value::from(value).equals(arg),
+                    })
+            })
+            .ok_or_else(|| MatikaError::NoMatchingClause {
+                name: self.name().to_string(),
+                args: args
+                    .iter()
+                    .map(|arg| arg.to_string())
+                    .collect::<Vec<String>>()
+                    .join(", "),
+                span: None,
+            })
+    }
+
+    pub fn chunk(&self, clause: usize) -> Rc<Chunk> {
+        let mut chunks = self.chunks.borrow_mut();
+
+        chunks[clause]
+            .get_or_insert_with(|| Rc::new(Compiler::compile_clause(&self.clauses[clause])))
+            .clone()
+    }
 }
 
 impl fmt::Display for Function {
     fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
-        write!(f, "<fnc:{}>", self.declaration.name.lexeme)
+        if self.anonymous {
+            let clause = &self.clauses[0];
+
+            write!(
+                f,
+                "{}",
+                Fnc {
+                    body: simplify(&clause.body),
+                    ..clause.clone()
+                }
+            )
+        } else {
+            let clauses: Vec<String> = self.clauses.iter().map(Fnc::definition).collect();
+
+            write!(f, "{}", clauses.join("\n"))
+        }
     }
 }
 
 impl Callable for Function {
-    fn call(&self, interpreter: &mut Interpreter, args: Vec<Value>) -> Value {
-        let mut env = Environment::from_enclosing(interpreter.environment.clone());
+    fn call(&self, interpreter: &mut Interpreter, args: Vec<Value>) -> Result<Value, MatikaError> {
+        if interpreter.backend == Backend::Vm {
+            return Vm::new(interpreter).call(self, args);
+        }
 
-        for (idx, param) in self.declaration.params.iter().enumerate() {
-            let arg = args[idx].clone();
+        let clause = &self.clauses[self.select(&args)?];
+        let env = Environment::from_enclosing(self.closure.clone());
 
-            env.define(param.lexeme.clone(), arg);
+        for (param, arg) in clause.params.iter().zip(args) {
+            if let Pattern::Binding(param) = param {
+                env.define(param.lexeme.clone(), arg);
+            }
         }
 
-        interpreter.evaluate_inner(&self.declaration.body, env)
+        interpreter.nested(|interpreter| interpreter.evaluate_inner(&clause.body, env))
     }
 
     fn arity(&self) -> u64 {
-        self.declaration.params.len() as u64
+        self.clauses[0].params.len() as u64
+    }
+
+    fn label(&self) -> String {
+        if self.anonymous {
+            self.to_string()
+        } else {
+            format!("<fnc:{}>", self.name())
+        }
+    }
+
+    fn as_function(&self) -> Option<&Function> {
+        Some(self)
     }
 }
 
@@ -39,13 +190,17 @@ impl Callable for Function {
 pub struct Pi;
 
 impl Callable for Pi {
-    fn call(&self, _: &mut Interpreter, _: Vec<Value>) -> Value {
-        Value::Number(std::f64::consts::PI)
+    fn call(&self, _: &mut Interpreter, _: Vec<Value>) -> Result<Value, MatikaError> {
+        Ok(Value::Number(std::f64::consts::PI))
     }
 
     fn arity(&self) -> u64 {
         0
     }
+
+    fn native(&self) -> Option<NativeFn> {
+        Some(Box::new(|_| std::f64::consts::PI))
+    }
 }
 
 impl fmt::Display for Pi {
@@ -54,23 +209,508 @@ impl fmt::Display for Pi {
     }
 }
 
+/// A one-argument builtin defined on complex numbers. Real arguments go through
+/// `real` unless it has no real result, as for `sqrt(-1)`, so `sin(2)` stays a
+/// plain number.
+#[derive(Debug)]
+pub struct MathFn {
+    pub name: &'static str,
+    real: fn(f64) -> f64,
+    complex: fn(Complex) -> Complex,
+    exact: Option<fn(&BigRational) -> Option<BigRational>>,
+}
+
+impl MathFn {
+    fn new(name: &'static str, real: fn(f64) -> f64, complex: fn(Complex) -> Complex) -> Self {
+        Self {
+            name,
+            real,
+            complex,
+            exact: None,
+        }
+    }
+
+    /// Rational arguments stay exact whenever `exact` has a result.
+    fn with_exact(mut self, exact: fn(&BigRational) -> Option<BigRational>) -> Self {
+        self.exact = Some(exact);
+
+        self
+    }
+
+    pub fn builtins() -> Vec<MathFn> {
+        vec![
+            MathFn::new("sin", f64::sin, Complex::sin),
+            MathFn::new("cos", f64::cos, Complex::cos),
+            MathFn::new("sqrt", f64::sqrt, Complex::sqrt).with_exact(exact_sqrt),
+            MathFn::new("exp", f64::exp, Complex::exp),
+            MathFn::new("ln", f64::ln, Complex::ln),
+            MathFn::new("abs", f64::abs, |z| Complex::from(z.abs()))
+                .with_exact(|ratio| Some(ratio.abs())),
+            MathFn::new(
+                "arg",
+                |x| if x < 0.0 { std::f64::consts::PI } else { 0.0 },
+                |z| Complex::from(z.arg()),
+            )
+            .with_exact(|ratio| {
+                if ratio.is_negative() {
+                    None
+                } else {
+                    Some(BigRational::zero())
+                }
+            }),
+            MathFn::new("conj", |x| x, Complex::conj).with_exact(|ratio| Some(ratio.clone())),
+            MathFn::new("re", |x| x, |z| Complex::from(z.re))
+                .with_exact(|ratio| Some(ratio.clone())),
+            MathFn::new("im", |_| 0.0, |z| Complex::from(z.im))
+                .with_exact(|_| Some(BigRational::zero())),
+        ]
+    }
+
+    /// Lists are mapped element by element, so `sin([0, pi()/2])` is `[0, 1]`.
+    fn apply(&self, arg: &Value) -> Result<Value, MatikaError> {
+        match arg {
+            Value::Number(num) => Ok(self.real(*num)),
+            Value::Rational(ratio) => match self.exact.and_then(|exact| exact(ratio)) {
+                Some(result) => Ok(Value::Rational(result)),
+                None => Ok(self.real(arg.as_f64().unwrap_or(f64::NAN))),
+            },
+            Value::Complex(z) => Ok(Value::complex((self.complex)(*z))),
+            Value::List(values) => values
+                .iter()
+                .map(|value| self.apply(value))
+                .collect::<Result<Vec<Value>, MatikaError>>()
+                .map(Value::List),
+            Value::Unbound(_) | Value::Symbolic(_) => symbolic::call(self.name, arg),
+            arg => Err(MatikaError::type_error(format!(
+                "{} expects a number, got {}",
+                self.name,
+                arg.type_name()
+            ))),
+        }
+    }
+
+    fn real(&self, num: f64) -> Value {
+        let result = (self.real)(num);
+
+        if result.is_nan() && !num.is_nan() {
+            Value::complex((self.complex)(Complex::from(num)))
+        } else {
+            Value::Number(result)
+        }
+    }
+}
+
+/// Square roots of rationals whose numerator and denominator are perfect squares.
+fn exact_sqrt(ratio: &BigRational) -> Option<BigRational> {
+    if ratio.is_negative() {
+        return None;
+    }
+
+    let numer = ratio.numer().sqrt();
+    let denom = ratio.denom().sqrt();
+
+    if &(&numer * &numer) == ratio.numer() && &(&denom * &denom) == ratio.denom() {
+        Some(BigRational::new(numer, denom))
+    } else {
+        None
+    }
+}
+
+impl fmt::Display for MathFn {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "<fnc:{}>", self.name)
+    }
+}
+
+impl Callable for MathFn {
+    fn call(&self, _: &mut Interpreter, args: Vec<Value>) -> Result<Value, MatikaError> {
+        self.apply(&args[0])
+    }
+
+    fn arity(&self) -> u64 {
+        1
+    }
+
+    fn native(&self) -> Option<NativeFn> {
+        let real = self.real;
+
+        Some(Box::new(move |args| real(args[0])))
+    }
+}
+
+/// A linear algebra builtin. Arguments are converted with `Value::as_matrix`
+/// and `Value::as_vector`, so plain lists work wherever a vector is expected.
 #[derive(Debug)]
-pub struct Sin;
+pub struct LinalgFn {
+    pub name: &'static str,
+    arity: u64,
+    apply: fn(&[Value]) -> Result<Value, MatikaError>,
+}
+
+impl LinalgFn {
+    fn new(
+        name: &'static str,
+        arity: u64,
+        apply: fn(&[Value]) -> Result<Value, MatikaError>,
+    ) -> Self {
+        Self { name, arity, apply }
+    }
+
+    pub fn builtins() -> Vec<LinalgFn> {
+        vec![
+            LinalgFn::new("transpose", 1, |args| {
+                Ok(Value::Matrix(args[0].as_matrix("transpose")?.transpose()))
+            }),
+            LinalgFn::new("det", 1, |args| {
+                args[0].as_matrix("det")?.det().map(Value::Number)
+            }),
+            LinalgFn::new("inv", 1, |args| {
+                args[0].as_matrix("inv")?.inv().map(Value::Matrix)
+            }),
+            LinalgFn::new("rank", 1, |args| {
+                Ok(Value::integer(args[0].as_matrix("rank")?.rank()))
+            }),
+            LinalgFn::new("eigenvalues", 1, |args| {
+                let mut eigenvalues = args[0].as_matrix("eigenvalues")?.eigenvalues()?;
+
+                eigenvalues.sort_by(|a, b| {
+                    b.re.partial_cmp(&a.re)
+                        .unwrap_or(Ordering::Equal)
+                        .then(b.im.partial_cmp(&a.im).unwrap_or(Ordering::Equal))
+                });
+
+                Ok(Value::List(
+                    eigenvalues.into_iter().map(Value::complex).collect(),
+                ))
+            }),
+            LinalgFn::new("dot", 2, |args| {
+                let (a, b) = (args[0].as_vector("dot")?, args[1].as_vector("dot")?);
+
+                if a.len() != b.len() {
+                    return Err(MatikaError::type_error(format!(
+                        "dot expects vectors of equal length, got {} and {}",
+                        a.len(),
+                        b.len()
+                    )));
+                }
+
+                Ok(Value::Number(a.iter().zip(&b).map(|(a, b)| a * b).sum()))
+            }),
+            LinalgFn::new("cross", 2, |args| {
+                let (a, b) = (args[0].as_vector("cross")?, args[1].as_vector("cross")?);
 
-impl fmt::Display for Sin {
+                if a.len() != 3 || b.len() != 3 {
+                    return Err(MatikaError::type_error(format!(
+                        "cross expects two vectors of length 3, got {} and {}",
+                        a.len(),
+                        b.len()
+                    )));
+                }
+
+                Ok(Value::List(vec![
+                    Value::Number(a[1] * b[2] - a[2] * b[1]),
+                    Value::Number(a[2] * b[0] - a[0] * b[2]),
+                    Value::Number(a[0] * b[1] - a[1] * b[0]),
+                ]))
+            }),
+        ]
+    }
+}
+
+impl fmt::Display for LinalgFn {
     fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
-        write!(f, "<fnc:sin>")
+        write!(f, "<fnc:{}>", self.name)
     }
 }
 
-impl Callable for Sin {
-    fn call(&self, _: &mut Interpreter, args: Vec<Value>) -> Value {
-        let arg = &args[0];
+impl Callable for LinalgFn {
+    fn call(&self, _: &mut Interpreter, args: Vec<Value>) -> Result<Value, MatikaError> {
+        (self.apply)(&args)
+    }
 
-        if let Value::Number(num) = arg {
-            Value::Number(num.sin())
-        } else {
-            panic!()
+    fn arity(&self) -> u64 {
+        self.arity
+    }
+}
+
+/// Ranges and sigma sums longer than this are refused instead of exhausting memory.
+const MAX_RANGE: usize = 10_000_000;
+
+/// A builtin working on lists, most of them taking a function to apply. Calls
+/// are accepted with any of `arities` arguments.
+#[derive(Debug)]
+pub struct ListFn {
+    pub name: &'static str,
+    arities: &'static [u64],
+    apply: fn(&mut Interpreter, &[Value]) -> Result<Value, MatikaError>,
+}
+
+impl ListFn {
+    fn new(
+        name: &'static str,
+        arities: &'static [u64],
+        apply: fn(&mut Interpreter, &[Value]) -> Result<Value, MatikaError>,
+    ) -> Self {
+        Self {
+            name,
+            arities,
+            apply,
+        }
+    }
+
+    pub fn builtins() -> Vec<ListFn> {
+        vec![
+            ListFn::new("map", &[2], |interpreter, args| {
+                list_arg("map", &args[1])?
+                    .iter()
+                    .map(|value| call_arg(interpreter, "map", &args[0], vec![value.clone()]))
+                    .collect::<Result<Vec<Value>, MatikaError>>()
+                    .map(Value::List)
+            }),
+            ListFn::new("filter", &[2], |interpreter, args| {
+                let mut kept = vec![];
+
+                for value in list_arg("filter", &args[1])?.iter() {
+                    if call_arg(interpreter, "filter", &args[0], vec![value.clone()])?.as_bool()? {
+                        kept.push(value.clone());
+                    }
+                }
+
+                Ok(Value::List(kept))
+            }),
+            ListFn::new("reduce", &[3], |interpreter, args| {
+                list_arg("reduce", &args[1])?
+                    .iter()
+                    .try_fold(args[2].clone(), |acc, value| {
+                        call_arg(interpreter, "reduce", &args[0], vec![acc, value.clone()])
+                    })
+            }),
+            ListFn::new("sum", &[1, 3], |interpreter, args| {
+                if args.len() == 3 {
+                    return sigma(interpreter, args);
+                }
+
+                list_arg("sum", &args[0])?
+                    .iter()
+                    .try_fold(Value::integer(0), |acc, value| {
+                        Value::binary(TokenKind::Plus, acc, value.clone())
+                    })
+            }),
+            ListFn::new("prod", &[1], |_, args| {
+                list_arg("prod", &args[0])?
+                    .iter()
+                    .try_fold(Value::integer(1), |acc, value| {
+                        Value::binary(TokenKind::Star, acc, value.clone())
+                    })
+            }),
+            ListFn::new("range", &[2, 3], |_, args| range(args)),
+        ]
+    }
+}
+
+impl fmt::Display for ListFn {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "<fnc:{}>", self.name)
+    }
+}
+
+impl Callable for ListFn {
+    fn call(&self, interpreter: &mut Interpreter, args: Vec<Value>) -> Result<Value, MatikaError> {
+        (self.apply)(interpreter, &args)
+    }
+
+    fn arity(&self) -> u64 {
+        self.arities[0]
+    }
+
+    fn accepts(&self, argc: usize) -> bool {
+        self.arities.contains(&(argc as u64))
+    }
+}
+
+/// A list argument; a matrix is taken as the list of its rows.
+fn list_arg<'a>(name: &str, arg: &'a Value) -> Result<Cow<'a, [Value]>, MatikaError> {
+    match arg {
+        Value::List(values) => Ok(Cow::Borrowed(values)),
+        Value::Matrix(matrix) => Ok(Cow::Owned(Value::rows(matrix))),
+        _ => Err(MatikaError::type_error(format!(
+            "{} expects a list, got {}",
+            name,
+            arg.type_name()
+        ))),
+    }
+}
+
+/// Calls a function passed to a builtin, checking the arity like a call
+/// expression would.
+fn call_arg(
+    interpreter: &mut Interpreter,
+    name: &str,
+    fnc: &Value,
+    args: Vec<Value>,
+) -> Result<Value, MatikaError> {
+    match fnc {
+        Value::Function(fnc) if fnc.accepts(args.len()) => fnc.call(interpreter, args),
+        Value::Function(fnc) => Err(MatikaError::Arity {
+            callee: fnc.label(),
+            expected: fnc.arity(),
+            got: args.len(),
+            span: None,
+        }),
+        _ => Err(MatikaError::type_error(format!(
+            "{} expects a function, got {}",
+            name,
+            fnc.type_name()
+        ))),
+    }
+}
+
+/// `sum(f, a, b)`, the sum of `f(k)` for every integer `k` from `a` to `b`.
+fn sigma(interpreter: &mut Interpreter, args: &[Value]) -> Result<Value, MatikaError> {
+    let from = integer_arg("sum", &args[1])?;
+    let to = integer_arg("sum", &args[2])?;
+
+    if (&to - &from)
+        .to_usize()
+        .is_some_and(|count| count >= MAX_RANGE)
+    {
+        return Err(MatikaError::type_error(format!(
+            "sum over more than {} terms",
+            MAX_RANGE
+        )));
+    }
+
+    let mut total = Value::integer(0);
+    let mut k = from;
+
+    while k <= to {
+        let term = call_arg(
+            interpreter,
+            "sum",
+            &args[0],
+            vec![Value::integer(k.clone())],
+        )?;
+
+        total = Value::binary(TokenKind::Plus, total, term)?;
+        k += 1;
+    }
+
+    Ok(total)
+}
+
+/// `range(a, b, step)` counts from `a` up to but excluding `b`, like slices do.
+/// Exact bounds give exact elements.
+fn range(args: &[Value]) -> Result<Value, MatikaError> {
+    let (start, end) = (&args[0], &args[1]);
+    let step = args.get(2).cloned().unwrap_or_else(|| Value::integer(1));
+
+    if step.compare(&Value::integer(0))? == Ordering::Equal {
+        return Err(MatikaError::type_error("range step cannot be zero"));
+    }
+
+    let count = match ((end.clone() - start.clone())? / step.clone())? {
+        Value::Rational(count) => count.ceil().to_integer().to_i64(),
+        Value::Number(count) if count.is_finite() => Some(count.ceil() as i64),
+        _ => None,
+    }
+    .ok_or_else(|| MatikaError::type_error("range bounds must be finite numbers"))?
+    .max(0) as usize;
+
+    if count > MAX_RANGE {
+        return Err(MatikaError::type_error(format!(
+            "range of {} elements is longer than the limit of {}",
+            count, MAX_RANGE
+        )));
+    }
+
+    (0..count)
+        .map(|k| start.clone() + (Value::integer(k) * step.clone())?)
+        .collect::<Result<Vec<Value>, MatikaError>>()
+        .map(Value::List)
+}
+
+#[derive(Debug)]
+pub struct Float;
+
+impl fmt::Display for Float {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "<fnc:float>")
+    }
+}
+
+impl Callable for Float {
+    fn call(&self, _: &mut Interpreter, args: Vec<Value>) -> Result<Value, MatikaError> {
+        match &args[0] {
+            Value::Number(_)
+            | Value::Rational(_)
+            | Value::Complex(_)
+            | Value::List(_)
+            | Value::Matrix(_) => Ok(args[0].clone().approx()),
+            arg => Err(MatikaError::type_error(format!(
+                "float expects a number, got {}",
+                arg.type_name()
+            ))),
+        }
+    }
+
+    fn arity(&self) -> u64 {
+        1
+    }
+
+    fn native(&self) -> Option<NativeFn> {
+        Some(Box::new(|args| args[0]))
+    }
+}
+
+#[derive(Debug)]
+pub struct Len;
+
+impl fmt::Display for Len {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "<fnc:len>")
+    }
+}
+
+impl Callable for Len {
+    fn call(&self, _: &mut Interpreter, args: Vec<Value>) -> Result<Value, MatikaError> {
+        match &args[0] {
+            Value::List(values) => Ok(Value::integer(values.len())),
+            Value::Matrix(matrix) => Ok(Value::integer(matrix.rows())),
+            arg => Err(MatikaError::type_error(format!(
+                "len expects a list, got {}",
+                arg.type_name()
+            ))),
+        }
+    }
+
+    fn arity(&self) -> u64 {
+        1
+    }
+}
+
+#[derive(Debug)]
+pub struct Factorial;
+
+impl fmt::Display for Factorial {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "<fnc:factorial>")
+    }
+}
+
+impl Callable for Factorial {
+    fn call(&self, _: &mut Interpreter, args: Vec<Value>) -> Result<Value, MatikaError> {
+        let n = integer_arg("factorial", &args[0])?;
+
+        match n.to_u64() {
+            Some(n) if n <= integer::MAX_FACTORIAL => Ok(Value::integer(integer::factorial(n))),
+            Some(_) => Err(MatikaError::type_error(format!(
+                "factorial of {} is too large",
+                n
+            ))),
+            None => Err(MatikaError::type_error(format!(
+                "factorial expects a non-negative integer, got {}",
+                n
+            ))),
         }
     }
 
@@ -89,20 +729,24 @@ impl fmt::Display for Factors {
 }
 
 impl Callable for Factors {
-    fn call(&self, _: &mut Interpreter, args: Vec<Value>) -> Value {
-        let arg = &args[0];
+    fn call(&self, _: &mut Interpreter, args: Vec<Value>) -> Result<Value, MatikaError> {
+        let n = integer_arg("factors", &args[0])?;
 
-        if let Value::Number(num) = arg {
-            let num = *num as u64;
-            let factors = (1..num + 1)
-                .into_iter()
-                .filter(|&x| num % x == 0)
-                .map(|x| x as f64)
-                .collect::<Vec<f64>>();
+        if !n.is_positive() {
+            return Err(MatikaError::type_error(format!(
+                "factors expects a positive integer, got {}",
+                n
+            )));
+        }
 
-            Value::List(factors)
-        } else {
-            panic!()
+        match integer::divisors(&n) {
+            Some(divisors) => Ok(Value::List(
+                divisors.into_iter().map(Value::integer).collect(),
+            )),
+            None => Err(MatikaError::type_error(format!(
+                "factors could not factor {}",
+                n
+            ))),
         }
     }
 
@@ -111,40 +755,1249 @@ impl Callable for Factors {
     }
 }
 
+/// Sample points used to bracket a root of `f(x) - y` grow geometrically from
+/// `INVERSE_START` up to `INVERSE_LIMIT` on both sides of zero.
+const INVERSE_START: f64 = 1e-3;
+const INVERSE_GROWTH: f64 = 1.5;
+const INVERSE_LIMIT: f64 = 1e9;
+const BISECTION_STEPS: usize = 200;
+
+/// `f + g`, `2 * f` and the like: applies `op` to the results of the function
+/// operands, using any other operand as a constant.
 #[derive(Debug)]
-pub struct Plot;
+pub struct Pointwise {
+    op: TokenKind,
+    left: Value,
+    right: Value,
+    arity: u64,
+}
 
-impl fmt::Display for Plot {
+impl Pointwise {
+    pub fn of(op: TokenKind, left: Value, right: Value) -> Result<Value, MatikaError> {
+        let arity = match (&left, &right) {
+            (Value::Function(f), Value::Function(g)) if f.arity() != g.arity() => {
+                return Err(MatikaError::type_error(format!(
+                    "cannot combine {} and {}, they take {} and {} arguments",
+                    f.label(),
+                    g.label(),
+                    f.arity(),
+                    g.arity()
+                )))
+            }
+            (Value::Function(fnc), _) | (_, Value::Function(fnc)) => fnc.arity(),
+            _ => return Value::binary(op, left, right),
+        };
+
+        Ok(Value::Function(Rc::new(Self {
+            op,
+            left,
+            right,
+            arity,
+        })))
+    }
+
+    fn operand(
+        interpreter: &mut Interpreter,
+        operand: &Value,
+        args: &[Value],
+    ) -> Result<Value, MatikaError> {
+        match operand {
+            Value::Function(fnc) => fnc.call(interpreter, args.to_vec()),
+            value => Ok(value.clone()),
+        }
+    }
+}
+
+impl fmt::Display for Pointwise {
     fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
-        write!(f, "<fnc:plot>")
+        write!(
+            f,
+            "<fnc:{} {} {}>",
+            describe(&self.left),
+            self.op,
+            describe(&self.right)
+        )
     }
 }
 
-impl Callable for Plot {
-    fn call(&self, interpreter: &mut Interpreter, args: Vec<Value>) -> Value {
-        let arg = &args[0];
+impl Callable for Pointwise {
+    fn call(&self, interpreter: &mut Interpreter, args: Vec<Value>) -> Result<Value, MatikaError> {
+        let left = Self::operand(interpreter, &self.left, &args)?;
+        let right = Self::operand(interpreter, &self.right, &args)?;
 
-        if let Value::Function(fnc) = arg {
-            let mut points: Vec<(f32, f32)> = vec![];
+        Value::binary(self.op, left, right)
+    }
 
-            for x in -10..10 {
-                let y: f64 = fnc.call(interpreter, vec![Value::Number(x as f64)]).into();
+    fn arity(&self) -> u64 {
+        self.arity
+    }
 
-                points.push((x as f32, y as f32))
-            }
+    fn formula(&self, args: &[Expr]) -> Option<Expr> {
+        Some(Expr::Binary {
+            left: Box::new(applied(&self.left, args)?),
+            op: Token::synthetic(self.op, &self.op.to_string(), Span::default()),
+            right: Box::new(applied(&self.right, args)?),
+        })
+    }
+}
 
-            interpreter
-                .plotter
-                .as_ref()
-                .map(|plotter| plotter.plot(points));
-        } else {
-            panic!("Not a function");
+/// `f ∘ g`, calling `g` with the arguments and `f` with its result.
+#[derive(Debug)]
+pub struct Composition {
+    outer: Rc<dyn Callable>,
+    inner: Rc<dyn Callable>,
+}
+
+impl Composition {
+    pub fn of(outer: Value, inner: Value) -> Result<Value, MatikaError> {
+        match (outer, inner) {
+            (Value::Function(outer), Value::Function(inner)) => {
+                if !outer.accepts(1) {
+                    return Err(MatikaError::type_error(format!(
+                        "cannot compose {}, it does not take a single argument",
+                        outer.label()
+                    )));
+                }
+
+                Ok(Value::Function(Rc::new(Self { outer, inner })))
+            }
+            (outer, inner) => Err(MatikaError::type_error(format!(
+                "can only compose functions, got {} and {}",
+                outer.type_name(),
+                inner.type_name()
+            ))),
         }
+    }
+}
+
+impl fmt::Display for Composition {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "<fnc:{} ∘ {}>",
+            describe(&Value::Function(self.outer.clone())),
+            describe(&Value::Function(self.inner.clone()))
+        )
+    }
+}
+
+impl Callable for Composition {
+    fn call(&self, interpreter: &mut Interpreter, args: Vec<Value>) -> Result<Value, MatikaError> {
+        let inner = self.inner.call(interpreter, args)?;
 
-        Value::Number(0.0)
+        self.outer.call(interpreter, vec![inner])
     }
 
     fn arity(&self) -> u64 {
-        1
+        self.inner.arity()
+    }
+
+    fn accepts(&self, argc: usize) -> bool {
+        self.inner.accepts(argc)
+    }
+
+    fn formula(&self, args: &[Expr]) -> Option<Expr> {
+        let inner = applied(&Value::Function(self.inner.clone()), args)?;
+
+        applied(&Value::Function(self.outer.clone()), &[inner])
+    }
+}
+
+#[derive(Debug)]
+pub struct Compose;
+
+impl fmt::Display for Compose {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "<fnc:compose>")
+    }
+}
+
+impl Callable for Compose {
+    fn call(&self, _: &mut Interpreter, mut args: Vec<Value>) -> Result<Value, MatikaError> {
+        let inner = args.remove(1);
+
+        Composition::of(args.remove(0), inner)
+    }
+
+    fn arity(&self) -> u64 {
+        2
+    }
+}
+
+/// `f^-1`, which finds `x` with `f(x) = y` numerically: it walks outwards from
+/// zero until `f(x) - y` changes sign, then bisects. When `f` is not monotonic
+/// this is the root closest to zero, not necessarily the one wanted.
+#[derive(Debug)]
+pub struct Inverse {
+    fnc: Rc<dyn Callable>,
+}
+
+impl Inverse {
+    pub fn of(fnc: Rc<dyn Callable>) -> Result<Value, MatikaError> {
+        if !fnc.accepts(1) {
+            return Err(MatikaError::type_error(format!(
+                "cannot invert {}, it does not take a single argument",
+                fnc.label()
+            )));
+        }
+
+        Ok(Value::Function(Rc::new(Self { fnc })))
+    }
+
+    fn solve(&self, interpreter: &mut Interpreter, y: f64) -> Result<Option<f64>, MatikaError> {
+        let origin = self.residual(interpreter, 0.0, y)?;
+
+        if origin == 0.0 {
+            return Ok(Some(0.0));
+        }
+
+        let mut sides = [(0.0, origin), (0.0, origin)];
+        let mut step = INVERSE_START;
+
+        while step <= INVERSE_LIMIT {
+            for (side, sign) in sides.iter_mut().zip([1.0, -1.0]) {
+                let x = sign * step;
+                let residual = self.residual(interpreter, x, y)?;
+
+                if residual == 0.0 {
+                    return Ok(Some(x));
+                }
+
+                if crosses(side.1, residual) {
+                    if let Some(root) = self.bisect(interpreter, *side, (x, residual), y)? {
+                        return Ok(Some(root));
+                    }
+                }
+
+                *side = (x, residual);
+            }
+
+            step *= INVERSE_GROWTH;
+        }
+
+        Ok(None)
+    }
+
+    /// Narrows a sign change down to adjacent floats. Returns `None` when the
+    /// change turns out to be a pole rather than a root.
+    fn bisect(
+        &self,
+        interpreter: &mut Interpreter,
+        mut low: (f64, f64),
+        mut high: (f64, f64),
+        y: f64,
+    ) -> Result<Option<f64>, MatikaError> {
+        for _ in 0..BISECTION_STEPS {
+            let mid = low.0 + (high.0 - low.0) / 2.0;
+
+            if mid == low.0 || mid == high.0 {
+                break;
+            }
+
+            let residual = self.residual(interpreter, mid, y)?;
+
+            if residual == 0.0 {
+                return Ok(Some(mid));
+            }
+
+            if crosses(low.1, residual) {
+                high = (mid, residual);
+            } else {
+                low = (mid, residual);
+            }
+        }
+
+        let (x, residual) = if low.1.abs() <= high.1.abs() {
+            low
+        } else {
+            high
+        };
+
+        Ok(if residual.abs() <= 1e-6 * (1.0 + y.abs()) {
+            Some(x)
+        } else {
+            None
+        })
+    }
+
+    /// `f(x) - y`, or NaN where `f` has no real value.
+    fn residual(&self, interpreter: &mut Interpreter, x: f64, y: f64) -> Result<f64, MatikaError> {
+        let fx = self.fnc.call(interpreter, vec![Value::Number(x)])?;
+
+        Ok(fx.as_f64().map_or(f64::NAN, |fx| fx - y))
+    }
+}
+
+fn crosses(a: f64, b: f64) -> bool {
+    a.is_finite() && b.is_finite() && a.signum() != b.signum()
+}
+
+impl fmt::Display for Inverse {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "<fnc:{}^-1>",
+            describe(&Value::Function(self.fnc.clone()))
+        )
+    }
+}
+
+impl Callable for Inverse {
+    fn call(&self, interpreter: &mut Interpreter, args: Vec<Value>) -> Result<Value, MatikaError> {
+        let y = args[0].as_f64().ok_or_else(|| {
+            MatikaError::type_error(format!(
+                "{} expects a real number, got {}",
+                self,
+                args[0].type_name()
+            ))
+        })?;
+
+        match self.solve(interpreter, y)? {
+            Some(x) => Ok(Value::Number(x)),
+            None => Err(MatikaError::type_error(format!(
+                "{} has no value at {}",
+                self, y
+            ))),
+        }
+    }
+
+    fn arity(&self) -> u64 {
+        1
+    }
+}
+
+/// An operand of a combined function as it appears in its name, so `f + 1`
+/// prints as `<fnc:f + 1>` rather than nesting `<fnc:...>`.
+fn describe(value: &Value) -> String {
+    let text = match value {
+        Value::Function(fnc) => fnc.label(),
+        _ => value.to_string(),
+    };
+
+    match value {
+        Value::Function(_) => text
+            .strip_prefix("<fnc:")
+            .and_then(|text| text.strip_suffix('>'))
+            .map(|name| {
+                if name.contains(' ') {
+                    format!("({})", name)
+                } else {
+                    name.to_string()
+                }
+            })
+            .unwrap_or(text),
+        _ => text,
+    }
+}
+
+/// An operand of a combined function applied to `args` as an expression:
+/// named functions are called by name and constants stay as they are.
+fn applied(value: &Value, args: &[Expr]) -> Option<Expr> {
+    let fnc = match value {
+        Value::Function(fnc) => fnc,
+        value => return symbolic::to_expr(value).ok(),
+    };
+
+    if let Some(formula) = fnc.formula(args) {
+        return Some(formula);
+    }
+
+    let name = describe(value);
+
+    if !name.chars().all(|c| c.is_alphanumeric() || c == '_') {
+        return None;
+    }
+
+    Some(Expr::Call {
+        callee: Box::new(Expr::Variable {
+            name: Token::synthetic(TokenKind::Identifier, &name, Span::default()),
+            depth: None,
+        }),
+        args: args.to_vec(),
+        span: Span::default(),
+    })
+}
+
+/// `d(f)` differentiates `f` by its first parameter, `derivative(f, y)` by the
+/// parameter named `y`. Builtins like `sin` are differentiated as `x -> sin(x)`,
+/// and combined functions like `p + sin` as `x -> p(x) + sin(x)`.
+#[derive(Debug)]
+pub struct Derivative {
+    pub name: &'static str,
+    arity: u64,
+}
+
+impl Derivative {
+    pub fn builtins() -> Vec<Derivative> {
+        vec![
+            Derivative {
+                name: "d",
+                arity: 1,
+            },
+            Derivative {
+                name: "derivative",
+                arity: 2,
+            },
+        ]
+    }
+}
+
+impl fmt::Display for Derivative {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "<fnc:{}>", self.name)
+    }
+}
+
+impl Callable for Derivative {
+    fn call(&self, interpreter: &mut Interpreter, args: Vec<Value>) -> Result<Value, MatikaError> {
+        let fnc = match &args[0] {
+            Value::Function(fnc) => fnc,
+            arg => {
+                return Err(MatikaError::type_error(format!(
+                    "{} expects a function, got {}",
+                    self.name,
+                    arg.type_name()
+                )))
+            }
+        };
+
+        let builtin;
+        let function = match fnc.as_function() {
+            Some(function) => function,
+            None => {
+                let call = Fnc::call(&describe(&args[0]), fnc.arity());
+                let formula = match &call.body {
+                    Expr::Call { args, .. } => fnc.formula(args),
+                    _ => None,
+                };
+                let clause = match formula {
+                    Some(body) => Fnc { body, ..call },
+                    None => call,
+                };
+
+                builtin = Function::lambda(clause, interpreter.environment.clone());
+
+                &builtin
+            }
+        };
+
+        let position = |names: &[String]| {
+            let mut found = function.clauses[0].params.iter().enumerate().filter_map(
+                |(idx, param)| match param {
+                    Pattern::Binding(token) if names.contains(&token.lexeme) => Some(idx),
+                    _ => None,
+                },
+            );
+
+            match (found.next(), found.next()) {
+                (Some(param), None) => Some(param),
+                _ => None,
+            }
+        };
+
+        let param = match args.get(1) {
+            None => 0,
+            Some(Value::Unbound(name)) => {
+                position(std::slice::from_ref(name)).ok_or_else(|| {
+                    MatikaError::type_error(format!("{} has no parameter {}", fnc.label(), name))
+                })?
+            }
+            // A parameter name bound in the session is passed as its value.
+            Some(arg) => position(&interpreter.environment.bound_to(arg)).ok_or_else(|| {
+                MatikaError::type_error(format!(
+                    "{} expects a parameter name, got {}{}",
+                    self.name,
+                    arg,
+                    shadowing(&interpreter.environment, arg)
+                ))
+            })?,
+        };
+
+        derivative::differentiate(function, param)
+            .map(|derivative| Value::Function(Rc::new(derivative)))
+    }
+
+    fn arity(&self) -> u64 {
+        self.arity
+    }
+
+    fn accepts(&self, argc: usize) -> bool {
+        argc == 1 || argc == 2
+    }
+}
+
+/// A computer algebra builtin, working on expressions over unbound names.
+/// Calls are accepted with any of `arities` arguments.
+#[derive(Debug)]
+pub struct AlgebraFn {
+    pub name: &'static str,
+    arities: &'static [u64],
+    apply: fn(&mut Interpreter, &[Value]) -> Result<Value, MatikaError>,
+}
+
+impl AlgebraFn {
+    fn new(
+        name: &'static str,
+        arities: &'static [u64],
+        apply: fn(&mut Interpreter, &[Value]) -> Result<Value, MatikaError>,
+    ) -> Self {
+        Self {
+            name,
+            arities,
+            apply,
+        }
+    }
+
+    pub fn builtins() -> Vec<AlgebraFn> {
+        vec![
+            AlgebraFn::new("simplify", &[1], |_, args| {
+                rewrite("simplify", &args[0], simplify)
+            }),
+            AlgebraFn::new("expand", &[1], |_, args| {
+                rewrite("expand", &args[0], expand)
+            }),
+            AlgebraFn::new("factor", &[1], |_, args| {
+                rewrite("factor", &args[0], factor)
+            }),
+            AlgebraFn::new("subs", &[3], |interpreter, args| match &args[1] {
+                Value::Unbound(symbol) => {
+                    symbolic::substitute(interpreter, &args[0], symbol, args[2].clone())
+                }
+                arg => Err(MatikaError::type_error(format!(
+                    "subs expects an unbound name to replace, got {}",
+                    arg
+                ))),
+            }),
+            AlgebraFn::new("solve", &[1, 2], |interpreter, args| {
+                match (args[0].to_matrix(), &args[0]) {
+                    (Some(matrix), _) => solve::matrix(&matrix, args.get(1)),
+                    (None, Value::List(equations)) => {
+                        solve::system(interpreter, equations, args.get(1))
+                    }
+                    (None, target) => solve::solve(interpreter, target, args.get(1)),
+                }
+            }),
+            AlgebraFn::new("roots", &[1, 2], |interpreter, args| {
+                solve::roots(interpreter, &args[0], args.get(1))
+            }),
+        ]
+    }
+}
+
+/// Applies `rewrite` to an expression, or to the bodies of a user function.
+/// Numbers and builtins are returned unchanged.
+fn rewrite(name: &str, value: &Value, rewrite: fn(&Expr) -> Expr) -> Result<Value, MatikaError> {
+    match value {
+        Value::Function(fnc) => match fnc.as_function() {
+            Some(function) => {
+                let mut clauses: Vec<Fnc> = function
+                    .clauses
+                    .iter()
+                    .map(|clause| Fnc {
+                        body: rewrite(&clause.body),
+                        ..clause.clone()
+                    })
+                    .collect();
+
+                // A single formula is returned as one, so that it prints as such.
+                let rewritten = if clauses.len() == 1 {
+                    Function::lambda(clauses.remove(0), function.closure.clone())
+                } else {
+                    Function::new(clauses, function.closure.clone())
+                };
+
+                Ok(Value::Function(Rc::new(rewritten)))
+            }
+            None => Ok(value.clone()),
+        },
+        Value::Unbound(_) | Value::Symbolic(_) => {
+            Ok(symbolic::from_expr(rewrite(&symbolic::to_expr(value)?)))
+        }
+        Value::Equation(left, right) => Ok(Value::Equation(
+            Rc::new(rewrite(left)),
+            Rc::new(rewrite(right)),
+        )),
+        Value::Number(_) | Value::Rational(_) => Ok(value.clone()),
+        value => Err(MatikaError::type_error(format!(
+            "{} expects an expression or a function, got {}",
+            name,
+            value.type_name()
+        ))),
+    }
+}
+
+impl fmt::Display for AlgebraFn {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "<fnc:{}>", self.name)
+    }
+}
+
+impl Callable for AlgebraFn {
+    fn call(&self, interpreter: &mut Interpreter, args: Vec<Value>) -> Result<Value, MatikaError> {
+        (self.apply)(interpreter, &args)
+    }
+
+    fn arity(&self) -> u64 {
+        self.arities[0]
+    }
+
+    fn accepts(&self, argc: usize) -> bool {
+        self.arities.contains(&(argc as u64))
+    }
+}
+
+/// `integrate` halves the interval with the largest error estimate until the
+/// estimates add up to at most `INTEGRATION_TOLERANCE` times the integral of
+/// `|f|`, and gives up after `MAX_SUBDIVISIONS` intervals.
+const INTEGRATION_TOLERANCE: f64 = 1e-12;
+const MAX_SUBDIVISIONS: usize = 1000;
+
+/// Nodes of the 15 point Kronrod rule on `[-1, 1]`, the odd ones shared with
+/// the 7 point Gauss rule, and the weights of both.
+const KRONROD_NODES: [f64; 8] = [
+    0.991_455_371_120_812_6,
+    0.949_107_912_342_758_5,
+    0.864_864_423_359_769_1,
+    0.741_531_185_599_394_4,
+    0.586_087_235_467_691_1,
+    0.405_845_151_377_397_2,
+    0.207_784_955_007_898_5,
+    0.0,
+];
+const KRONROD_WEIGHTS: [f64; 8] = [
+    0.022_935_322_010_529_22,
+    0.063_092_092_629_978_55,
+    0.104_790_010_322_250_2,
+    0.140_653_259_715_525_9,
+    0.169_004_726_639_267_9,
+    0.190_350_578_064_785_4,
+    0.204_432_940_075_298_9,
+    0.209_482_141_084_727_8,
+];
+const GAUSS_WEIGHTS: [f64; 4] = [
+    0.129_484_966_168_869_7,
+    0.279_705_391_489_276_7,
+    0.381_830_050_505_118_9,
+    0.417_959_183_673_469_4,
+];
+
+/// `integrate(f, a, b)`, the integral of `f` from `a` to `b` by adaptive
+/// Gauss-Kronrod quadrature, each interval's error estimated from the Gauss
+/// rule embedded in it. Infinite bounds are first substituted away, mapping
+/// the range onto a finite interval, and singularities at the ends of that
+/// interval are fitted and integrated exactly. Fails instead of returning an
+/// inaccurate result when the estimate does not get small enough.
+/// `quad(f, a, b)` integrates the same way but returns `[integral, error]`,
+/// the error being the sum of the intervals' estimates.
+#[derive(Debug)]
+pub struct Integrate {
+    pub name: &'static str,
+    with_error: bool,
+}
+
+/// A piece of the interval `integrate` works on, in the substituted variable.
+struct Segment {
+    low: f64,
+    high: f64,
+    integral: f64,
+    error: f64,
+    magnitude: f64,
+}
+
+/// `c/|t - at|^p`, fitted to an integrand that is infinite at the end `at` of
+/// the range integrated over.
+struct Singularity {
+    at: f64,
+    c: f64,
+    p: f64,
+    /// The exact integral of the fit over the range, and how far rounding in
+    /// the fitted `p` can move the result.
+    integral: f64,
+    error: f64,
+}
+
+/// What `integrate` evaluates over `a < b`: `f(x) dx/dt` in the substituted
+/// variable `t`, less the singularities fitted at the ends of its range.
+/// Their integrals are added back exactly, so the quadrature only sees what
+/// is left, which stays finite even where rounding puts nodes on an end.
+struct Integrand<'a> {
+    fnc: &'a Value,
+    a: f64,
+    b: f64,
+    singularities: Vec<Singularity>,
+}
+
+impl Integrate {
+    pub fn builtins() -> Vec<Integrate> {
+        vec![
+            Integrate {
+                name: "integrate",
+                with_error: false,
+            },
+            Integrate {
+                name: "quad",
+                with_error: true,
+            },
+        ]
+    }
+
+    fn bound(&self, arg: &Value) -> Result<f64, MatikaError> {
+        arg.as_f64().filter(|bound| !bound.is_nan()).ok_or_else(|| {
+            MatikaError::type_error(format!(
+                "{} expects a real bound, got {}",
+                self.name,
+                arg.type_name()
+            ))
+        })
+    }
+
+    /// The interval integrated over once infinite bounds are substituted away.
+    fn range(a: f64, b: f64) -> (f64, f64) {
+        match (a.is_finite(), b.is_finite()) {
+            (true, true) => (a, b),
+            (false, false) => (-1.0, 1.0),
+            _ => (0.0, 1.0),
+        }
+    }
+
+    /// `x` and `dx/dt` at `t`, with `x = a + t/(1 - t)` for an infinite upper
+    /// bound, `x = b - (1 - t)/t` for an infinite lower one and
+    /// `x = t/(1 - t^2)` when both are.
+    fn substitute(a: f64, b: f64, t: f64) -> (f64, f64) {
+        match (a.is_finite(), b.is_finite()) {
+            (true, true) => (t, 1.0),
+            (true, false) => (a + t / (1.0 - t), 1.0 / ((1.0 - t) * (1.0 - t))),
+            (false, true) => (b - (1.0 - t) / t, 1.0 / (t * t)),
+            (false, false) => {
+                let s = 1.0 - t * t;
+
+                (t / s, (1.0 + t * t) / (s * s))
+            }
+        }
+    }
+
+    /// `f(x)`, which must be a finite real number.
+    fn sample(interpreter: &mut Interpreter, fnc: &Value, x: f64) -> Result<f64, MatikaError> {
+        let y = call_arg(interpreter, "integrate", fnc, vec![Value::Number(x)])?;
+
+        match y.as_f64() {
+            Some(y) if y.is_finite() => Ok(y),
+            Some(_) => Err(MatikaError::type_error(format!(
+                "cannot integrate {}, it is not finite at {}",
+                describe(fnc),
+                x
+            ))),
+            None => Err(MatikaError::type_error(format!(
+                "cannot integrate {}, it has no real value at {}",
+                describe(fnc),
+                x
+            ))),
+        }
+    }
+
+    /// `f(x) dx/dt` at `t`, or `None` at an infinite bound, where the
+    /// substitution has no value.
+    fn substituted(
+        interpreter: &mut Interpreter,
+        integrand: &Integrand,
+        t: f64,
+    ) -> Result<Option<f64>, MatikaError> {
+        let (x, slope) = Self::substitute(integrand.a, integrand.b, t);
+
+        if !x.is_finite() || !slope.is_finite() {
+            return Ok(None);
+        }
+
+        let y = Self::sample(
+            interpreter,
+            integrand.fnc,
+            x.clamp(integrand.a, integrand.b),
+        )?;
+
+        Ok(Some(y * slope))
+    }
+
+    /// Fits `c/r^p` to the integrand at the distances `r` of one, two and
+    /// four machine epsilons from the end `at` of the range, towards
+    /// `inward`. Where `at` is a finite bound, `f` must be infinite there.
+    /// Where it is an infinite one, both pairs of points must agree on `p`,
+    /// which rules out oscillating integrands. An integrand growing at least
+    /// as fast as `1/r` does not have a finite integral.
+    fn singularity(
+        interpreter: &mut Interpreter,
+        integrand: &Integrand,
+        at: f64,
+        inward: f64,
+    ) -> Result<Option<Singularity>, MatikaError> {
+        let (x, slope) = Self::substitute(integrand.a, integrand.b, at);
+        let finite = x.is_finite() && slope.is_finite();
+
+        if finite {
+            let infinite = call_arg(
+                interpreter,
+                "integrate",
+                integrand.fnc,
+                vec![Value::Number(x)],
+            )
+            .ok()
+            .and_then(|y| y.as_f64())
+            .is_some_and(f64::is_infinite);
+
+            if !infinite {
+                return Ok(None);
+            }
+        }
+
+        let step = f64::EPSILON * at.abs().max(1.0) * (inward - at).signum();
+        let mut fit = vec![];
+
+        for steps in [1.0, 2.0, 4.0] {
+            let t = at + steps * step;
+
+            match Self::substituted(interpreter, integrand, t) {
+                Ok(Some(y)) => fit.push(((t - at).abs(), y)),
+                Err(err) if finite => return Err(err),
+                _ => return Ok(None),
+            }
+        }
+
+        let power = |(near, y): (f64, f64), (far, z): (f64, f64)| (y / z).ln() / (far / near).ln();
+        let (p, q) = (power(fit[0], fit[1]), power(fit[1], fit[2]));
+
+        let agree = (p - q).abs().partial_cmp(&1e-3) == Some(Ordering::Less);
+
+        if !finite && !agree {
+            return Ok(None);
+        }
+
+        if p >= 1.0 {
+            let reason = if finite {
+                format!("it grows too fast towards {}", x)
+            } else {
+                format!(
+                    "it does not vanish fast enough towards {}",
+                    x.signum() * f64::INFINITY
+                )
+            };
+
+            return Err(MatikaError::type_error(format!(
+                "integral of {} does not converge, {}",
+                describe(integrand.fnc),
+                reason
+            )));
+        }
+
+        let (near, y) = fit[0];
+        let length = (inward - at).abs();
+        let c = y * near.powf(p);
+        let integral = c * length.powf(1.0 - p) / (1.0 - p);
+        // `p` comes from a ratio of two samples, each rounded by about an
+        // epsilon, and `c` and the integral both scale with powers of it.
+        let sensitivity = (near / length).ln().abs() + 1.0 / (1.0 - p);
+        let error = integral.abs() * sensitivity * 4.0 * f64::EPSILON / 2f64.ln();
+
+        Ok(Some(Singularity {
+            at,
+            c,
+            p,
+            integral,
+            error,
+        })
+        .filter(|_| p > 0.0))
+    }
+
+    fn integrand(
+        interpreter: &mut Interpreter,
+        integrand: &Integrand,
+        t: f64,
+    ) -> Result<f64, MatikaError> {
+        // What is left of the integrand at a singular end is bounded, and a
+        // node rounded onto the end only weighs as much as its tiny interval.
+        if integrand.singularities.iter().any(|s| s.at == t) {
+            return Ok(0.0);
+        }
+
+        // Rounding can also push nodes next to an infinite bound onto it,
+        // where the substituted integrand vanishes for any convergent integral.
+        let y = match Self::substituted(interpreter, integrand, t)? {
+            Some(y) => y,
+            None => return Ok(0.0),
+        };
+        let fitted: f64 = integrand
+            .singularities
+            .iter()
+            .map(|s| s.c / (t - s.at).abs().powf(s.p))
+            .sum();
+
+        Ok(y - fitted)
+    }
+
+    fn segment(
+        interpreter: &mut Interpreter,
+        integrand: &Integrand,
+        low: f64,
+        high: f64,
+    ) -> Result<Segment, MatikaError> {
+        let center = low + (high - low) / 2.0;
+        let half = (high - low) / 2.0;
+        let mut kronrod = 0.0;
+        let mut gauss = 0.0;
+        let mut magnitude = 0.0;
+
+        for (i, (node, weight)) in KRONROD_NODES.iter().zip(KRONROD_WEIGHTS).enumerate() {
+            let points = if *node == 0.0 {
+                vec![center]
+            } else {
+                vec![center - half * node, center + half * node]
+            };
+
+            for t in points {
+                let y = Self::integrand(interpreter, integrand, t)?;
+
+                kronrod += weight * y;
+                magnitude += weight * y.abs();
+
+                if i % 2 == 1 {
+                    gauss += GAUSS_WEIGHTS[i / 2] * y;
+                }
+            }
+        }
+
+        Ok(Segment {
+            low,
+            high,
+            integral: kronrod * half,
+            error: ((kronrod - gauss) * half).abs(),
+            magnitude: magnitude * half.abs(),
+        })
+    }
+
+    /// The integral over `a < b` and its error estimate.
+    fn integrate(
+        interpreter: &mut Interpreter,
+        fnc: &Value,
+        a: f64,
+        b: f64,
+    ) -> Result<(f64, f64), MatikaError> {
+        let (low, high) = Self::range(a, b);
+        let mut integrand = Integrand {
+            fnc,
+            a,
+            b,
+            singularities: vec![],
+        };
+
+        for (at, inward) in [(low, high), (high, low)] {
+            if let Some(singularity) = Self::singularity(interpreter, &integrand, at, inward)? {
+                integrand.singularities.push(singularity);
+            }
+        }
+
+        let exact: f64 = integrand.singularities.iter().map(|s| s.integral).sum();
+        let fitted: f64 = integrand.singularities.iter().map(|s| s.error).sum();
+        let mut segments = vec![Self::segment(interpreter, &integrand, low, high)?];
+
+        loop {
+            let integral: f64 = segments.iter().map(|segment| segment.integral).sum();
+            let error: f64 = segments.iter().map(|segment| segment.error).sum();
+            let magnitude: f64 = segments.iter().map(|segment| segment.magnitude).sum();
+
+            if error + fitted <= INTEGRATION_TOLERANCE * (magnitude + exact.abs()) {
+                // Rounding the sum alone costs about an epsilon of it.
+                let rounding = f64::EPSILON * (magnitude + exact.abs());
+
+                return Ok((integral + exact, error + fitted + rounding));
+            }
+
+            let worst = segments
+                .iter()
+                .enumerate()
+                .max_by(|(_, a), (_, b)| a.error.partial_cmp(&b.error).unwrap_or(Ordering::Equal))
+                .map_or(0, |(i, _)| i);
+            let Segment { low, high, .. } = segments[worst];
+            let mid = low + (high - low) / 2.0;
+
+            if segments.len() >= MAX_SUBDIVISIONS || mid <= low || mid >= high {
+                return Err(MatikaError::type_error(format!(
+                    "integral of {} did not converge, its error estimate is still {} after {} subdivisions",
+                    describe(fnc),
+                    error,
+                    segments.len()
+                )));
+            }
+
+            segments[worst] = Self::segment(interpreter, &integrand, low, mid)?;
+            segments.push(Self::segment(interpreter, &integrand, mid, high)?);
+        }
+    }
+}
+
+impl fmt::Display for Integrate {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "<fnc:{}>", self.name)
+    }
+}
+
+impl Callable for Integrate {
+    fn call(&self, interpreter: &mut Interpreter, args: Vec<Value>) -> Result<Value, MatikaError> {
+        let a = self.bound(&args[1])?;
+        let b = self.bound(&args[2])?;
+
+        let (integral, error) = match a.partial_cmp(&b) {
+            Some(Ordering::Less) => Self::integrate(interpreter, &args[0], a, b)?,
+            Some(Ordering::Greater) => {
+                let (integral, error) = Self::integrate(interpreter, &args[0], b, a)?;
+
+                (-integral, error)
+            }
+            _ => (0.0, 0.0),
+        };
+
+        if self.with_error {
+            Ok(Value::List(vec![
+                Value::Number(integral),
+                Value::Number(error),
+            ]))
+        } else {
+            Ok(Value::Number(integral))
+        }
+    }
+
+    fn arity(&self) -> u64 {
+        3
+    }
+}
+
+#[derive(Debug)]
+pub struct Plot;
+
+impl fmt::Display for Plot {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "<fnc:plot>")
+    }
+}
+
+impl Callable for Plot {
+    fn call(&self, interpreter: &mut Interpreter, args: Vec<Value>) -> Result<Value, MatikaError> {
+        let arg = &args[0];
+
+        if let Value::Function(fnc) = arg {
+            let mut points: Vec<(f32, f32)> = vec![];
+
+            for x in -10..10 {
+                let y: f64 = fnc.call(interpreter, vec![Value::Number(x as f64)])?.into();
+
+                points.push((x as f32, y as f32))
+            }
+
+            if let Some(plotter) = interpreter.plotter.as_ref() {
+                plotter.plot(points);
+            }
+        } else {
+            return Err(MatikaError::NotCallable {
+                name: arg.to_string(),
+                span: None,
+            });
+        }
+
+        Ok(Value::Number(0.0))
+    }
+
+    fn arity(&self) -> u64 {
+        1
+    }
+}
+
+/// Names the session bindings `value` may have come from, for errors about
+/// arguments meant as unbound names.
+pub fn shadowing(env: &Environment, value: &Value) -> String {
+    match env.bound_to(value).as_slice() {
+        [] => String::new(),
+        names => format!(", the value of {}", names.join(", ")),
+    }
+}
+
+fn integer_arg(name: &str, arg: &Value) -> Result<BigInt, MatikaError> {
+    arg.as_integer().ok_or_else(|| {
+        let got = match arg.as_f64() {
+            Some(_) => arg.to_string(),
+            None => arg.type_name().to_string(),
+        };
+
+        MatikaError::type_error(format!("{} expects an integer, got {}", name, got))
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::tests::{eval, session, value};
+    use crate::Value;
+
+    /// The integral and error estimate returned by `quad`.
+    fn quad(src: &str) -> (f64, f64) {
+        match value(src) {
+            Value::List(values) => (values[0].as_f64().unwrap(), values[1].as_f64().unwrap()),
+            value => panic!("expected a list, got {}", value),
+        }
+    }
+
+    /// The value of an `integrate` call.
+    fn integral(src: &str) -> f64 {
+        value(src).as_f64().unwrap()
+    }
+
+    #[test]
+    fn quad_returns_the_error_estimate() {
+        let (integral, error) = quad("quad(sin, 0, 1)");
+
+        assert_eq!(value("integrate(sin, 0, 1)").as_f64(), Some(integral));
+        assert!((integral - (1.0 - 1f64.cos())).abs() < 1e-14);
+        assert!((0.0..1e-12).contains(&error));
+    }
+
+    #[test]
+    fn quad_over_reversed_and_empty_ranges() {
+        let (integral, error) = quad("quad(sin, 0, 1)");
+
+        assert_eq!(quad("quad(sin, 1, 0)"), (-integral, error));
+        assert_eq!(quad("quad(sin, 2, 2)"), (0.0, 0.0));
+    }
+
+    #[test]
+    fn factors_of_positive_integers() {
+        assert_eq!(eval("factors(12)"), "[1, 2, 3, 4, 6, 12]");
+        assert_eq!(eval("factors(1)"), "[1]");
+    }
+
+    #[test]
+    fn factors_rejects_non_positive_integers() {
+        for src in ["factors(0)", "factors(-6)"] {
+            let err = session(&[src]).unwrap_err();
+
+            assert!(err.contains("expects a positive integer"), "{}", err);
+        }
+    }
+
+    #[test]
+    fn derivative_takes_bound_parameter_names() {
+        let derived = |line| session(&["x = 0.5", "g(x) = x^2", "h(x, y) = x*y", line]);
+
+        assert_eq!(derived("derivative(g, x)").unwrap(), "x -> 2x");
+        assert_eq!(derived("derivative(h, x)").unwrap(), "(x, y) -> y");
+        assert_eq!(derived("derivative(sin, x)").unwrap(), "x -> cos(x)");
+    }
+
+    #[test]
+    fn derivative_reports_ambiguous_bindings() {
+        let err =
+            session(&["x = 0.5", "y = 0.5", "h(x, y) = x*y", "derivative(h, x)"]).unwrap_err();
+
+        assert!(err.contains("got 0.5, the value of x, y"), "{}", err);
+    }
+
+    #[test]
+    fn combined_functions_negate_and_differentiate() {
+        let derived = |line| session(&["p(x) = x^2", line]);
+
+        assert_eq!(derived("-p").unwrap(), "<fnc:-1 * p>");
+        assert_eq!(derived("(-p)(3)").unwrap(), "-9");
+        assert_eq!(derived("d(-p)").unwrap(), "x -> -2x");
+        assert_eq!(derived("d(p + sin)").unwrap(), "x -> cos(x) + 2x");
+        assert_eq!(derived("d(2 * p)").unwrap(), "x -> 4x");
+        assert_eq!(derived("d(p ∘ sin)").unwrap(), "x -> 2cos(x)*sin(x)");
+        assert_eq!(
+            derived("d(p^-1)").unwrap_err(),
+            "type error at line 1, column 1: cannot differentiate p^-1"
+        );
+    }
+
+    #[test]
+    fn named_functions_print_their_definitions() {
+        let fact = ["fact(0) = 1", "fact(n) = n * fact(n - 1)"];
+
+        assert_eq!(session(&["p(x) = x^2", "p"]).unwrap(), "p(x) = x^2");
+        assert_eq!(
+            session(&[fact[0], fact[1], "fact"]).unwrap(),
+            "fact(0) = 1\nfact(n) = n*fact(n - 1)"
+        );
+        assert_eq!(
+            session(&["p(x) = x^2", "p(1, 2)"]).unwrap_err(),
+            "arity mismatch at line 1, column 1: <fnc:p> expects 1 argument(s), got 2"
+        );
+        assert_eq!(session(&["p(x) = x^2", "p + 1"]).unwrap(), "<fnc:p + 1>");
+    }
+
+    #[test]
+    fn integrate_over_endpoint_singularities() {
+        let pi = std::f64::consts::PI;
+
+        for (src, exact) in [
+            ("x -> 1/sqrt(x), 0, 1", 2.0),
+            ("x -> 1/sqrt(1 - x), 0, 1", 2.0),
+            ("x -> x^(-0.9), 0, 1", 10.0),
+            ("x -> (1 - x)^(-0.9), 0, 1", 10.0),
+            ("x -> ln(x), 0, 1", -1.0),
+            ("x -> ln(1 - x), 0, 1", -1.0),
+            ("x -> 1/sqrt(1 - x^2), -1, 1", pi),
+            ("x -> 1/sqrt(x)/(1 + x), 0, inf", pi),
+            ("x -> 1/x^1.5, 1, inf", 2.0),
+        ] {
+            let (integral, error) = quad(&format!("quad({})", src));
+
+            assert!((integral - exact).abs() < 1e-9, "{}: {}", src, integral);
+            assert!(
+                error >= (integral - exact).abs(),
+                "{}: {} off by more than {}",
+                src,
+                integral,
+                error
+            );
+        }
+    }
+
+    #[test]
+    fn integrate_over_finite_ranges() {
+        assert!((integral("integrate(x -> x^2, 0, 3)") - 9.0).abs() < 1e-12);
+        assert!((integral("integrate(cos, 0, pi()/2)") - 1.0).abs() < 1e-12);
+        assert!((integral("integrate(x -> exp(x), -1, 1)") - 2.0 * 1f64.sinh()).abs() < 1e-12);
+        assert!((integral("integrate(x -> x^2, 3, 0)") + 9.0).abs() < 1e-12);
+    }
+
+    #[test]
+    fn integrate_over_infinite_ranges() {
+        let pi = std::f64::consts::PI;
+
+        assert!((integral("integrate(x -> 1/x^2, 1, inf)") - 1.0).abs() < 1e-9);
+        assert!((integral("integrate(x -> exp(x), -inf, 0)") - 1.0).abs() < 1e-9);
+        assert!((integral("integrate(x -> 1/(1 + x^2), -inf, inf)") - pi).abs() < 1e-9);
+        assert!((integral("integrate(x -> exp(-(x^2)), -inf, inf)") - pi.sqrt()).abs() < 1e-9);
+    }
+
+    #[test]
+    fn divergent_integrals_are_errors() {
+        for (src, reason) in [
+            ("integrate(x -> 1/x, 0, 1)", "grows too fast towards 0"),
+            (
+                "integrate(x -> 1/(1 - x), 0, 1)",
+                "grows too fast towards 1",
+            ),
+            (
+                "integrate(x -> 1/x, 1, inf)",
+                "does not vanish fast enough towards inf",
+            ),
+            (
+                "integrate(x -> x^2, -inf, 0)",
+                "does not vanish fast enough towards -inf",
+            ),
+            ("integrate(sin, 0, inf)", "did not converge"),
+        ] {
+            let err = session(&[src]).unwrap_err();
+
+            assert!(err.contains(reason), "{}: {}", src, err);
+        }
+
+        let err = session(&["integrate(x -> [x], 0, 1)"]).unwrap_err();
+
+        assert!(err.contains("no real value"), "{}", err);
     }
 }
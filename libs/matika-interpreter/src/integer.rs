@@ -0,0 +1,148 @@
+use num_bigint::BigInt;
+use num_integer::Integer;
+use num_traits::{One, Signed, Zero};
+
+const SMALL_PRIMES: [u32; 13] = [2, 3, 5, 7, 11, 13, 17, 19, 23, 29, 31, 37, 41];
+
+/// Pollard's rho gives up after this many steps per polynomial, so numbers with
+/// two huge prime factors fail instead of hanging.
+const RHO_STEPS: usize = 1 << 20;
+
+/// Factorials above this are refused rather than computed for minutes.
+pub const MAX_FACTORIAL: u64 = 100_000;
+
+pub fn factorial(n: u64) -> BigInt {
+    (2..=n).fold(BigInt::one(), |product, k| product * k)
+}
+
+/// All positive divisors of `n` in increasing order, or `None` if `n` could not
+/// be factored in reasonable time. Non-positive `n` has none.
+pub fn divisors(n: &BigInt) -> Option<Vec<BigInt>> {
+    if !n.is_positive() {
+        return Some(vec![]);
+    }
+
+    let mut primes = vec![];
+
+    factorize(n.clone(), &mut primes)?;
+    primes.sort();
+
+    let mut divisors = vec![BigInt::one()];
+    let mut idx = 0;
+
+    while idx < primes.len() {
+        let prime = &primes[idx];
+        let count = primes[idx..].iter().take_while(|p| *p == prime).count();
+        let mut next = Vec::with_capacity(divisors.len() * (count + 1));
+
+        for divisor in divisors.iter() {
+            let mut power = divisor.clone();
+
+            next.push(power.clone());
+
+            for _ in 0..count {
+                power *= prime;
+                next.push(power.clone());
+            }
+        }
+
+        divisors = next;
+        idx += count;
+    }
+
+    divisors.sort();
+
+    Some(divisors)
+}
+
+fn factorize(mut n: BigInt, primes: &mut Vec<BigInt>) -> Option<()> {
+    for p in SMALL_PRIMES.iter() {
+        while (&n % p).is_zero() {
+            primes.push(BigInt::from(*p));
+            n /= *p;
+        }
+    }
+
+    if n.is_one() {
+        return Some(());
+    }
+
+    if is_prime(&n) {
+        primes.push(n);
+
+        return Some(());
+    }
+
+    let divisor = rho(&n)?;
+    let rest = &n / &divisor;
+
+    factorize(divisor, primes)?;
+    factorize(rest, primes)
+}
+
+/// Miller-Rabin with the first thirteen primes as witnesses, which is exact for
+/// every `n` below 3.3 * 10^24 and a very strong test beyond that.
+pub fn is_prime(n: &BigInt) -> bool {
+    if *n < BigInt::from(2) {
+        return false;
+    }
+
+    for p in SMALL_PRIMES.iter() {
+        if *n == BigInt::from(*p) {
+            return true;
+        }
+
+        if (n % p).is_zero() {
+            return false;
+        }
+    }
+
+    let one = BigInt::one();
+    let n_minus_one = n - &one;
+    let shift = n_minus_one.trailing_zeros().unwrap_or(0);
+    let odd = &n_minus_one >> shift;
+
+    SMALL_PRIMES.iter().all(|witness| {
+        let mut x = BigInt::from(*witness).modpow(&odd, n);
+
+        if x == one || x == n_minus_one {
+            return true;
+        }
+
+        for _ in 1..shift {
+            x = &x * &x % n;
+
+            if x == n_minus_one {
+                return true;
+            }
+        }
+
+        false
+    })
+}
+
+fn rho(n: &BigInt) -> Option<BigInt> {
+    for c in 1..=8u32 {
+        let step = |x: &BigInt| (x * x + c) % n;
+
+        let mut x = BigInt::from(2);
+        let mut y = BigInt::from(2);
+
+        for _ in 0..RHO_STEPS {
+            x = step(&x);
+            y = step(&step(&y));
+
+            let divisor = (&x - &y).abs().gcd(n);
+
+            if divisor == *n {
+                break;
+            }
+
+            if !divisor.is_one() {
+                return Some(divisor);
+            }
+        }
+    }
+
+    None
+}
@@ -1,19 +1,45 @@
 use std::rc::Rc;
 
+use crate::compiler::Compiler;
+use crate::error::MatikaError;
 use crate::functions::Function;
 use crate::value::Value;
+use crate::vm::Vm;
 use crate::{
     environment::Environment,
     expr::Expr,
     plotter::Plotter,
-    stmt::Stmt,
-    token::{LiteralKind, Token, TokenKind},
+    stmt::{Fnc, Stmt},
+    token::{Span, Token, TokenKind},
     visitor::{Acceptor, Visitor},
 };
 
+/// How statements are executed: walking the syntax tree directly, or compiling
+/// it to bytecode first and running that on the `Vm`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Backend {
+    TreeWalker,
+    Vm,
+}
+
+/// User function calls nested deeper than this fail with a recursion error
+/// instead of exhausting the stack. The tree walker, the `Vm` and native
+/// closures all share the limit, which leaves the tree walker headroom on a
+/// default 2 MiB thread even in debug builds.
+pub const MAX_CALL_DEPTH: usize = 128;
+
 pub struct Interpreter {
     pub environment: Environment,
     pub plotter: Option<Box<dyn Plotter>>,
+    pub backend: Backend,
+    /// User function calls currently in progress.
+    pub depth: usize,
+}
+
+impl Default for Interpreter {
+    fn default() -> Self {
+        Self::new()
+    }
 }
 
 impl Interpreter {
@@ -21,6 +47,8 @@ impl Interpreter {
         Self {
             environment: Environment::global(),
             plotter: None,
+            backend: Backend::TreeWalker,
+            depth: 0,
         }
     }
 
@@ -30,21 +58,37 @@ impl Interpreter {
         self
     }
 
-    pub fn interpret(&mut self, stmts: Vec<Stmt>) -> Value {
+    pub fn with_backend(mut self, backend: Backend) -> Self {
+        self.backend = backend;
+
+        self
+    }
+
+    pub fn interpret(&mut self, stmts: Vec<Stmt>) -> Result<Value, MatikaError> {
+        if self.backend == Backend::Vm {
+            let chunk = Compiler::compile_script(&stmts);
+
+            return Vm::new(self).run_script(chunk);
+        }
+
         let mut val = Value::Number(0.0);
 
         for stmt in stmts {
-            val = self.execute(&stmt);
+            val = self.execute(&stmt)?;
         }
 
-        val
+        Ok(val)
     }
 
-    fn execute(&mut self, stmt: &Stmt) -> Value {
+    fn execute(&mut self, stmt: &Stmt) -> Result<Value, MatikaError> {
         stmt.accept(self)
     }
 
-    pub fn evaluate_inner(&mut self, expr: &Expr, environment: Environment) -> Value {
+    pub fn evaluate_inner(
+        &mut self,
+        expr: &Expr,
+        environment: Environment,
+    ) -> Result<Value, MatikaError> {
         let previous = self.environment.clone();
 
         self.environment = environment;
@@ -56,106 +100,249 @@ impl Interpreter {
         result
     }
 
-    fn evaluate(&mut self, expr: &Expr) -> Value {
+    /// Runs `call` as a nested user function call, failing once calls are
+    /// nested `MAX_CALL_DEPTH` deep.
+    pub fn nested<T>(
+        &mut self,
+        call: impl FnOnce(&mut Self) -> Result<T, MatikaError>,
+    ) -> Result<T, MatikaError> {
+        if self.depth >= MAX_CALL_DEPTH {
+            return Err(MatikaError::recursion_limit(MAX_CALL_DEPTH));
+        }
+
+        self.depth += 1;
+
+        let result = call(self);
+
+        self.depth -= 1;
+
+        result
+    }
+
+    fn evaluate(&mut self, expr: &Expr) -> Result<Value, MatikaError> {
         expr.accept(self)
     }
 
-    fn visit_unary_expr(&mut self, op: &Token, right: &Expr) -> Value {
-        let right = self.evaluate(&right);
+    fn visit_unary_expr(&mut self, op: &Token, right: &Expr) -> Result<Value, MatikaError> {
+        let right = self.evaluate(right)?;
 
-        match op.kind {
-            TokenKind::Minus => -right,
-            _ => panic!(),
+        Value::unary(op.kind, right).map_err(|err| err.at(op.span))
+    }
+
+    fn visit_binary_expr(
+        &mut self,
+        left: &Expr,
+        op: &Token,
+        right: &Expr,
+    ) -> Result<Value, MatikaError> {
+        let left = self.evaluate(left)?;
+        let right = self.evaluate(right)?;
+
+        Value::binary(op.kind, left, right).map_err(|err| err.at(op.span))
+    }
+
+    fn visit_logical_expr(
+        &mut self,
+        left: &Expr,
+        op: &Token,
+        right: &Expr,
+    ) -> Result<Value, MatikaError> {
+        let left = self
+            .evaluate(left)?
+            .as_bool()
+            .map_err(|err| err.at(op.span))?;
+
+        let short_circuit = match op.kind {
+            TokenKind::Or => left,
+            _ => !left,
+        };
+
+        if short_circuit {
+            return Ok(Value::Bool(left));
+        }
+
+        let right = self
+            .evaluate(right)?
+            .as_bool()
+            .map_err(|err| err.at(op.span))?;
+
+        Ok(Value::Bool(right))
+    }
+
+    /// Only the selected branch is evaluated.
+    fn visit_conditional_expr(
+        &mut self,
+        condition: &Expr,
+        then_branch: &Expr,
+        else_branch: &Expr,
+    ) -> Result<Value, MatikaError> {
+        let selected = self
+            .evaluate(condition)?
+            .as_bool()
+            .map_err(|err| err.at(condition.span()))?;
+
+        if selected {
+            self.evaluate(then_branch)
+        } else {
+            self.evaluate(else_branch)
+        }
+    }
+
+    fn visit_call_expr(
+        &mut self,
+        callee: &Expr,
+        args: &[Expr],
+        span: Span,
+    ) -> Result<Value, MatikaError> {
+        let callee = self.evaluate(callee)?;
+
+        let args = args
+            .iter()
+            .map(|arg| self.evaluate(arg))
+            .collect::<Result<Vec<Value>, MatikaError>>()?;
+
+        match callee {
+            Value::Function(fnc) if fnc.accepts(args.len()) => {
+                fnc.call(self, args).map_err(|err| err.relocate(span))
+            }
+            callee => Err(Self::call_error(callee, args.len(), span)),
+        }
+    }
+
+    /// Why `callee` can't be called with `argc` arguments. Kept out of
+    /// `visit_call_expr`, whose frame every nested call pays for.
+    fn call_error(callee: Value, argc: usize, span: Span) -> MatikaError {
+        match callee {
+            Value::Function(fnc) => MatikaError::Arity {
+                callee: fnc.label(),
+                expected: fnc.arity(),
+                got: argc,
+                span: Some(span),
+            },
+            Value::Unbound(name) => MatikaError::UnknownFunction {
+                name,
+                suggestion: None,
+                span: Some(span),
+            },
+            value => MatikaError::NotCallable {
+                name: value.to_string(),
+                span: Some(span),
+            },
         }
     }
 
-    fn visit_binary_expr(&mut self, left: &Expr, op: &Token, right: &Expr) -> Value {
-        let left = self.evaluate(&left);
-        let right = self.evaluate(&right);
+    fn visit_list_expr(&mut self, elements: &[Expr]) -> Result<Value, MatikaError> {
+        elements
+            .iter()
+            .map(|element| self.evaluate(element))
+            .collect::<Result<Vec<Value>, MatikaError>>()
+            .map(Value::List)
+    }
+
+    fn visit_lambda_expr(&mut self, fnc: &Fnc) -> Value {
+        Value::Function(Rc::new(Function::lambda(
+            fnc.clone(),
+            self.environment.clone(),
+        )))
+    }
+
+    fn visit_index_expr(
+        &mut self,
+        object: &Expr,
+        index: &Expr,
+        span: Span,
+    ) -> Result<Value, MatikaError> {
+        let object = self.evaluate(object)?;
+        let index = self.evaluate(index)?;
 
-        let ret = match op.kind {
-            TokenKind::Plus => left + right,
-            TokenKind::Minus => left - right,
-            TokenKind::Star => left * right,
-            TokenKind::Slash => left / right,
-            TokenKind::Caret => left.powf(right),
-            _ => panic!(),
+        object.index(&index).map_err(|err| err.at(span))
+    }
+
+    fn visit_slice_expr(
+        &mut self,
+        object: &Expr,
+        start: Option<&Expr>,
+        end: Option<&Expr>,
+        span: Span,
+    ) -> Result<Value, MatikaError> {
+        let object = self.evaluate(object)?;
+        let start = start.map(|start| self.evaluate(start)).transpose()?;
+        let end = end.map(|end| self.evaluate(end)).transpose()?;
+
+        object
+            .slice(start.as_ref(), end.as_ref())
+            .map_err(|err| err.at(span))
+    }
+
+    fn visit_variable_expr(&self, name: &Token, depth: Option<usize>) -> Value {
+        let value = match depth {
+            Some(depth) => self.environment.get_at(depth, &name.lexeme),
+            None => self.environment.get(&name.lexeme),
         };
 
-        ret
+        value.unwrap_or_else(|| Value::Unbound(name.lexeme.clone()))
     }
 }
 
 impl Visitor<Stmt> for &mut Interpreter {
-    type Result = Value;
+    type Result = Result<Value, MatikaError>;
 
     fn visit(&mut self, stmt: &Stmt) -> Self::Result {
         match stmt {
             Stmt::Expression(expr) => self.evaluate(expr),
             Stmt::Print(expr) => {
-                let value = self.evaluate(expr);
+                let value = self.evaluate(expr)?;
 
                 println!("{}", value);
 
-                Value::Number(0.0)
+                Ok(Value::Number(0.0))
             }
             Stmt::Variable { name, initializer } => {
-                let value = self.evaluate(&initializer);
+                let value = self.evaluate(initializer)?;
 
                 self.environment.define(name.lexeme.clone(), value);
 
-                Value::Number(0.0)
+                Ok(Value::Number(0.0))
             }
             Stmt::Function(fnc) => {
-                let function = Rc::new(Function {
-                    declaration: fnc.clone(),
-                });
-
-                self.environment
-                    .define(fnc.name.lexeme.clone(), Value::Function(function));
+                Function::define(&self.environment, fnc);
 
-                Value::Number(0.0)
+                Ok(Value::Number(0.0))
             }
         }
     }
 }
 
 impl Visitor<Expr> for &mut Interpreter {
-    type Result = Value;
+    type Result = Result<Value, MatikaError>;
 
     fn visit(&mut self, expr: &Expr) -> Self::Result {
         match expr {
-            Expr::Literal(LiteralKind::Number(value)) => Value::Number(*value),
-            Expr::Grouping(expr) => self.evaluate(&expr),
+            Expr::Literal { value, .. } => Ok(Value::from(value)),
+            Expr::Grouping { expr, .. } => self.evaluate(expr),
             Expr::Unary { op, right } => self.visit_unary_expr(op, right),
             Expr::Binary { left, op, right } => self.visit_binary_expr(left, op, right),
-            Expr::Call { callee, args } => {
-                let callee = self.evaluate(callee);
-
-                let args: Vec<Value> = args.iter().map(|arg| self.evaluate(arg)).collect();
-
-                match callee {
-                    Value::Function(fnc) => {
-                        if args.len() != fnc.arity() as usize {
-                            panic!("Arity mismatch")
-                        }
-                        fnc.call(self, args)
-                    }
-                    _ => panic!("Not a function"),
-                }
-            }
-            Expr::Variable(variable) => {
-                if let Some(value) = self.environment.get(&variable.lexeme) {
-                    value
-                } else {
-                    let value = Value::Unbound(variable.lexeme.clone());
-
-                    self.environment
-                        .define(variable.lexeme.clone(), value.clone());
-
-                    value
-                }
-            }
+            Expr::Logical { left, op, right } => self.visit_logical_expr(left, op, right),
+            Expr::Conditional {
+                condition,
+                then_branch,
+                else_branch,
+            } => self.visit_conditional_expr(condition, then_branch, else_branch),
+            Expr::Call { callee, args, span } => self.visit_call_expr(callee, args, *span),
+            Expr::List { elements, .. } => self.visit_list_expr(elements),
+            Expr::Lambda { fnc, .. } => Ok(self.visit_lambda_expr(fnc)),
+            Expr::Index {
+                object,
+                index,
+                span,
+            } => self.visit_index_expr(object, index, *span),
+            Expr::Slice {
+                object,
+                start,
+                end,
+                span,
+            } => self.visit_slice_expr(object, start.as_deref(), end.as_deref(), *span),
+            Expr::Variable { name, depth } => Ok(self.visit_variable_expr(name, *depth)),
         }
     }
 }
@@ -1,33 +1,64 @@
 mod callable;
+mod chunk;
+mod compiler;
+mod complex;
+mod derivative;
 mod environment;
+mod error;
 mod expr;
 mod functions;
+mod integer;
 mod interpreter;
+mod matrix;
+mod native;
 mod parser;
 mod plotter;
+mod printer;
+mod resolver;
 mod scanner;
+mod simplify;
+mod solve;
 mod stmt;
+mod symbolic;
 mod token;
 mod value;
 mod visitor;
+mod vm;
 
 pub use callable::Callable;
+pub use complex::Complex;
+pub use error::{MatikaError, Warning};
 pub use expr::Expr;
-pub use interpreter::Interpreter;
+pub use interpreter::{Backend, Interpreter};
+pub use matrix::Matrix;
 pub use parser::Parser;
 pub use plotter::Plotter;
+pub use resolver::Resolver;
 pub use scanner::Scanner;
 pub use stmt::Stmt;
+pub use token::Span;
 pub use value::Value;
 
+use native::{NativeCompiler, NativeFunction};
+
 pub struct Matika {
     interpreter: Interpreter,
+    warnings: Vec<Warning>,
+    approx: bool,
+}
+
+impl Default for Matika {
+    fn default() -> Self {
+        Self::new()
+    }
 }
 
 impl Matika {
     pub fn new() -> Self {
         Self {
-            interpreter: Interpreter::new()
+            interpreter: Interpreter::new(),
+            warnings: vec![],
+            approx: false,
         }
     }
 
@@ -37,13 +68,268 @@ impl Matika {
         self
     }
 
-    pub fn eval(&mut self, txt: String) -> Value {
+    /// Selects how evaluated code is executed, the tree walker by default.
+    pub fn with_backend(mut self, backend: Backend) -> Self {
+        self.set_backend(backend);
+
+        self
+    }
+
+    pub fn set_backend(&mut self, backend: Backend) {
+        self.interpreter.backend = backend;
+    }
+
+    /// Results are exact fractions by default; with `approx` they are floats.
+    pub fn with_approx(mut self, approx: bool) -> Self {
+        self.set_approx(approx);
+
+        self
+    }
+
+    pub fn set_approx(&mut self, approx: bool) {
+        self.approx = approx;
+    }
+
+    pub fn eval(&mut self, txt: String) -> Result<Value, MatikaError> {
+        let mut scanner = Scanner::new(txt);
+        let tokens = scanner.scan()?;
+
+        let mut parser = Parser::new(tokens);
+        let mut stmts = parser.parse()?;
+
+        let (mut errors, warnings) =
+            Resolver::new(&self.interpreter.environment).resolve(&mut stmts);
+
+        self.warnings = warnings;
+
+        if !errors.is_empty() {
+            return Err(errors.remove(0));
+        }
+
+        self.interpreter
+            .interpret(stmts)
+            .map(|value| self.present(value))
+    }
+
+    /// Compiles the function `name` into a native closure over its arguments.
+    /// Names other than parameters are resolved now, so later redefinitions do
+    /// not affect the returned closure. Fails if the function depends on
+    /// anything that is not a number.
+    pub fn compile_fn(
+        &self,
+        name: &str,
+    ) -> Result<impl Fn(&[f64]) -> Result<f64, MatikaError>, MatikaError> {
+        let fnc = match self.interpreter.environment.get(name) {
+            Some(Value::Function(fnc)) => fnc,
+            Some(value) => {
+                return Err(MatikaError::NotCallable {
+                    name: value.to_string(),
+                    span: None,
+                })
+            }
+            None => {
+                return Err(MatikaError::UnknownFunction {
+                    name: name.to_string(),
+                    suggestion: None,
+                    span: None,
+                })
+            }
+        };
+
+        let native = match (fnc.as_function(), fnc.native()) {
+            (Some(function), _) => NativeCompiler::default().compile(function)?,
+            (None, Some(builtin)) => NativeFunction::builtin(name, fnc.arity(), builtin),
+            (None, None) => {
+                return Err(MatikaError::type_error(format!(
+                    "{} does not return a number",
+                    fnc
+                )))
+            }
+        };
+
+        Ok(move |args: &[f64]| native.call(args))
+    }
+
+    /// Warnings reported by the resolver for the last evaluated source.
+    pub fn warnings(&self) -> &[Warning] {
+        &self.warnings
+    }
+
+    /// Evaluates a multi-line script, reporting every syntax error before running anything.
+    pub fn eval_script(&mut self, txt: String) -> Result<Value, Vec<MatikaError>> {
         let mut scanner = Scanner::new(txt);
-        let tokens = scanner.scan();
+        let (tokens, mut errors) = scanner.scan_with_diagnostics();
 
         let mut parser = Parser::new(tokens);
-        let stmts = parser.parse();
+        let (mut stmts, parse_errors) = parser.parse_with_diagnostics();
+
+        // A line that failed to lex almost always fails to parse too; report it once.
+        let lexed_lines: Vec<usize> = errors
+            .iter()
+            .filter_map(|err| err.span())
+            .map(|span| span.line)
+            .collect();
+
+        errors.extend(parse_errors.into_iter().filter(|err| {
+            err.span()
+                .map(|span| !lexed_lines.contains(&span.line))
+                .unwrap_or(true)
+        }));
+
+        if !errors.is_empty() {
+            return Err(by_position(errors));
+        }
+
+        let (errors, warnings) = Resolver::new(&self.interpreter.environment).resolve(&mut stmts);
+
+        self.warnings = warnings;
+
+        if !errors.is_empty() {
+            return Err(by_position(errors));
+        }
 
-        self.interpreter.interpret(stmts)
+        self.interpreter
+            .interpret(stmts)
+            .map(|value| self.present(value))
+            .map_err(|err| vec![err])
     }
-}
\ No newline at end of file
+
+    fn present(&self, value: Value) -> Value {
+        if self.approx {
+            value.approx()
+        } else {
+            value
+        }
+    }
+}
+
+/// Sorts errors by where they occur in the source, unplaced ones last.
+fn by_position(mut errors: Vec<MatikaError>) -> Vec<MatikaError> {
+    errors.sort_by_key(|err| err.span().map_or(usize::MAX, |span| span.start));
+
+    errors
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Evaluates `src` in a fresh session.
+    pub(crate) fn value(src: &str) -> Value {
+        Matika::new().eval(src.to_string()).unwrap()
+    }
+
+    /// Evaluates `src` in a fresh session, returning the result as printed.
+    pub(crate) fn eval(src: &str) -> String {
+        value(src).to_string()
+    }
+
+    /// Evaluates each line in one session, returning the last result or error
+    /// as printed.
+    pub(crate) fn session(lines: &[&str]) -> Result<String, String> {
+        let mut matika = Matika::new();
+        let mut result = Ok(String::new());
+
+        for line in lines {
+            result = matika
+                .eval(line.to_string())
+                .map(|value| value.to_string())
+                .map_err(|err| err.to_string());
+        }
+
+        result
+    }
+
+    #[test]
+    fn script_errors_are_reported_in_source_order() {
+        let errors = Matika::new()
+            .eval_script("x = (1\ny = 2 $ 3\nz = 4 +".to_string())
+            .unwrap_err();
+        let messages: Vec<String> = errors.iter().map(|err| err.to_string()).collect();
+
+        assert_eq!(messages.len(), 3);
+        assert!(
+            messages[0].starts_with("parse error at line 1,"),
+            "{:?}",
+            messages
+        );
+        assert!(
+            messages[1].starts_with("lex error at line 2,"),
+            "{:?}",
+            messages
+        );
+        assert!(
+            messages[2].starts_with("parse error at line 3,"),
+            "{:?}",
+            messages
+        );
+    }
+
+    fn factorial(backend: Backend) -> Matika {
+        let mut matika = Matika::new().with_backend(backend);
+
+        matika.eval("fact(0) = 1".to_string()).unwrap();
+        matika
+            .eval("fact(n) = n * fact(n - 1)".to_string())
+            .unwrap();
+
+        matika
+    }
+
+    #[test]
+    fn unbounded_recursion_is_an_error() {
+        for backend in [Backend::TreeWalker, Backend::Vm] {
+            let mut matika = factorial(backend);
+
+            for src in ["fact(-1)", "fact(0.5)"] {
+                let err = matika.eval(src.to_string()).unwrap_err();
+
+                assert_eq!(
+                    err,
+                    MatikaError::RecursionLimit {
+                        limit: interpreter::MAX_CALL_DEPTH,
+                        span: Some(Span {
+                            start: 0,
+                            end: src.len(),
+                            line: 1,
+                            column: 1
+                        }),
+                    }
+                );
+            }
+
+            assert_eq!(
+                matika.eval("fact(5)".to_string()).unwrap().to_string(),
+                "120"
+            );
+        }
+    }
+
+    #[test]
+    fn recursion_through_builtins_is_limited() {
+        for backend in [Backend::TreeWalker, Backend::Vm] {
+            let mut matika = Matika::new().with_backend(backend);
+
+            matika
+                .eval("g(n) = 0 if n == 0 else sum(k -> g(n - 1), 1, 1)".to_string())
+                .unwrap();
+
+            assert!(matches!(
+                matika.eval("g(-1)".to_string()),
+                Err(MatikaError::RecursionLimit { .. })
+            ));
+            assert_eq!(matika.eval("g(3)".to_string()).unwrap().to_string(), "0");
+        }
+    }
+
+    #[test]
+    fn native_recursion_is_limited() {
+        let fact = factorial(Backend::TreeWalker).compile_fn("fact").unwrap();
+
+        assert!(matches!(
+            fact(&[-1.0]),
+            Err(MatikaError::RecursionLimit { .. })
+        ));
+        assert_eq!(fact(&[5.0]), Ok(120.0));
+    }
+}
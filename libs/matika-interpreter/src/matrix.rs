@@ -0,0 +1,553 @@
+use std::fmt;
+
+use crate::complex::Complex;
+use crate::error::MatikaError;
+
+/// Pivots and subdiagonal entries this small relative to the largest entry
+/// count as zero.
+const EPSILON: f64 = 1e-10;
+
+/// QR iterations run by `eigenvalues` before giving up on full convergence.
+const QR_STEPS: usize = 500;
+
+/// Significant digits kept when printing, which hides the rounding noise
+/// elimination leaves behind. Entries themselves are never rounded.
+const DIGITS: i32 = 12;
+
+/// A dense matrix of floats stored row by row.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Matrix {
+    rows: usize,
+    cols: usize,
+    data: Vec<f64>,
+}
+
+impl Matrix {
+    /// Builds a matrix from rows that must all have the same, non-zero length.
+    pub fn from_rows(rows: Vec<Vec<f64>>) -> Result<Self, MatikaError> {
+        let cols = rows.first().map_or(0, Vec::len);
+
+        if cols == 0 || rows.iter().any(|row| row.len() != cols) {
+            return Err(MatikaError::type_error(
+                "matrix rows must be non-empty and of equal length",
+            ));
+        }
+
+        Ok(Self {
+            rows: rows.len(),
+            cols,
+            data: rows.into_iter().flatten().collect(),
+        })
+    }
+
+    pub fn identity(n: usize) -> Self {
+        let mut identity = Self::zeros(n, n);
+
+        for i in 0..n {
+            identity.data[i * n + i] = 1.0;
+        }
+
+        identity
+    }
+
+    fn zeros(rows: usize, cols: usize) -> Self {
+        Self {
+            rows,
+            cols,
+            data: vec![0.0; rows * cols],
+        }
+    }
+
+    pub fn rows(&self) -> usize {
+        self.rows
+    }
+
+    pub fn cols(&self) -> usize {
+        self.cols
+    }
+
+    pub fn row(&self, i: usize) -> &[f64] {
+        &self.data[i * self.cols..(i + 1) * self.cols]
+    }
+
+    /// The entries in row-major order.
+    pub fn data(&self) -> &[f64] {
+        &self.data
+    }
+
+    fn get(&self, i: usize, j: usize) -> f64 {
+        self.data[i * self.cols + j]
+    }
+
+    fn set(&mut self, i: usize, j: usize, value: f64) {
+        self.data[i * self.cols + j] = value;
+    }
+
+    pub fn transpose(&self) -> Self {
+        let mut transposed = Self::zeros(self.cols, self.rows);
+
+        for i in 0..self.rows {
+            for j in 0..self.cols {
+                transposed.set(j, i, self.get(i, j));
+            }
+        }
+
+        transposed
+    }
+
+    pub fn map(&self, f: impl Fn(f64) -> f64) -> Self {
+        Self {
+            rows: self.rows,
+            cols: self.cols,
+            data: self.data.iter().map(|x| f(*x)).collect(),
+        }
+    }
+
+    /// Combines two matrices of the same shape entry by entry.
+    pub fn zip(
+        &self,
+        symbol: &str,
+        other: &Self,
+        f: impl Fn(f64, f64) -> f64,
+    ) -> Result<Self, MatikaError> {
+        if (self.rows, self.cols) != (other.rows, other.cols) {
+            return Err(MatikaError::type_error(format!(
+                "cannot apply '{}' to a {} and a {} matrix",
+                symbol,
+                self.shape(),
+                other.shape()
+            )));
+        }
+
+        Ok(Self {
+            rows: self.rows,
+            cols: self.cols,
+            data: self
+                .data
+                .iter()
+                .zip(&other.data)
+                .map(|(a, b)| f(*a, *b))
+                .collect(),
+        })
+    }
+
+    pub fn mul(&self, other: &Self) -> Result<Self, MatikaError> {
+        if self.cols != other.rows {
+            return Err(MatikaError::type_error(format!(
+                "cannot multiply a {} by a {} matrix",
+                self.shape(),
+                other.shape()
+            )));
+        }
+
+        let mut product = Self::zeros(self.rows, other.cols);
+
+        for i in 0..self.rows {
+            for j in 0..other.cols {
+                product.set(
+                    i,
+                    j,
+                    (0..self.cols)
+                        .map(|k| self.get(i, k) * other.get(k, j))
+                        .sum(),
+                );
+            }
+        }
+
+        Ok(product)
+    }
+
+    pub fn mul_vector(&self, vector: &[f64]) -> Result<Vec<f64>, MatikaError> {
+        if self.cols != vector.len() {
+            return Err(MatikaError::type_error(format!(
+                "cannot multiply a {} matrix by a vector of length {}",
+                self.shape(),
+                vector.len()
+            )));
+        }
+
+        Ok((0..self.rows)
+            .map(|i| self.row(i).iter().zip(vector).map(|(a, b)| a * b).sum())
+            .collect())
+    }
+
+    /// Integer powers by repeated squaring; negative powers invert first.
+    pub fn powi(&self, exponent: i64) -> Result<Self, MatikaError> {
+        self.square("^")?;
+
+        let mut base = if exponent < 0 {
+            self.inv()?
+        } else {
+            self.clone()
+        };
+        let mut result = Self::identity(self.rows);
+        let mut n = exponent.unsigned_abs();
+
+        while n > 0 {
+            if n % 2 == 1 {
+                result = result.mul(&base)?;
+            }
+
+            base = base.mul(&base)?;
+            n /= 2;
+        }
+
+        Ok(result)
+    }
+
+    pub fn det(&self) -> Result<f64, MatikaError> {
+        self.square("det")?;
+
+        let mut reduced = self.clone();
+        let (det, rank) = reduced.reduce(self.cols);
+
+        Ok(if rank < self.rows { 0.0 } else { det })
+    }
+
+    pub fn inv(&self) -> Result<Self, MatikaError> {
+        self.square("inv")?;
+
+        let n = self.rows;
+        let mut augmented = self.augment(&Self::identity(n));
+        let (_, rank) = augmented.reduce(n);
+
+        if rank < n {
+            return Err(MatikaError::type_error("matrix is singular"));
+        }
+
+        let mut inverse = Self::zeros(n, n);
+
+        for i in 0..n {
+            for j in 0..n {
+                inverse.set(i, j, augmented.get(i, n + j));
+            }
+        }
+
+        Ok(inverse)
+    }
+
+    pub fn rank(&self) -> usize {
+        self.clone().reduce(self.cols).1
+    }
+
+    /// Solves `self * x = b` for a square, non-singular matrix.
+    pub fn solve(&self, b: &[f64]) -> Result<Vec<f64>, MatikaError> {
+        self.square("solve")?;
+
+        if b.len() != self.rows {
+            return Err(MatikaError::type_error(format!(
+                "cannot solve a {} system with a right-hand side of length {}",
+                self.shape(),
+                b.len()
+            )));
+        }
+
+        let n = self.rows;
+        let column = Self {
+            rows: n,
+            cols: 1,
+            data: b.to_vec(),
+        };
+        let mut augmented = self.augment(&column);
+        let (_, rank) = augmented.reduce(n);
+
+        if rank < n {
+            return Err(MatikaError::type_error(
+                "matrix is singular, the system has no unique solution",
+            ));
+        }
+
+        Ok((0..n).map(|i| augmented.get(i, n)).collect())
+    }
+
+    /// Eigenvalues by unshifted QR iteration, which leaves real eigenvalues on
+    /// the diagonal and complex pairs, or pairs of equal magnitude, in 2x2
+    /// blocks. Fails when larger clusters of equal magnitude never separate.
+    /// Parts negligible next to the largest entry are snapped to zero.
+    pub fn eigenvalues(&self) -> Result<Vec<Complex>, MatikaError> {
+        self.square("eigenvalues")?;
+
+        let mut a = self.clone();
+        let n = a.rows;
+        let tolerance = EPSILON * a.scale();
+        let converged = f64::EPSILON * a.scale();
+
+        for _ in 0..QR_STEPS {
+            if (0..n.saturating_sub(1)).all(|i| a.get(i + 1, i).abs() <= converged) {
+                break;
+            }
+
+            let (q, r) = a.qr();
+            a = r.mul(&q)?;
+        }
+
+        let split = |i: usize| a.get(i + 1, i).abs() <= tolerance;
+        let mut eigenvalues = Vec::with_capacity(n);
+        let mut i = 0;
+
+        while i < n {
+            if i + 1 == n || split(i) {
+                eigenvalues.push(Complex::from(a.get(i, i)));
+                i += 1;
+
+                continue;
+            }
+
+            if i + 2 < n && !split(i + 1) {
+                return Err(MatikaError::type_error("eigenvalues did not converge"));
+            }
+
+            let (p, q) = (a.get(i, i), a.get(i, i + 1));
+            let (r, s) = (a.get(i + 1, i), a.get(i + 1, i + 1));
+            let mean = (p + s) / 2.0;
+            let root = Complex::from(((p - s) / 2.0).powi(2) + q * r).sqrt();
+
+            eigenvalues.push(Complex::from(mean) + root);
+            eigenvalues.push(Complex::from(mean) - root);
+            i += 2;
+        }
+
+        let snap = |x: f64| if x.abs() <= tolerance { 0.0 } else { x };
+
+        Ok(eigenvalues
+            .into_iter()
+            .map(|z| Complex::new(snap(z.re), snap(z.im)))
+            .collect())
+    }
+
+    /// Householder QR decomposition of a square matrix.
+    fn qr(&self) -> (Self, Self) {
+        let n = self.rows;
+        let mut q = Self::identity(n);
+        let mut r = self.clone();
+
+        for k in 0..n.saturating_sub(1) {
+            let mut v: Vec<f64> = (k..n).map(|i| r.get(i, k)).collect();
+            let norm = v.iter().map(|x| x * x).sum::<f64>().sqrt();
+
+            if norm == 0.0 {
+                continue;
+            }
+
+            v[0] += if v[0] < 0.0 { -norm } else { norm };
+
+            let length = v.iter().map(|x| x * x).sum::<f64>().sqrt();
+            v.iter_mut().for_each(|x| *x /= length);
+
+            for j in 0..n {
+                let dot: f64 = v.iter().enumerate().map(|(i, x)| x * r.get(k + i, j)).sum();
+
+                for (i, x) in v.iter().enumerate() {
+                    r.set(k + i, j, r.get(k + i, j) - 2.0 * x * dot);
+                }
+            }
+
+            for i in 0..n {
+                let dot: f64 = v.iter().enumerate().map(|(j, x)| x * q.get(i, k + j)).sum();
+
+                for (j, x) in v.iter().enumerate() {
+                    q.set(i, k + j, q.get(i, k + j) - 2.0 * x * dot);
+                }
+            }
+        }
+
+        (q, r)
+    }
+
+    /// Gauss-Jordan elimination with partial pivoting over the first `cols`
+    /// columns, leaving them in reduced row echelon form. Returns the
+    /// determinant of that block and its rank.
+    fn reduce(&mut self, cols: usize) -> (f64, usize) {
+        let tolerance = EPSILON * self.scale();
+        let mut det = 1.0;
+        let mut rank = 0;
+
+        for col in 0..cols {
+            if rank == self.rows {
+                break;
+            }
+
+            let pivot = (rank..self.rows)
+                .max_by(|a, b| {
+                    self.get(*a, col)
+                        .abs()
+                        .partial_cmp(&self.get(*b, col).abs())
+                        .unwrap_or(std::cmp::Ordering::Equal)
+                })
+                .unwrap_or(rank);
+
+            if self.get(pivot, col).abs() <= tolerance {
+                det = 0.0;
+
+                continue;
+            }
+
+            if pivot != rank {
+                self.swap_rows(pivot, rank);
+                det = -det;
+            }
+
+            let value = self.get(rank, col);
+            det *= value;
+
+            for j in 0..self.cols {
+                self.set(rank, j, self.get(rank, j) / value);
+            }
+
+            for i in 0..self.rows {
+                let factor = self.get(i, col);
+
+                if i == rank || factor == 0.0 {
+                    continue;
+                }
+
+                for j in 0..self.cols {
+                    self.set(i, j, self.get(i, j) - factor * self.get(rank, j));
+                }
+            }
+
+            rank += 1;
+        }
+
+        (det, rank)
+    }
+
+    fn swap_rows(&mut self, a: usize, b: usize) {
+        for j in 0..self.cols {
+            self.data.swap(a * self.cols + j, b * self.cols + j);
+        }
+    }
+
+    /// Places `other` to the right of `self`.
+    fn augment(&self, other: &Self) -> Self {
+        let mut augmented = Self::zeros(self.rows, self.cols + other.cols);
+
+        for i in 0..self.rows {
+            for j in 0..self.cols {
+                augmented.set(i, j, self.get(i, j));
+            }
+
+            for j in 0..other.cols {
+                augmented.set(i, self.cols + j, other.get(i, j));
+            }
+        }
+
+        augmented
+    }
+
+    /// The largest absolute entry, used to make tolerances relative.
+    fn scale(&self) -> f64 {
+        self.data.iter().fold(0.0, |max: f64, x| max.max(x.abs()))
+    }
+
+    fn square(&self, name: &str) -> Result<(), MatikaError> {
+        if self.rows != self.cols {
+            return Err(MatikaError::type_error(format!(
+                "{} expects a square matrix, got a {} matrix",
+                name,
+                self.shape()
+            )));
+        }
+
+        Ok(())
+    }
+
+    fn shape(&self) -> String {
+        format!("{}x{}", self.rows, self.cols)
+    }
+}
+
+/// Prints one bracketed row per line with right-aligned columns.
+impl fmt::Display for Matrix {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let tolerance = EPSILON * self.scale();
+        let cells: Vec<String> = self
+            .data
+            .iter()
+            .map(|x| {
+                if x.abs() <= tolerance {
+                    String::from("0")
+                } else {
+                    round(*x).to_string()
+                }
+            })
+            .collect();
+        let widths: Vec<usize> = (0..self.cols)
+            .map(|j| {
+                (0..self.rows)
+                    .map(|i| cells[i * self.cols + j].chars().count())
+                    .max()
+                    .unwrap_or(0)
+            })
+            .collect();
+
+        for i in 0..self.rows {
+            if i > 0 {
+                writeln!(f)?;
+            }
+
+            let row: Vec<String> = (0..self.cols)
+                .map(|j| format!("{:>width$}", cells[i * self.cols + j], width = widths[j]))
+                .collect();
+
+            write!(f, "[{}]", row.join("  "))?;
+        }
+
+        Ok(())
+    }
+}
+
+/// Rounds to `DIGITS` significant digits.
+fn round(x: f64) -> f64 {
+    if x == 0.0 || !x.is_finite() {
+        return x + 0.0;
+    }
+
+    let scale = 10f64.powi(DIGITS - 1 - x.abs().log10().floor() as i32);
+
+    (x * scale).round() / scale + 0.0
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn eigenvalues_are_not_rounded() {
+        let matrix = Matrix::from_rows(vec![vec![1.000_000_000_000_123_4, 0.0], vec![0.0, 2.0]]);
+        let eigenvalues = matrix.unwrap().eigenvalues().unwrap();
+
+        assert_eq!(eigenvalues[0], Complex::from(1.000_000_000_000_123_4));
+        assert_eq!(eigenvalues[1], Complex::from(2.0));
+    }
+
+    #[test]
+    fn eigenvalues_match_closed_form() {
+        let matrix = Matrix::from_rows(vec![vec![1.0, 2.0], vec![3.0, 4.0]]).unwrap();
+        let eigenvalues = matrix.eigenvalues().unwrap();
+        let root = 33f64.sqrt();
+
+        assert!((eigenvalues[0].re - (5.0 + root) / 2.0).abs() < 1e-12);
+        assert!((eigenvalues[1].re - (5.0 - root) / 2.0).abs() < 1e-12);
+        assert_eq!((eigenvalues[0].im, eigenvalues[1].im), (0.0, 0.0));
+    }
+
+    #[test]
+    fn rotation_eigenvalues_have_no_real_part() {
+        let matrix = Matrix::from_rows(vec![vec![0.0, -1.0], vec![1.0, 0.0]]).unwrap();
+        let eigenvalues = matrix.eigenvalues().unwrap();
+
+        assert_eq!((eigenvalues[0].re, eigenvalues[1].re), (0.0, 0.0));
+        assert!((eigenvalues[0].im.abs() - 1.0).abs() < 1e-12);
+    }
+
+    #[test]
+    fn display_rounds_entries() {
+        let matrix = Matrix::from_rows(vec![vec![1.0 / 3.0, 1e-17], vec![2.0, 10.0]]).unwrap();
+
+        assert_eq!(
+            matrix.to_string(),
+            "[0.333333333333   0]\n[             2  10]"
+        );
+    }
+}
@@ -0,0 +1,448 @@
+use std::cell::{Cell, RefCell};
+use std::collections::HashMap;
+use std::rc::{Rc, Weak};
+
+use crate::callable::{Callable, NativeFn};
+use crate::environment::Environment;
+use crate::error::MatikaError;
+use crate::expr::Expr;
+use crate::functions::Function;
+use crate::interpreter::MAX_CALL_DEPTH;
+use crate::stmt::{Fnc, Pattern};
+use crate::token::{LiteralKind, TokenKind};
+use crate::value::Value;
+
+type Num = Box<dyn Fn(&[f64]) -> Result<f64, MatikaError>>;
+type Cond = Box<dyn Fn(&[f64]) -> Result<bool, MatikaError>>;
+
+struct NativeClause {
+    patterns: Vec<Option<f64>>,
+    body: Num,
+}
+
+/// A user function lowered into a tree of closures over `f64` arguments.
+/// Functions compiled together share `depth`, the number of calls in progress.
+pub struct NativeFunction {
+    name: String,
+    arity: usize,
+    clauses: RefCell<Vec<NativeClause>>,
+    depth: Rc<Cell<usize>>,
+}
+
+impl NativeFunction {
+    pub fn builtin(name: &str, arity: u64, builtin: NativeFn) -> Rc<Self> {
+        Rc::new(Self {
+            name: name.to_string(),
+            arity: arity as usize,
+            clauses: RefCell::new(vec![NativeClause {
+                patterns: vec![None; arity as usize],
+                body: Box::new(move |args| Ok(builtin(args))),
+            }]),
+            depth: Rc::default(),
+        })
+    }
+
+    pub fn call(&self, args: &[f64]) -> Result<f64, MatikaError> {
+        if args.len() != self.arity {
+            return Err(MatikaError::Arity {
+                callee: format!("<fnc:{}>", self.name),
+                expected: self.arity as u64,
+                got: args.len(),
+                span: None,
+            });
+        }
+
+        let clauses = self.clauses.borrow();
+        let clause = clauses.iter().find(|clause| {
+            clause
+                .patterns
+                .iter()
+                .zip(args)
+                .all(|(pattern, arg)| pattern.is_none_or(|literal| literal == *arg))
+        });
+
+        match clause {
+            Some(clause) => {
+                let depth = self.depth.get();
+
+                if depth >= MAX_CALL_DEPTH {
+                    return Err(MatikaError::recursion_limit(MAX_CALL_DEPTH));
+                }
+
+                self.depth.set(depth + 1);
+
+                let result = (clause.body)(args);
+
+                self.depth.set(depth);
+
+                result
+            }
+            None => Err(MatikaError::NoMatchingClause {
+                name: self.name.clone(),
+                args: args
+                    .iter()
+                    .map(|arg| arg.to_string())
+                    .collect::<Vec<String>>()
+                    .join(", "),
+                span: None,
+            }),
+        }
+    }
+}
+
+enum Target {
+    Done(Rc<NativeFunction>),
+    // Recursive calls point back at a function still being lowered; holding it
+    // weakly keeps the closure tree free of reference cycles.
+    Pending(Weak<NativeFunction>),
+    Builtin(NativeFn),
+}
+
+/// Lowers user functions into native closures. Parameters become slots of the
+/// argument slice and every other name is resolved once, at compile time, from
+/// the environment the function was defined in.
+#[derive(Default)]
+pub struct NativeCompiler {
+    done: HashMap<*const Function, Rc<NativeFunction>>,
+    pending: HashMap<*const Function, Weak<NativeFunction>>,
+    depth: Rc<Cell<usize>>,
+}
+
+impl NativeCompiler {
+    pub fn compile(&mut self, function: &Function) -> Result<Rc<NativeFunction>, MatikaError> {
+        let key = function as *const Function;
+
+        if let Some(native) = self.done.get(&key) {
+            return Ok(native.clone());
+        }
+
+        let native = Rc::new(NativeFunction {
+            name: function.name().to_string(),
+            arity: function.arity() as usize,
+            clauses: RefCell::new(vec![]),
+            depth: self.depth.clone(),
+        });
+
+        self.pending.insert(key, Rc::downgrade(&native));
+
+        let clauses = function
+            .clauses
+            .iter()
+            .map(|clause| self.clause(clause, &function.closure))
+            .collect::<Result<Vec<NativeClause>, MatikaError>>();
+
+        self.pending.remove(&key);
+
+        *native.clauses.borrow_mut() = clauses?;
+        self.done.insert(key, native.clone());
+
+        Ok(native)
+    }
+
+    fn clause(&mut self, clause: &Fnc, closure: &Environment) -> Result<NativeClause, MatikaError> {
+        let mut patterns = vec![];
+        let mut slots = HashMap::new();
+
+        for (idx, param) in clause.params.iter().enumerate() {
+            match param {
+                Pattern::Binding(token) => {
+                    slots.insert(token.lexeme.clone(), idx);
+                    patterns.push(None);
+                }
+                Pattern::Literal { value, .. } if value.number().is_some() => {
+                    patterns.push(value.number())
+                }
+                Pattern::Literal { value, span } => {
+                    return Err(MatikaError::type_error(format!(
+                        "`{}` matches the non-numeric literal {}",
+                        clause.name.lexeme,
+                        Value::from(value)
+                    ))
+                    .at(*span))
+                }
+            }
+        }
+
+        let mut scope = Scope {
+            compiler: self,
+            slots: &slots,
+            closure,
+        };
+
+        Ok(NativeClause {
+            patterns,
+            body: scope.num(&clause.body)?,
+        })
+    }
+}
+
+struct Scope<'a> {
+    compiler: &'a mut NativeCompiler,
+    slots: &'a HashMap<String, usize>,
+    closure: &'a Environment,
+}
+
+impl<'a> Scope<'a> {
+    fn num(&mut self, expr: &Expr) -> Result<Num, MatikaError> {
+        match expr {
+            Expr::Literal { value, .. } if value.number().is_some() => {
+                let number = value.number().unwrap_or_default();
+
+                Ok(Box::new(move |_| Ok(number)))
+            }
+            Expr::Grouping { expr, .. } => self.num(expr),
+            Expr::Variable { name, .. } => {
+                if let Some(slot) = self.slots.get(&name.lexeme).copied() {
+                    return Ok(Box::new(move |args| Ok(args[slot])));
+                }
+
+                match self.closure.get(&name.lexeme) {
+                    Some(value) if value.as_f64().is_some() => {
+                        let number = value.as_f64().unwrap_or_default();
+
+                        Ok(Box::new(move |_| Ok(number)))
+                    }
+                    value => Err(non_numeric(&name.lexeme, value).at(name.span)),
+                }
+            }
+            Expr::Unary { op, right } if op.kind == TokenKind::Minus => {
+                let right = self.num(right)?;
+
+                Ok(Box::new(move |args| Ok(-right(args)?)))
+            }
+            Expr::Binary { left, op, right } => {
+                let left = self.num(left)?;
+                let right = self.num(right)?;
+
+                let node: Num = match op.kind {
+                    TokenKind::Plus => Box::new(move |args| Ok(left(args)? + right(args)?)),
+                    TokenKind::Minus => Box::new(move |args| Ok(left(args)? - right(args)?)),
+                    TokenKind::Star => Box::new(move |args| Ok(left(args)? * right(args)?)),
+                    TokenKind::Slash => Box::new(move |args| Ok(left(args)? / right(args)?)),
+                    TokenKind::Caret => Box::new(move |args| Ok(left(args)?.powf(right(args)?))),
+                    _ => return Err(boolean_result(expr)),
+                };
+
+                Ok(node)
+            }
+            Expr::Conditional {
+                condition,
+                then_branch,
+                else_branch,
+            } => {
+                let condition = self.cond(condition)?;
+                let then_branch = self.num(then_branch)?;
+                let else_branch = self.num(else_branch)?;
+
+                Ok(Box::new(move |args| {
+                    if condition(args)? {
+                        then_branch(args)
+                    } else {
+                        else_branch(args)
+                    }
+                }))
+            }
+            Expr::Call { callee, args, span } => {
+                let target = self.target(callee, args.len())?;
+                let args = args
+                    .iter()
+                    .map(|arg| self.num(arg))
+                    .collect::<Result<Vec<Num>, MatikaError>>()?;
+                let span = *span;
+
+                Ok(Box::new(move |values| {
+                    let values = args
+                        .iter()
+                        .map(|arg| arg(values))
+                        .collect::<Result<Vec<f64>, MatikaError>>()?;
+
+                    match &target {
+                        Target::Done(native) => native.call(&values),
+                        Target::Pending(native) => match native.upgrade() {
+                            Some(native) => native.call(&values),
+                            None => Err(MatikaError::type_error("function was dropped")),
+                        },
+                        Target::Builtin(builtin) => Ok(builtin(&values)),
+                    }
+                    .map_err(|err| err.relocate(span))
+                }))
+            }
+            Expr::List { .. } | Expr::Index { .. } | Expr::Slice { .. } => Err(
+                MatikaError::type_error("lists cannot be compiled to native closures")
+                    .at(expr.span()),
+            ),
+            Expr::Lambda { .. } => Err(MatikaError::type_error(
+                "lambdas cannot be compiled to native closures",
+            )
+            .at(expr.span())),
+            _ => Err(boolean_result(expr)),
+        }
+    }
+
+    fn cond(&mut self, expr: &Expr) -> Result<Cond, MatikaError> {
+        match expr {
+            Expr::Literal {
+                value: LiteralKind::Bool(value),
+                ..
+            } => {
+                let value = *value;
+
+                Ok(Box::new(move |_| Ok(value)))
+            }
+            Expr::Grouping { expr, .. } => self.cond(expr),
+            Expr::Variable { name, .. } if !self.slots.contains_key(&name.lexeme) => {
+                match self.closure.get(&name.lexeme) {
+                    Some(Value::Bool(value)) => Ok(Box::new(move |_| Ok(value))),
+                    value => Err(MatikaError::type_error(format!(
+                        "expected a boolean, got {}",
+                        value.map_or("unbound name", |value| value.type_name())
+                    ))
+                    .at(name.span)),
+                }
+            }
+            Expr::Unary { op, right }
+                if op.kind == TokenKind::Bang || op.kind == TokenKind::Not =>
+            {
+                let right = self.cond(right)?;
+
+                Ok(Box::new(move |args| Ok(!right(args)?)))
+            }
+            Expr::Logical { left, op, right } => {
+                let left = self.cond(left)?;
+                let right = self.cond(right)?;
+
+                let node: Cond = match op.kind {
+                    TokenKind::Or => Box::new(move |args| Ok(left(args)? || right(args)?)),
+                    _ => Box::new(move |args| Ok(left(args)? && right(args)?)),
+                };
+
+                Ok(node)
+            }
+            Expr::Binary { left, op, right } => {
+                let numbers = self.num(left).and_then(|left| Ok((left, self.num(right)?)));
+
+                let err = match numbers {
+                    Ok((left, right)) => {
+                        return comparison(op.kind, left, right).map_err(|err| err.at(op.span))
+                    }
+                    Err(err) => err,
+                };
+
+                // Not numbers, so both sides must be booleans compared for equality.
+                let left = self.cond(left).map_err(|_| err.clone())?;
+                let right = self.cond(right).map_err(|_| err)?;
+
+                let node: Cond = match op.kind {
+                    TokenKind::EqualEqual => Box::new(move |args| Ok(left(args)? == right(args)?)),
+                    TokenKind::BangEqual => Box::new(move |args| Ok(left(args)? != right(args)?)),
+                    _ => {
+                        return Err(MatikaError::type_error(format!(
+                            "cannot compare booleans with '{}'",
+                            op.kind
+                        ))
+                        .at(op.span))
+                    }
+                };
+
+                Ok(node)
+            }
+            _ => Err(MatikaError::type_error("expected a boolean, got number").at(expr.span())),
+        }
+    }
+
+    fn target(&mut self, callee: &Expr, argc: usize) -> Result<Target, MatikaError> {
+        let name = match callee {
+            Expr::Variable { name, .. } if !self.slots.contains_key(&name.lexeme) => name,
+            _ => {
+                return Err(
+                    MatikaError::type_error("only named functions can be compiled")
+                        .at(callee.span()),
+                )
+            }
+        };
+
+        let fnc = match self.closure.get(&name.lexeme) {
+            Some(Value::Function(fnc)) => fnc,
+            value => return Err(non_numeric(&name.lexeme, value).at(name.span)),
+        };
+
+        if !fnc.accepts(argc) {
+            return Err(MatikaError::Arity {
+                callee: fnc.label(),
+                expected: fnc.arity(),
+                got: argc,
+                span: Some(name.span),
+            });
+        }
+
+        if let Some(builtin) = fnc.native() {
+            return Ok(Target::Builtin(builtin));
+        }
+
+        let function = match fnc.as_function() {
+            Some(function) => function,
+            None => {
+                return Err(
+                    MatikaError::type_error(format!("{} does not return a number", fnc))
+                        .at(name.span),
+                )
+            }
+        };
+
+        if let Some(native) = self.compiler.pending.get(&(function as *const Function)) {
+            return Ok(Target::Pending(native.clone()));
+        }
+
+        self.compiler.compile(function).map(Target::Done)
+    }
+}
+
+fn comparison(op: TokenKind, left: Num, right: Num) -> Result<Cond, MatikaError> {
+    let node: Cond = match op {
+        TokenKind::EqualEqual => Box::new(move |args| Ok(left(args)? == right(args)?)),
+        TokenKind::BangEqual => Box::new(move |args| Ok(left(args)? != right(args)?)),
+        TokenKind::Greater | TokenKind::GreaterEqual | TokenKind::Less | TokenKind::LessEqual => {
+            Box::new(move |args| {
+                let left = left(args)?;
+                let right = right(args)?;
+
+                if left.is_nan() || right.is_nan() {
+                    return Err(MatikaError::type_error(format!(
+                        "cannot compare {} and {}",
+                        left, right
+                    )));
+                }
+
+                Ok(match op {
+                    TokenKind::Greater => left > right,
+                    TokenKind::GreaterEqual => left >= right,
+                    TokenKind::Less => left < right,
+                    _ => left <= right,
+                })
+            })
+        }
+        _ => {
+            return Err(MatikaError::type_error(format!(
+                "unsupported binary operator '{}'",
+                op
+            )))
+        }
+    };
+
+    Ok(node)
+}
+
+fn non_numeric(name: &str, value: Option<Value>) -> MatikaError {
+    match value {
+        Some(value) => MatikaError::type_error(format!(
+            "`{}` is a {}, not a number",
+            name,
+            value.type_name()
+        )),
+        None => MatikaError::type_error(format!("`{}` is not defined", name)),
+    }
+}
+
+fn boolean_result(expr: &Expr) -> MatikaError {
+    MatikaError::type_error("expected a number, got boolean").at(expr.span())
+}
@@ -1,83 +1,289 @@
-use crate::{expr::Expr, stmt::{Fnc, Stmt}, token::{LiteralKind, Token, TokenKind}};
+use crate::{
+    error::MatikaError,
+    expr::Expr,
+    stmt::{Fnc, Pattern, Stmt},
+    token::{LiteralKind, Span, Token, TokenKind},
+};
 
 pub struct Parser<'a> {
     tokens: &'a Vec<Token>,
     current: usize,
+    depth: usize,
 }
 
 impl<'a> Parser<'a> {
     pub fn new(tokens: &'a Vec<Token>) -> Self {
-        Self { tokens, current: 0 }
+        Self {
+            tokens,
+            current: 0,
+            depth: 0,
+        }
+    }
+
+    pub fn parse(&mut self) -> Result<Vec<Stmt>, MatikaError> {
+        let (statements, mut errors) = self.parse_with_diagnostics();
+
+        if errors.is_empty() {
+            Ok(statements)
+        } else {
+            Err(errors.remove(0))
+        }
     }
 
-    pub fn parse(&mut self) -> Vec<Stmt> {
+    /// Parses every statement it can, skipping to the next newline or `;` after
+    /// a syntax error, and returns the partial AST alongside all errors found.
+    pub fn parse_with_diagnostics(&mut self) -> (Vec<Stmt>, Vec<MatikaError>) {
         let mut statements: Vec<Stmt> = vec![];
+        let mut errors: Vec<MatikaError> = vec![];
 
-        while !self.is_end() {
-            statements.push(self.declaration());
+        loop {
+            while self
+                .matches(vec![TokenKind::Newline, TokenKind::Semicolon])
+                .is_some()
+            {}
+
+            if self.is_end() {
+                break;
+            }
+
+            match self.declaration().and_then(|stmt| {
+                self.terminator()?;
+
+                Ok(stmt)
+            }) {
+                Ok(stmt) => statements.push(stmt),
+                Err(err) => {
+                    errors.push(err);
+                    self.synchronize();
+                }
+            }
         }
 
-        statements
+        (statements, errors)
     }
 
-    fn declaration(&mut self) -> Stmt {
-        let stmt = self.statement();
+    fn terminator(&mut self) -> Result<(), MatikaError> {
+        if self.is_end()
+            || self
+                .matches(vec![TokenKind::Newline, TokenKind::Semicolon])
+                .is_some()
+        {
+            return Ok(());
+        }
 
-        if let Some(_) = self.matches(vec![TokenKind::Equal]) {
-            let initializer = self.expression();
+        Err(MatikaError::parse(format!(
+            "expected end of statement, found {}",
+            self.describe_current()
+        ))
+        .at(self.current_span()))
+    }
 
-            if let Stmt::Expression(Expr::Variable(var)) = stmt {
-                return Stmt::Variable {
-                    name: var,
-                    initializer,
-                };
-            } else if let Stmt::Expression(Expr::Call { callee, args }) = stmt.clone() {
-                let mut params: Vec<Token> = vec![];
+    fn synchronize(&mut self) {
+        self.depth = 0;
 
-                for var in args {
-                    if let Expr::Variable(var) = var {
-                        params.push(var);
-                    }
-                }
+        while !self.is_end() {
+            if self
+                .matches(vec![TokenKind::Newline, TokenKind::Semicolon])
+                .is_some()
+            {
+                return;
+            }
+
+            self.advance();
+        }
+    }
 
-                if let Expr::Variable(var) = *callee {
-                    return Stmt::Function(Fnc {
+    fn declaration(&mut self) -> Result<Stmt, MatikaError> {
+        let stmt = self.statement()?;
+
+        if self.matches(vec![TokenKind::Equal]).is_some() {
+            let initializer = self.expression()?;
+
+            if let Stmt::Expression(Expr::Variable { name, .. }) = stmt {
+                return Ok(Stmt::Variable { name, initializer });
+            } else if let Stmt::Expression(Expr::Call { callee, args, .. }) = stmt.clone() {
+                let params = args
+                    .iter()
+                    .map(|arg| self.pattern(arg))
+                    .collect::<Result<Vec<Pattern>, MatikaError>>()?;
+
+                if let Expr::Variable { name: var, .. } = *callee {
+                    return Ok(Stmt::Function(Fnc {
                         name: var,
                         params,
                         body: initializer,
-                    });
+                    }));
                 }
-            } else {
-                panic!("Declaration not supported")
             }
+
+            return Err(MatikaError::parse("declaration not supported").at(stmt.span()));
         }
 
-        stmt
+        Ok(stmt)
     }
 
-    fn statement(&mut self) -> Stmt {
-        if let Some(_) = self.matches(vec![TokenKind::Print]) {
+    /// Parameters are either names or literals, e.g. `fact(0) = 1`.
+    fn pattern(&self, arg: &Expr) -> Result<Pattern, MatikaError> {
+        match arg {
+            Expr::Variable { name, .. } => Ok(Pattern::Binding(name.clone())),
+            Expr::Literal { value, span } => Ok(Pattern::Literal {
+                value: value.clone(),
+                span: *span,
+            }),
+            Expr::Unary { op, right } if op.kind == TokenKind::Minus => match right.as_ref() {
+                Expr::Literal { value, span } if value.negate().is_some() => Ok(Pattern::Literal {
+                    value: value.negate().unwrap_or_else(|| value.clone()),
+                    span: op.span.to(*span),
+                }),
+                _ => Err(MatikaError::parse("expected a parameter name or literal").at(arg.span())),
+            },
+            _ => Err(MatikaError::parse("expected a parameter name or literal").at(arg.span())),
+        }
+    }
+
+    fn statement(&mut self) -> Result<Stmt, MatikaError> {
+        if self.matches(vec![TokenKind::Print]).is_some() {
             return self.print_statement();
         }
 
-        Stmt::Expression(self.expression())
+        Ok(Stmt::Expression(self.expression()?))
+    }
+
+    fn print_statement(&mut self) -> Result<Stmt, MatikaError> {
+        let value = self.expression()?;
+
+        Ok(Stmt::Print(value))
+    }
+
+    fn expression(&mut self) -> Result<Expr, MatikaError> {
+        self.conditional()
+    }
+
+    fn conditional(&mut self) -> Result<Expr, MatikaError> {
+        let expr = self.or()?;
+
+        if self.matches(vec![TokenKind::If]).is_some() {
+            let condition = self.or()?;
+
+            self.consume(TokenKind::Else)?;
+
+            let else_branch = self.conditional()?;
+
+            return Ok(Expr::Conditional {
+                condition: Box::new(condition),
+                then_branch: Box::new(expr),
+                else_branch: Box::new(else_branch),
+            });
+        }
+
+        Ok(expr)
+    }
+
+    fn or(&mut self) -> Result<Expr, MatikaError> {
+        let mut expr = self.and()?;
+
+        while let Some(op) = self.matches(vec![TokenKind::Or]) {
+            let right = self.and()?;
+
+            expr = Expr::Logical {
+                left: Box::new(expr),
+                right: Box::new(right),
+                op,
+            };
+        }
+
+        Ok(expr)
+    }
+
+    fn and(&mut self) -> Result<Expr, MatikaError> {
+        let mut expr = self.not()?;
+
+        while let Some(op) = self.matches(vec![TokenKind::And]) {
+            let right = self.not()?;
+
+            expr = Expr::Logical {
+                left: Box::new(expr),
+                right: Box::new(right),
+                op,
+            };
+        }
+
+        Ok(expr)
+    }
+
+    fn not(&mut self) -> Result<Expr, MatikaError> {
+        if let Some(op) = self.matches(vec![TokenKind::Not]) {
+            let right = self.not()?;
+
+            return Ok(Expr::Unary {
+                op,
+                right: Box::new(right),
+            });
+        }
+
+        self.equality()
     }
 
-    fn print_statement(&mut self) -> Stmt {
-        let value = self.expression();
+    fn equality(&mut self) -> Result<Expr, MatikaError> {
+        let mut expr = self.comparison()?;
+
+        while let Some(op) = self.matches(vec![TokenKind::EqualEqual, TokenKind::BangEqual]) {
+            let right = self.comparison()?;
+
+            expr = Expr::Binary {
+                left: Box::new(expr),
+                right: Box::new(right),
+                op,
+            };
+        }
 
-        Stmt::Print(value)
+        Ok(expr)
     }
 
-    fn expression(&mut self) -> Expr {
-        self.term()
+    /// Chained comparisons read like maths: `0 <= x < 1` means `0 <= x and x < 1`.
+    fn comparison(&mut self) -> Result<Expr, MatikaError> {
+        let comparisons = vec![
+            TokenKind::Greater,
+            TokenKind::GreaterEqual,
+            TokenKind::Less,
+            TokenKind::LessEqual,
+        ];
+
+        let mut left = self.term()?;
+        let mut expr: Option<Expr> = None;
+
+        while let Some(op) = self.matches(comparisons.clone()) {
+            let right = self.term()?;
+            let span = op.span;
+
+            let comparison = Expr::Binary {
+                left: Box::new(left),
+                right: Box::new(right.clone()),
+                op,
+            };
+
+            expr = Some(match expr {
+                Some(chain) => Expr::Logical {
+                    left: Box::new(chain),
+                    right: Box::new(comparison),
+                    op: Token::synthetic(TokenKind::And, "and", span),
+                },
+                None => comparison,
+            });
+
+            left = right;
+        }
+
+        Ok(expr.unwrap_or(left))
     }
 
-    fn term(&mut self) -> Expr {
-        let mut expr = self.factor();
+    fn term(&mut self) -> Result<Expr, MatikaError> {
+        let mut expr = self.factor()?;
 
-        while let Some(op) = self.matches(vec![TokenKind::Minus, TokenKind::Plus]) {
-            let right = self.factor();
+        while let Some(op) =
+            self.matches(vec![TokenKind::Minus, TokenKind::Plus, TokenKind::PlusPlus])
+        {
+            let right = self.factor()?;
 
             expr = Expr::Binary {
                 left: Box::new(expr),
@@ -86,14 +292,16 @@ impl<'a> Parser<'a> {
             };
         }
 
-        expr
+        Ok(expr)
     }
 
-    fn factor(&mut self) -> Expr {
-        let mut expr = self.power();
+    fn factor(&mut self) -> Result<Expr, MatikaError> {
+        let mut expr = self.power()?;
 
-        while let Some(op) = self.matches(vec![TokenKind::Star, TokenKind::Slash]) {
-            let right = self.power();
+        while let Some(op) =
+            self.matches(vec![TokenKind::Star, TokenKind::Slash, TokenKind::Compose])
+        {
+            let right = self.power()?;
 
             expr = Expr::Binary {
                 left: Box::new(expr),
@@ -102,14 +310,21 @@ impl<'a> Parser<'a> {
             };
         }
 
-        expr
+        Ok(expr)
     }
 
-    fn power(&mut self) -> Expr {
-        let mut expr = self.unary();
+    fn power(&mut self) -> Result<Expr, MatikaError> {
+        let mut expr = self.unary()?;
 
         while let Some(op) = self.matches(vec![TokenKind::Caret]) {
-            let right = self.primary();
+            // A signed exponent needs no parentheses, as in `2^-1` or `f^-1`.
+            let right = match self.matches(vec![TokenKind::Minus]) {
+                Some(minus) => Expr::Unary {
+                    op: minus,
+                    right: Box::new(self.primary()?),
+                },
+                None => self.primary()?,
+            };
 
             expr = Expr::Binary {
                 left: Box::new(expr),
@@ -118,91 +333,323 @@ impl<'a> Parser<'a> {
             };
         }
 
-        expr
+        Ok(expr)
     }
 
-    fn unary(&mut self) -> Expr {
-        if let Some(op) = self.matches(vec![TokenKind::Minus]) {
-            let right = self.unary();
+    fn unary(&mut self) -> Result<Expr, MatikaError> {
+        // `√x` is sugar for `sqrt(x)`.
+        if let Some(op) = self.matches(vec![TokenKind::Root]) {
+            let right = self.unary()?;
+            let span = op.span.to(right.span());
+            let callee = Token::synthetic(TokenKind::Identifier, "sqrt", op.span);
+
+            return Ok(Expr::Call {
+                callee: Box::new(Expr::Variable {
+                    name: callee,
+                    depth: None,
+                }),
+                args: vec![right],
+                span,
+            });
+        }
+
+        if let Some(op) = self.matches(vec![TokenKind::Minus, TokenKind::Bang]) {
+            let right = self.unary()?;
 
-            return Expr::Unary {
+            return Ok(Expr::Unary {
                 op,
                 right: Box::new(right),
-            };
+            });
         }
 
         self.call()
     }
 
-    fn call(&mut self) -> Expr {
-        let mut expr = self.primary();
+    fn call(&mut self) -> Result<Expr, MatikaError> {
+        let mut expr = self.primary()?;
+
+        loop {
+            if self.matches(vec![TokenKind::LeftBracket]).is_some() {
+                expr = self.index(expr)?;
+                continue;
+            }
+
+            if self.matches(vec![TokenKind::LeftParen]).is_none() {
+                break;
+            }
+
+            self.depth += 1;
 
-        while let Some(_) = self.matches(vec![TokenKind::LeftParen]) {
             let mut args: Vec<Expr> = vec![];
 
             if !self.check(TokenKind::RightParen) {
                 loop {
-                    args.push(self.expression());
+                    args.push(self.expression()?);
 
-                    if let None = self.matches(vec![TokenKind::Comma]) {
+                    if self.matches(vec![TokenKind::Comma]).is_none() {
                         break;
                     }
                 }
             }
 
-            self.consume(TokenKind::RightParen);
+            let paren = self.consume(TokenKind::RightParen)?.span;
+            let span = expr.span().to(paren);
+
+            self.depth -= 1;
 
             match expr {
-                Expr::Literal(LiteralKind::Number(_)) => {
+                Expr::Literal { .. } => {
                     if args.len() == 1 {
+                        let star = Token::star(expr.span());
+
                         expr = Expr::Binary {
                             left: Box::new(expr),
                             right: Box::new(args[0].clone()),
-                            op: Token::star(),
+                            op: star,
                         };
                     } else {
-                        panic!();
+                        return Err(MatikaError::parse(
+                            "a number can only be multiplied by a single parenthesized expression",
+                        )
+                        .at(span));
                     }
-                },
+                }
                 _ => {
                     expr = Expr::Call {
                         callee: Box::new(expr),
                         args,
+                        span,
                     };
                 }
             }
-
         }
 
-        expr
+        Ok(expr)
+    }
+
+    /// Parses the rest of `xs[i]` or `xs[start:end]` after the opening bracket.
+    fn index(&mut self, object: Expr) -> Result<Expr, MatikaError> {
+        self.depth += 1;
+
+        let start = if self.check(TokenKind::Colon) {
+            None
+        } else {
+            Some(Box::new(self.expression()?))
+        };
+
+        let expr = match start {
+            Some(index) if self.matches(vec![TokenKind::Colon]).is_none() => {
+                let bracket = self.consume(TokenKind::RightBracket)?.span;
+
+                Expr::Index {
+                    span: object.span().to(bracket),
+                    object: Box::new(object),
+                    index,
+                }
+            }
+            start => {
+                if start.is_none() {
+                    self.consume(TokenKind::Colon)?;
+                }
+
+                let end = if self.check(TokenKind::RightBracket) {
+                    None
+                } else {
+                    Some(Box::new(self.expression()?))
+                };
+
+                let bracket = self.consume(TokenKind::RightBracket)?.span;
+
+                Expr::Slice {
+                    span: object.span().to(bracket),
+                    object: Box::new(object),
+                    start,
+                    end,
+                }
+            }
+        };
+
+        self.depth -= 1;
+
+        Ok(expr)
     }
 
-    fn primary(&mut self) -> Expr {
+    fn primary(&mut self) -> Result<Expr, MatikaError> {
+        if self.lambda_ahead() {
+            return self.lambda();
+        }
+
         if let Some(num) = self.matches(vec![TokenKind::Number]) {
-            return Expr::Literal(num.literal.unwrap());
+            if let Some(value) = num.literal {
+                return Ok(Expr::Literal {
+                    value,
+                    span: num.span,
+                });
+            }
+        }
+
+        if let Some(boolean) = self.matches(vec![TokenKind::True, TokenKind::False]) {
+            return Ok(Expr::Literal {
+                value: LiteralKind::Bool(boolean.kind == TokenKind::True),
+                span: boolean.span,
+            });
         }
 
         if let Some(var) = self.matches(vec![TokenKind::Identifier]) {
-            return Expr::Variable(var);
+            return Ok(Expr::Variable {
+                name: var,
+                depth: None,
+            });
+        }
+
+        if let Some(bracket) = self.matches(vec![TokenKind::LeftBracket]) {
+            self.depth += 1;
+
+            let mut elements = vec![];
+
+            if !self.check(TokenKind::RightBracket) {
+                loop {
+                    elements.push(self.expression()?);
+
+                    if self.matches(vec![TokenKind::Comma]).is_none() {
+                        break;
+                    }
+                }
+            }
+
+            let closing = self.consume(TokenKind::RightBracket)?.span;
+
+            self.depth -= 1;
+
+            return Ok(Expr::List {
+                elements,
+                span: bracket.span.to(closing),
+            });
+        }
+
+        if let Some(paren) = self.matches(vec![TokenKind::LeftParen]) {
+            self.depth += 1;
+
+            let expr = self.expression()?;
+
+            let closing = self.consume(TokenKind::RightParen)?.span;
+
+            self.depth -= 1;
+
+            return Ok(Expr::Grouping {
+                expr: Box::new(expr),
+                span: paren.span.to(closing),
+            });
+        }
+
+        Err(MatikaError::parse(format!(
+            "expected expression, found {}",
+            self.describe_current()
+        ))
+        .at(self.current_span()))
+    }
+
+    /// Whether the next tokens start a lambda, `x -> ...` or `(x, y) -> ...`.
+    fn lambda_ahead(&self) -> bool {
+        let mut kinds = self.tokens[self.position()..]
+            .iter()
+            .map(|token| token.kind)
+            .filter(|kind| *kind != TokenKind::Newline);
+
+        match kinds.next() {
+            Some(TokenKind::Identifier) => kinds.next() == Some(TokenKind::Arrow),
+            Some(TokenKind::LeftParen) => {
+                loop {
+                    match kinds.next() {
+                        Some(TokenKind::Identifier) => (),
+                        Some(TokenKind::RightParen) => break,
+                        _ => return false,
+                    }
+
+                    match kinds.next() {
+                        Some(TokenKind::Comma) => (),
+                        Some(TokenKind::RightParen) => break,
+                        _ => return false,
+                    }
+                }
+
+                kinds.next() == Some(TokenKind::Arrow)
+            }
+            _ => false,
         }
+    }
+
+    /// The body extends as far as an expression can, so `x -> x + 1` is one lambda.
+    fn lambda(&mut self) -> Result<Expr, MatikaError> {
+        let start = self.current_span();
+        let mut params = vec![];
+
+        if self.matches(vec![TokenKind::LeftParen]).is_some() {
+            self.depth += 1;
 
-        if let Some(_) = self.matches(vec![TokenKind::LeftParen]) {
-            let expr = self.expression();
+            if !self.check(TokenKind::RightParen) {
+                loop {
+                    let param = self.consume(TokenKind::Identifier)?.clone();
 
-            self.consume(TokenKind::RightParen);
+                    params.push(Pattern::Binding(param));
 
-            return Expr::Grouping(Box::new(expr));
+                    if self.matches(vec![TokenKind::Comma]).is_none() {
+                        break;
+                    }
+                }
+            }
+
+            self.consume(TokenKind::RightParen)?;
+            self.depth -= 1;
+        } else {
+            let param = self.consume(TokenKind::Identifier)?.clone();
+
+            params.push(Pattern::Binding(param));
         }
 
-        panic!()
+        let arrow = self.consume(TokenKind::Arrow)?.span;
+        let body = self.expression()?;
+        let span = start.to(body.span());
+
+        Ok(Expr::Lambda {
+            fnc: Box::new(Fnc {
+                name: Token::synthetic(TokenKind::Identifier, "lambda", arrow),
+                params,
+                body,
+            }),
+            span,
+        })
     }
 
-    fn consume(&mut self, kind: TokenKind) -> &Token {
+    fn consume(&mut self, kind: TokenKind) -> Result<&Token, MatikaError> {
         if self.check(kind) {
-            return self.advance();
+            return Ok(self.advance());
         }
 
-        panic!();
+        Err(MatikaError::parse(format!(
+            "expected '{}', found {}",
+            kind,
+            self.describe_current()
+        ))
+        .at(self.current_span()))
+    }
+
+    // Errors are reported against the raw next token, so a line break skipped
+    // inside an unclosed parenthesis is blamed instead of the following line.
+    fn current_span(&self) -> Span {
+        self.tokens
+            .get(self.current)
+            .or_else(|| self.tokens.last())
+            .map(|token| token.span)
+            .unwrap_or_default()
+    }
+
+    fn describe_current(&self) -> String {
+        match self.tokens.get(self.current) {
+            Some(token) if token.kind == TokenKind::Newline => token.kind.to_string(),
+            Some(token) if token.kind != TokenKind::Eof => format!("'{}'", token.lexeme),
+            _ => TokenKind::Eof.to_string(),
+        }
     }
 
     fn matches(&mut self, forms: Vec<TokenKind>) -> Option<Token> {
@@ -225,14 +672,28 @@ impl<'a> Parser<'a> {
 
     fn advance(&mut self) -> &Token {
         if !self.is_end() {
-            self.current = self.current + 1;
+            self.current = self.position() + 1;
         }
 
         self.previous()
     }
 
     fn peek(&self) -> Option<&Token> {
-        self.tokens.get(self.current)
+        self.tokens.get(self.position())
+    }
+
+    /// Index of the next significant token; line breaks are insignificant inside parentheses.
+    fn position(&self) -> usize {
+        let mut position = self.current;
+
+        if self.depth > 0 {
+            while let Some(TokenKind::Newline) = self.tokens.get(position).map(|token| &token.kind)
+            {
+                position += 1;
+            }
+        }
+
+        position
     }
 
     fn previous(&self) -> &Token {
@@ -246,3 +707,28 @@ impl<'a> Parser<'a> {
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::scanner::Scanner;
+
+    #[test]
+    fn parsing_recovers_after_each_error() {
+        let mut scanner = Scanner::new(String::from("a = 1\nb = (2\nc = 3\nd = 4 +\ne = 5"));
+        let tokens = scanner.scan().unwrap();
+        let (stmts, errors) = Parser::new(tokens).parse_with_diagnostics();
+
+        let names: Vec<&str> = stmts
+            .iter()
+            .filter_map(|stmt| match stmt {
+                Stmt::Variable { name, .. } => Some(name.lexeme.as_str()),
+                _ => None,
+            })
+            .collect();
+        let lines: Vec<usize> = errors.iter().map(|err| err.span().unwrap().line).collect();
+
+        assert_eq!(names, ["a", "c", "e"]);
+        assert_eq!(lines, [2, 4]);
+    }
+}
@@ -0,0 +1,353 @@
+use std::fmt;
+
+use crate::expr::Expr;
+use crate::token::TokenKind;
+
+// Binding strength of each grammar level, loosest first, mirroring the
+// parser's descent from `conditional` down to `primary`.
+const LAMBDA: u8 = 0;
+const CONDITIONAL: u8 = 1;
+const OR: u8 = 2;
+const AND: u8 = 3;
+const NOT: u8 = 4;
+const EQUALITY: u8 = 5;
+const COMPARISON: u8 = 6;
+const TERM: u8 = 7;
+const FACTOR: u8 = 8;
+const POWER: u8 = 9;
+const UNARY: u8 = 10;
+const CALL: u8 = 11;
+const PRIMARY: u8 = 12;
+
+/// Prints the expression back as source, with only the parentheses needed for
+/// it to parse to the same tree.
+impl fmt::Display for Expr {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Expr::Binary { left, op, right } if op.kind == TokenKind::Caret => {
+                write!(f, "{}^{}", Operand(left, POWER), Exponent(right))
+            }
+            Expr::Binary { left, op, right } => {
+                let level = binary(op.kind);
+
+                match op.kind {
+                    TokenKind::Star if implicit(left, right) => {
+                        write!(f, "{}{}", left, right)
+                    }
+                    TokenKind::Star | TokenKind::Slash => write!(
+                        f,
+                        "{}{}{}",
+                        Operand(left, level),
+                        op.kind,
+                        Operand(right, level + 1)
+                    ),
+                    // Chained comparisons parse as `and`, so neither side may be one.
+                    _ if level == COMPARISON => write!(
+                        f,
+                        "{} {} {}",
+                        Operand(left, level + 1),
+                        op.kind,
+                        Operand(right, level + 1)
+                    ),
+                    _ => write!(
+                        f,
+                        "{} {} {}",
+                        Operand(left, level),
+                        op.kind,
+                        Operand(right, level + 1)
+                    ),
+                }
+            }
+            Expr::Logical { left, op, right } => {
+                let level = precedence(self);
+
+                write!(
+                    f,
+                    "{} {} {}",
+                    Operand(left, level),
+                    op.kind,
+                    Operand(right, level + 1)
+                )
+            }
+            Expr::Conditional {
+                condition,
+                then_branch,
+                else_branch,
+            } => write!(
+                f,
+                "{} if {} else {}",
+                Operand(then_branch, OR),
+                Operand(condition, OR),
+                Operand(else_branch, CONDITIONAL)
+            ),
+            Expr::Grouping { expr, .. } => write!(f, "{}", expr),
+            Expr::Literal { value, .. } => write!(f, "{}", value),
+            Expr::Variable { name, .. } => write!(f, "{}", name.lexeme),
+            Expr::Unary { op, right } if op.kind == TokenKind::Not => {
+                write!(f, "not {}", Operand(right, NOT))
+            }
+            Expr::Unary { op, right } => write!(f, "{}{}", op.kind, Operand(right, UNARY)),
+            Expr::Call { callee, args, .. } => {
+                write!(f, "{}({})", Operand(callee, CALL), List(args))
+            }
+            Expr::List { elements, .. } => write!(f, "[{}]", List(elements)),
+            Expr::Index { object, index, .. } => {
+                write!(f, "{}[{}]", Operand(object, CALL), index)
+            }
+            Expr::Slice {
+                object, start, end, ..
+            } => {
+                write!(f, "{}[", Operand(object, CALL))?;
+
+                if let Some(start) = start {
+                    write!(f, "{}", start)?;
+                }
+
+                write!(f, ":")?;
+
+                if let Some(end) = end {
+                    write!(f, "{}", end)?;
+                }
+
+                write!(f, "]")
+            }
+            Expr::Lambda { fnc, .. } => write!(f, "{}", fnc),
+        }
+    }
+}
+
+/// How tightly `expr` binds, groupings being transparent.
+fn precedence(expr: &Expr) -> u8 {
+    match expr {
+        Expr::Lambda { .. } => LAMBDA,
+        Expr::Conditional { .. } => CONDITIONAL,
+        Expr::Logical { op, .. } if op.kind == TokenKind::Or => OR,
+        Expr::Logical { .. } => AND,
+        Expr::Unary { op, .. } if op.kind == TokenKind::Not => NOT,
+        Expr::Binary { op, .. } => binary(op.kind),
+        Expr::Unary { .. } => UNARY,
+        Expr::Literal { value, .. } if value.to_string().starts_with('-') => UNARY,
+        Expr::Call { .. } | Expr::Index { .. } | Expr::Slice { .. } => CALL,
+        Expr::Grouping { expr, .. } => precedence(expr),
+        Expr::Literal { .. } | Expr::Variable { .. } | Expr::List { .. } => PRIMARY,
+    }
+}
+
+/// Whether `left * right` can be written `2x`, as the scanner reads a number
+/// followed by a name. The number must end in a digit, and the name must not
+/// read as more of the number: `e` would start an exponent, `_` a digit
+/// separator and `x`, `b` or `o` after a lone `0` a radix prefix.
+fn implicit(left: &Expr, right: &Expr) -> bool {
+    let number = match left {
+        Expr::Literal { value, .. } if value.number().is_some() => value.to_string(),
+        _ => return false,
+    };
+
+    let named = |expr: &Expr| match expr {
+        Expr::Variable { name, .. } => {
+            let radix = number == "0" && name.lexeme.starts_with(['x', 'X', 'b', 'B', 'o', 'O']);
+
+            !radix && !name.lexeme.starts_with(['e', 'E', '_'])
+        }
+        _ => false,
+    };
+
+    number.ends_with(|c: char| c.is_ascii_digit())
+        && match right {
+            Expr::Grouping { expr, .. } => implicit(left, expr),
+            Expr::Call { callee, .. } => named(callee),
+            Expr::Binary { left, op, .. } if op.kind == TokenKind::Caret => named(left),
+            expr => named(expr),
+        }
+}
+
+/// The grammar level of a binary operator.
+fn binary(op: TokenKind) -> u8 {
+    match op {
+        TokenKind::EqualEqual | TokenKind::BangEqual => EQUALITY,
+        TokenKind::Greater | TokenKind::GreaterEqual | TokenKind::Less | TokenKind::LessEqual => {
+            COMPARISON
+        }
+        TokenKind::Star | TokenKind::Slash | TokenKind::Compose => FACTOR,
+        TokenKind::Caret => POWER,
+        _ => TERM,
+    }
+}
+
+/// An operand that binds at least as tightly as `.1`, in parentheses otherwise.
+struct Operand<'a>(&'a Expr, u8);
+
+impl fmt::Display for Operand<'_> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        if precedence(self.0) < self.1 {
+            write!(f, "({})", self.0)
+        } else {
+            write!(f, "{}", self.0)
+        }
+    }
+}
+
+/// The exponent of `^`, which is a primary, optionally negated.
+struct Exponent<'a>(&'a Expr);
+
+impl fmt::Display for Exponent<'_> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self.0 {
+            Expr::Grouping { expr, .. } => write!(f, "{}", Exponent(expr)),
+            Expr::Literal { .. } => write!(f, "{}", self.0),
+            Expr::Unary { op, right }
+                if op.kind == TokenKind::Minus && precedence(right) == PRIMARY =>
+            {
+                write!(f, "{}", self.0)
+            }
+            expr => write!(f, "{}", Operand(expr, PRIMARY)),
+        }
+    }
+}
+
+struct List<'a>(&'a [Expr]);
+
+impl fmt::Display for List<'_> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        for (idx, expr) in self.0.iter().enumerate() {
+            if idx > 0 {
+                write!(f, ", ")?;
+            }
+
+            write!(f, "{}", expr)?;
+        }
+
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::tests::{eval, session};
+    use crate::token::{LiteralKind, Span, Token};
+    use crate::{Parser, Scanner, Stmt};
+
+    fn parse(src: &str) -> Expr {
+        let mut scanner = Scanner::new(src.to_string());
+        let tokens = scanner.scan().unwrap();
+
+        match Parser::new(tokens).parse().unwrap().remove(0) {
+            Stmt::Expression(expr) => expr,
+            stmt => panic!("expected an expression, got {:?}", stmt),
+        }
+    }
+
+    /// The tree with every node in parentheses, groupings left out.
+    fn shape(expr: &Expr) -> String {
+        let list = |exprs: &[Expr]| exprs.iter().map(shape).collect::<Vec<_>>().join(" ");
+
+        match expr {
+            Expr::Binary { left, op, right } | Expr::Logical { left, op, right } => {
+                format!("({} {} {})", op.kind, shape(left), shape(right))
+            }
+            Expr::Unary { op, right } => format!("({} {})", op.kind, shape(right)),
+            Expr::Grouping { expr, .. } => shape(expr),
+            Expr::Conditional {
+                condition,
+                then_branch,
+                else_branch,
+            } => format!(
+                "(if {} {} {})",
+                shape(condition),
+                shape(then_branch),
+                shape(else_branch)
+            ),
+            Expr::Call { callee, args, .. } => format!("(call {} {})", shape(callee), list(args)),
+            Expr::List { elements, .. } => format!("[{}]", list(elements)),
+            Expr::Index { object, index, .. } => format!("(at {} {})", shape(object), shape(index)),
+            Expr::Lambda { fnc, .. } => format!("(-> {})", shape(&fnc.body)),
+            expr => expr.to_string(),
+        }
+    }
+
+    /// Prints `expr` and checks that the result parses back to the same tree.
+    fn round_trip(expr: &Expr) -> String {
+        let printed = expr.to_string();
+
+        assert_eq!(
+            shape(&parse(&printed)),
+            shape(expr),
+            "{} re-parses differently",
+            printed
+        );
+
+        printed
+    }
+
+    fn times(left: Expr, name: &str) -> Expr {
+        Expr::Binary {
+            left: Box::new(left),
+            op: Token::synthetic(TokenKind::Star, "*", Span::default()),
+            right: Box::new(Expr::Variable {
+                name: Token::synthetic(TokenKind::Identifier, name, Span::default()),
+                depth: None,
+            }),
+        }
+    }
+
+    fn number(value: LiteralKind) -> Expr {
+        Expr::Literal {
+            value,
+            span: Span::default(),
+        }
+    }
+
+    #[test]
+    fn sources_round_trip() {
+        for src in [
+            "2x + 3y^2",
+            "(a + b)*(a - b)",
+            "a - (b - c)",
+            "a/(b*c)",
+            "2^3^4",
+            "(2^3)^4",
+            "-x^2",
+            "-(x^2)",
+            "2(x + 1)",
+            "2sin(x)",
+            "f(x)(y)",
+            "a < b == (c < d)",
+            "not a and b or c",
+            "a if b else c if d else e",
+            "(a if b else c) + 1",
+            "[1, 2x, [3]][0]",
+            "x -> x^2 + 1",
+            "2*e",
+            "3*E1",
+        ] {
+            round_trip(&parse(src));
+        }
+    }
+
+    #[test]
+    fn names_the_number_lexer_continues_keep_their_star() {
+        let integer = |n: i64| number(LiteralKind::Integer(n.into()));
+
+        assert_eq!(round_trip(&times(integer(2), "_1")), "2*_1");
+        assert_eq!(round_trip(&times(integer(2), "_a")), "2*_a");
+        assert_eq!(round_trip(&times(integer(2), "e1")), "2*e1");
+        assert_eq!(round_trip(&times(integer(0), "x1")), "0*x1");
+        assert_eq!(round_trip(&times(integer(0), "b1")), "0*b1");
+        assert_eq!(round_trip(&times(integer(0), "o7")), "0*o7");
+        assert_eq!(round_trip(&times(integer(10), "x1")), "10x1");
+        assert_eq!(round_trip(&times(integer(2), "y")), "2y");
+        assert_eq!(
+            times(number(LiteralKind::Number(f64::INFINITY)), "x").to_string(),
+            "inf*x"
+        );
+    }
+
+    #[test]
+    fn symbolic_results_round_trip() {
+        let printed = session(&["y = 2 * _1", "y"]).unwrap();
+
+        assert_eq!(printed, "2*_1");
+        assert_eq!(eval(&printed), "2*_1");
+    }
+}
@@ -0,0 +1,361 @@
+use std::collections::HashMap;
+
+use crate::environment::Environment;
+use crate::error::{MatikaError, Warning};
+use crate::expr::Expr;
+use crate::stmt::{Fnc, Pattern, Stmt};
+use crate::token::{Span, Token};
+use crate::value::Value;
+
+struct Binding {
+    token: Token,
+    used: bool,
+}
+
+/// Static pass between the parser and the interpreter. It binds every variable
+/// to the depth of the scope declaring it, rejects calls to unknown functions or
+/// with the wrong number of arguments, and warns about likely mistakes.
+pub struct Resolver<'a> {
+    globals: &'a Environment,
+    declared: HashMap<String, Option<u64>>,
+    scopes: Vec<HashMap<String, Binding>>,
+    errors: Vec<MatikaError>,
+    warnings: Vec<Warning>,
+}
+
+impl<'a> Resolver<'a> {
+    pub fn new(globals: &'a Environment) -> Self {
+        Self {
+            globals,
+            declared: HashMap::new(),
+            scopes: vec![],
+            errors: vec![],
+            warnings: vec![],
+        }
+    }
+
+    pub fn resolve(mut self, stmts: &mut [Stmt]) -> (Vec<MatikaError>, Vec<Warning>) {
+        for stmt in stmts.iter_mut() {
+            self.resolve_stmt(stmt);
+        }
+
+        (self.errors, self.warnings)
+    }
+
+    fn resolve_stmt(&mut self, stmt: &mut Stmt) {
+        match stmt {
+            Stmt::Expression(expr) | Stmt::Print(expr) => self.resolve_expr(expr),
+            Stmt::Variable { name, initializer } => {
+                self.resolve_expr(initializer);
+
+                self.declared.insert(name.lexeme.clone(), None);
+            }
+            Stmt::Function(fnc) => self.resolve_function(fnc),
+        }
+    }
+
+    fn resolve_function(&mut self, fnc: &mut Fnc) {
+        // Declared before the body so clauses can recurse.
+        self.declared
+            .insert(fnc.name.lexeme.clone(), Some(fnc.params.len() as u64));
+
+        self.resolve_clause(fnc);
+    }
+
+    /// Resolves the body in a scope holding the parameters, warning about unused ones.
+    fn resolve_clause(&mut self, fnc: &mut Fnc) {
+        let mut scope = HashMap::new();
+
+        for param in fnc.params.iter() {
+            if let Pattern::Binding(token) = param {
+                scope.insert(
+                    token.lexeme.clone(),
+                    Binding {
+                        token: token.clone(),
+                        used: false,
+                    },
+                );
+            }
+        }
+
+        self.scopes.push(scope);
+        self.resolve_expr(&mut fnc.body);
+
+        let scope = self.scopes.pop().unwrap_or_default();
+        let mut unused: Vec<&Binding> = scope
+            .values()
+            .filter(|binding| !binding.used && !binding.token.lexeme.starts_with('_'))
+            .collect();
+
+        unused.sort_by_key(|binding| binding.token.span.start);
+
+        for binding in unused {
+            self.warnings.push(Warning::new(
+                format!(
+                    "parameter `{}` of `{}` is never used",
+                    binding.token.lexeme, fnc.name.lexeme
+                ),
+                binding.token.span,
+            ));
+        }
+    }
+
+    fn resolve_expr(&mut self, expr: &mut Expr) {
+        match expr {
+            Expr::Binary { left, right, .. } | Expr::Logical { left, right, .. } => {
+                self.resolve_expr(left);
+                self.resolve_expr(right);
+            }
+            Expr::Conditional {
+                condition,
+                then_branch,
+                else_branch,
+            } => {
+                self.resolve_expr(condition);
+                self.resolve_expr(then_branch);
+                self.resolve_expr(else_branch);
+            }
+            Expr::Grouping { expr, .. } => self.resolve_expr(expr),
+            Expr::List { elements, .. } => {
+                for element in elements.iter_mut() {
+                    self.resolve_expr(element);
+                }
+            }
+            Expr::Index { object, index, .. } => {
+                self.resolve_expr(object);
+                self.resolve_expr(index);
+            }
+            Expr::Slice {
+                object, start, end, ..
+            } => {
+                self.resolve_expr(object);
+
+                for bound in start.iter_mut().chain(end.iter_mut()) {
+                    self.resolve_expr(bound);
+                }
+            }
+            Expr::Unary { right, .. } => self.resolve_expr(right),
+            Expr::Lambda { fnc, .. } => self.resolve_clause(fnc),
+            Expr::Literal { .. } => (),
+            Expr::Variable { name, depth } => {
+                *depth = self.resolve_local(&name.lexeme);
+
+                if depth.is_none() && !self.is_known(&name.lexeme) {
+                    self.unknown_variable(name);
+                }
+            }
+            Expr::Call { callee, args, span } => {
+                if let Expr::Variable { name, depth } = callee.as_mut() {
+                    *depth = self.resolve_local(&name.lexeme);
+
+                    if depth.is_none() {
+                        self.check_call(name, args.len(), *span);
+                    }
+                } else {
+                    self.resolve_expr(callee);
+                }
+
+                for arg in args.iter_mut() {
+                    self.resolve_expr(arg);
+                }
+            }
+        }
+    }
+
+    fn resolve_local(&mut self, name: &str) -> Option<usize> {
+        let innermost = self.scopes.len();
+
+        for (idx, scope) in self.scopes.iter_mut().enumerate().rev() {
+            if let Some(binding) = scope.get_mut(name) {
+                binding.used = true;
+
+                return Some(innermost - 1 - idx);
+            }
+        }
+
+        None
+    }
+
+    fn check_call(&mut self, name: &Token, got: usize, span: Span) {
+        if !self.is_known(&name.lexeme) {
+            let suggestion = self.suggest(&name.lexeme);
+
+            // Function bodies may call functions that are only defined later.
+            if self.scopes.is_empty() {
+                self.errors.push(MatikaError::UnknownFunction {
+                    name: name.lexeme.clone(),
+                    suggestion,
+                    span: Some(name.span),
+                });
+            } else {
+                self.warnings.push(Warning::new(
+                    MatikaError::UnknownFunction {
+                        name: name.lexeme.clone(),
+                        suggestion,
+                        span: None,
+                    }
+                    .message(),
+                    name.span,
+                ));
+            }
+
+            return;
+        }
+
+        if let Some((callee, expected)) = self.arity_mismatch(&name.lexeme, got) {
+            self.errors.push(MatikaError::Arity {
+                callee,
+                expected,
+                got,
+                span: Some(span),
+            });
+        }
+    }
+
+    /// Unknown names evaluate to symbols, so they are only reported when they
+    /// look like a typo of a known name.
+    fn unknown_variable(&mut self, name: &Token) {
+        if let Some(suggestion) = self.suggest(&name.lexeme) {
+            self.warnings.push(Warning::new(
+                format!(
+                    "`{}` is not defined, did you mean `{}`?",
+                    name.lexeme, suggestion
+                ),
+                name.span,
+            ));
+        }
+    }
+
+    fn is_known(&self, name: &str) -> bool {
+        self.declared.contains_key(name) || self.globals.get(name).is_some()
+    }
+
+    /// The callee and its expected arity when calling `name` with `got`
+    /// arguments is known to fail.
+    fn arity_mismatch(&self, name: &str, got: usize) -> Option<(String, u64)> {
+        if let Some(arity) = self.declared.get(name) {
+            return arity
+                .filter(|arity| *arity as usize != got)
+                .map(|arity| (format!("<fnc:{}>", name), arity));
+        }
+
+        match self.globals.get(name) {
+            Some(Value::Function(fnc)) if !fnc.accepts(got) => Some((fnc.label(), fnc.arity())),
+            _ => None,
+        }
+    }
+
+    fn suggest(&self, name: &str) -> Option<String> {
+        let threshold = name.chars().count() / 3;
+
+        let mut candidates: Vec<String> = self.globals.names();
+
+        candidates.extend(self.declared.keys().cloned());
+        candidates.extend(self.scopes.iter().flat_map(|scope| scope.keys().cloned()));
+
+        candidates.sort();
+        candidates.dedup();
+
+        candidates
+            .into_iter()
+            .filter(|candidate| candidate != name)
+            .map(|candidate| (distance(name, &candidate), candidate))
+            .filter(|(distance, _)| *distance <= threshold)
+            .min_by_key(|(distance, _)| *distance)
+            .map(|(_, candidate)| candidate)
+    }
+}
+
+/// Edit distance counting adjacent transpositions as one edit, so `sni` is one
+/// step away from `sin`.
+fn distance(a: &str, b: &str) -> usize {
+    let a: Vec<char> = a.chars().collect();
+    let b: Vec<char> = b.chars().collect();
+
+    let mut table = vec![vec![0; b.len() + 1]; a.len() + 1];
+
+    for (i, row) in table.iter_mut().enumerate() {
+        row[0] = i;
+    }
+
+    for (j, cell) in table[0].iter_mut().enumerate() {
+        *cell = j;
+    }
+
+    for i in 1..=a.len() {
+        for j in 1..=b.len() {
+            let cost = if a[i - 1] == b[j - 1] { 0 } else { 1 };
+
+            table[i][j] = (table[i - 1][j] + 1)
+                .min(table[i][j - 1] + 1)
+                .min(table[i - 1][j - 1] + cost);
+
+            if i > 1 && j > 1 && a[i - 1] == b[j - 2] && a[i - 2] == b[j - 1] {
+                table[i][j] = table[i][j].min(table[i - 2][j - 2] + 1);
+            }
+        }
+    }
+
+    table[a.len()][b.len()]
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::Matika;
+
+    #[test]
+    fn unknown_functions_suggest_close_names() {
+        let err = Matika::new().eval("sni(1)".to_string()).unwrap_err();
+
+        assert!(
+            matches!(
+                &err,
+                MatikaError::UnknownFunction { name, suggestion: Some(suggestion), .. }
+                    if name == "sni" && suggestion == "sin"
+            ),
+            "{:?}",
+            err
+        );
+        assert_eq!(distance("sni", "sin"), 1);
+    }
+
+    #[test]
+    fn calls_with_the_wrong_arity_are_errors() {
+        let err = Matika::new().eval("pi(3)".to_string()).unwrap_err();
+
+        assert_eq!(
+            err,
+            MatikaError::Arity {
+                callee: "<fnc:pi>".to_string(),
+                expected: 0,
+                got: 1,
+                span: Some(Span {
+                    start: 0,
+                    end: 5,
+                    line: 1,
+                    column: 1
+                }),
+            }
+        );
+    }
+
+    #[test]
+    fn unused_parameters_are_warnings() {
+        let mut matika = Matika::new();
+
+        matika.eval("k(x) = 1".to_string()).unwrap();
+
+        let messages: Vec<&str> = matika
+            .warnings()
+            .iter()
+            .map(|warning| warning.message.as_str())
+            .collect();
+
+        assert_eq!(messages, ["parameter `x` of `k` is never used"]);
+
+        matika.eval("k(_x) = 1".to_string()).unwrap();
+
+        assert!(matika.warnings().is_empty());
+    }
+}
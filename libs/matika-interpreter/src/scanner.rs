@@ -1,12 +1,19 @@
 use std::collections::HashMap;
 
-use crate::token::{LiteralKind, Token, TokenKind};
+use num_bigint::BigInt;
 
+use crate::error::MatikaError;
+use crate::token::{LiteralKind, Span, Token, TokenKind};
+
+/// Walks the source one char at a time. `start` and `current` are byte offsets
+/// into `source`, while columns count chars so carets line up under Unicode.
 pub struct Scanner {
     source: String,
     start: usize,
     current: usize,
     line: usize,
+    column: usize,
+    start_column: usize,
     tokens: Vec<Token>,
     keywords: HashMap<String, TokenKind>,
 }
@@ -16,42 +23,115 @@ impl Scanner {
         let mut keywords = HashMap::new();
 
         keywords.insert("print".into(), TokenKind::Print);
+        keywords.insert("and".into(), TokenKind::And);
+        keywords.insert("or".into(), TokenKind::Or);
+        keywords.insert("not".into(), TokenKind::Not);
+        keywords.insert("true".into(), TokenKind::True);
+        keywords.insert("false".into(), TokenKind::False);
+        keywords.insert("if".into(), TokenKind::If);
+        keywords.insert("else".into(), TokenKind::Else);
 
         Self {
             source,
             start: 0,
             current: 0,
             line: 1,
+            column: 0,
+            start_column: 0,
             tokens: vec![],
             keywords,
         }
     }
 
-    pub fn scan(&mut self) -> &Vec<Token> {
+    pub fn scan(&mut self) -> Result<&Vec<Token>, MatikaError> {
+        let (_, mut errors) = self.scan_with_diagnostics();
+
+        if errors.is_empty() {
+            Ok(&self.tokens)
+        } else {
+            Err(errors.remove(0))
+        }
+    }
+
+    /// Scans the whole source, skipping unexpected characters instead of stopping at the first.
+    pub fn scan_with_diagnostics(&mut self) -> (&Vec<Token>, Vec<MatikaError>) {
+        let mut errors = vec![];
+
         while !self.is_end() {
             self.start = self.current;
-            self.scan_token();
+            self.start_column = self.column;
+
+            if let Err(err) = self.scan_token() {
+                errors.push(err);
+            }
         }
 
-        self.tokens.push(Token::eof());
+        let span = self.span_at_current();
 
-        &self.tokens
+        self.tokens.push(Token::eof(span));
+
+        (&self.tokens, errors)
     }
 
-    fn scan_token(&mut self) {
+    fn scan_token(&mut self) -> Result<(), MatikaError> {
         let ch = self.advance();
 
         match ch {
             Some('(') => self.add_token(TokenKind::LeftParen, None),
             Some(')') => self.add_token(TokenKind::RightParen, None),
+            Some('[') => self.add_token(TokenKind::LeftBracket, None),
+            Some(']') => self.add_token(TokenKind::RightBracket, None),
+            Some(':') => self.add_token(TokenKind::Colon, None),
             Some('.') => self.add_token(TokenKind::Dot, None),
-            Some('-') => self.add_token(TokenKind::Minus, None),
-            Some('+') => self.add_token(TokenKind::Plus, None),
-            Some('*') => self.add_token(TokenKind::Star, None),
+            Some('-') | Some('−') => {
+                let token = if self.matches('>') {
+                    TokenKind::Arrow
+                } else {
+                    TokenKind::Minus
+                };
+
+                self.add_token(token, None);
+            }
+            Some('→') => self.add_token(TokenKind::Arrow, None),
+            Some('∘') => self.add_token(TokenKind::Compose, None),
+            Some('+') => {
+                let token = if self.matches('+') {
+                    TokenKind::PlusPlus
+                } else {
+                    TokenKind::Plus
+                };
+
+                self.add_token(token, None);
+            }
+            Some('*') | Some('×') | Some('·') => self.add_token(TokenKind::Star, None),
+            Some('÷') => self.add_token(TokenKind::Slash, None),
             Some('^') => self.add_token(TokenKind::Caret, None),
-            Some('!') => self.add_token(TokenKind::Bang, None),
-            Some('=') => self.add_token(TokenKind::Equal, None),
+            Some('√') => self.add_token(TokenKind::Root, None),
+            Some('≠') => self.add_token(TokenKind::BangEqual, None),
+            Some('≤') => self.add_token(TokenKind::LessEqual, None),
+            Some('≥') => self.add_token(TokenKind::GreaterEqual, None),
+            Some('π') => self.pi(),
+            Some(c) if superscript(c).is_some() => self.superscript(c),
+            Some('!') => {
+                let token = if self.matches('=') {
+                    TokenKind::BangEqual
+                } else {
+                    TokenKind::Bang
+                };
+
+                self.add_token(token, None);
+            }
+            Some('=') => {
+                let token = if self.matches('=') {
+                    TokenKind::EqualEqual
+                } else {
+                    TokenKind::Equal
+                };
+
+                self.add_token(token, None);
+            }
             Some(',') => self.add_token(TokenKind::Comma, None),
+            Some(';') => self.add_token(TokenKind::Semicolon, None),
             Some('<') => {
                 let token = if self.matches('=') {
                     TokenKind::LessEqual
@@ -84,27 +164,38 @@ impl Scanner {
             Some('\r') => (),
             Some('\t') => (),
 
-            Some('\n') => self.line += 1,
+            Some('\n') => {
+                self.add_token(TokenKind::Newline, None);
+
+                self.line += 1;
+                self.column = 0;
+            }
 
-            Some(c) if self.is_digit(c) => self.number(),
+            Some(c) if self.is_digit(c) => self.number(c)?,
 
             Some(c) if self.is_alpha(c) => self.identifier(),
 
-            Some(_) => panic!("Unexpected character"),
+            Some(c) => {
+                return Err(
+                    MatikaError::lex(format!("unexpected character '{}'", c)).at(self.span())
+                )
+            }
             None => (),
         }
+
+        Ok(())
     }
 
     fn is_digit(&self, c: char) -> bool {
-        c >= '0' && c <= '9'
+        c.is_ascii_digit()
     }
 
     fn is_alpha(&self, c: char) -> bool {
-        (c >= 'a' && c <= 'z') || (c >= 'A' && c <= 'Z') || c == '_'
+        (c.is_alphabetic() || c == '_') && c != 'π'
     }
 
     fn is_alphanumeric(&self, c: char) -> bool {
-        self.is_digit(c) || self.is_alpha(c)
+        self.is_digit(c) || self.is_alpha(c) || (c.is_numeric() && superscript(c).is_none())
     }
 
     fn identifier(&mut self) {
@@ -114,82 +205,176 @@ impl Scanner {
 
         let text = &self.source[self.start..self.current];
 
-        let kind = self
-            .keywords
-            .get(text)
-            .unwrap_or(&TokenKind::Identifier)
-            .clone();
+        let kind = *self.keywords.get(text).unwrap_or(&TokenKind::Identifier);
 
         self.add_token(kind, None);
     }
 
-    fn number(&mut self) {
-        while self.is_digit(self.peek()) {
+    /// `π` stands for a call to the `pi` builtin.
+    fn pi(&mut self) {
+        let span = self.span();
+        let end = self.span_at_current();
+
+        self.tokens
+            .push(Token::synthetic(TokenKind::Identifier, "pi", span));
+        self.tokens
+            .push(Token::synthetic(TokenKind::LeftParen, "(", end));
+        self.tokens
+            .push(Token::synthetic(TokenKind::RightParen, ")", end));
+    }
+
+    /// A run of superscript digits is an exponent, so `x²` scans as `x ^ 2`.
+    fn superscript(&mut self, first: char) {
+        let mut exponent = BigInt::from(superscript(first).unwrap_or(0));
+
+        while let Some(digit) = superscript(self.peek()) {
+            self.advance();
+
+            exponent = exponent * 10 + digit;
+        }
+
+        let span = self.span();
+
+        self.tokens.push(Token::synthetic(
+            TokenKind::Caret,
+            &self.source[self.start..self.current],
+            span,
+        ));
+        self.add_token(TokenKind::Number, Some(LiteralKind::Integer(exponent)));
+    }
+
+    /// Scans `42`, `1_000`, `6.022e23`, `0xff`, `0b1010` or `0o17`. A prefix or
+    /// exponent only counts when a digit follows, so `2e` and `0x` still read as
+    /// the implicit multiplications `2*e` and `0*x`.
+    fn number(&mut self, first: char) -> Result<(), MatikaError> {
+        let radix = match self.peek() {
+            'x' | 'X' => 16,
+            'b' | 'B' => 2,
+            'o' | 'O' => 8,
+            _ => 10,
+        };
+
+        let literal = if first == '0' && radix != 10 && self.peek_next().is_digit(radix) {
             self.advance();
+            self.radix(radix)?
+        } else {
+            self.decimal()?
+        };
+
+        self.add_token(TokenKind::Number, Some(literal));
+
+        let next = self.peek();
+
+        if self.is_alpha(next) || next == 'π' || next == '√' {
+            let span = self.span_at_current();
+
+            self.tokens.push(Token::star(span));
         }
 
+        Ok(())
+    }
+
+    fn decimal(&mut self) -> Result<LiteralKind, MatikaError> {
+        self.digits(10);
+
+        let mut float = false;
+
         if self.peek() == '.' && self.is_digit(self.peek_next()) {
+            float = true;
+
             self.advance();
+            self.digits(10);
+        }
+
+        if self.peek() == 'e' || self.peek() == 'E' {
+            let mut rest = self.source[self.current..].chars().skip(1);
+            let exponent = match rest.next() {
+                Some('+') | Some('-') => rest.next(),
+                next => next,
+            };
+
+            if exponent.is_some_and(|c| self.is_digit(c)) {
+                float = true;
 
-            while self.is_digit(self.peek()) {
                 self.advance();
+
+                if !self.matches('+') {
+                    self.matches('-');
+                }
+
+                self.digits(10);
             }
         }
 
-        let value = &self.source[self.start..self.current];
-        let value = value.parse::<f64>().unwrap();
+        let text = self.source[self.start..self.current].replace('_', "");
 
-        self.add_token(TokenKind::Number, Some(LiteralKind::Number(value)));
+        if !float {
+            if let Ok(integer) = text.parse::<BigInt>() {
+                return Ok(LiteralKind::Integer(integer));
+            }
+        }
+
+        text.parse::<f64>()
+            .map(LiteralKind::Number)
+            .map_err(|_| MatikaError::lex(format!("invalid number '{}'", text)).at(self.span()))
+    }
+
+    fn radix(&mut self, radix: u32) -> Result<LiteralKind, MatikaError> {
+        self.digits(radix);
+
+        let next = self.peek();
+
+        if self.is_digit(next) {
+            self.advance();
 
-        if self.is_alpha(self.peek()) {
-            let token = Token {
-                kind: TokenKind::Star,
-                lexeme: "*".to_string(),
-                literal: None,
+            let name = match radix {
+                2 => "binary",
+                8 => "octal",
+                _ => "hexadecimal",
             };
 
-            self.tokens.push(token);
+            return Err(
+                MatikaError::lex(format!("invalid digit '{}' in {} literal", next, name))
+                    .at(self.span()),
+            );
         }
+
+        let digits = self.source[self.start + 2..self.current].replace('_', "");
+
+        BigInt::parse_bytes(digits.as_bytes(), radix)
+            .map(LiteralKind::Integer)
+            .ok_or_else(|| MatikaError::lex(format!("invalid number '{}'", digits)).at(self.span()))
     }
 
-    fn peek_next(&self) -> char {
-        if self.current + 1 >= self.source.len() {
-            return '\0';
-        }
+    /// Consumes digits of `radix`, allowing single `_` separators between them.
+    fn digits(&mut self, radix: u32) {
+        loop {
+            let next = self.peek();
 
-        match self.source.chars().nth(self.current + 1) {
-            Some(ch) => ch,
-            None => '\n',
+            if next.is_digit(radix) || (next == '_' && self.peek_next().is_digit(radix)) {
+                self.advance();
+            } else {
+                break;
+            }
         }
     }
 
-    fn peek(&self) -> char {
-        if self.is_end() {
-            return '\0';
-        }
+    fn peek_next(&self) -> char {
+        self.source[self.current..].chars().nth(1).unwrap_or('\0')
+    }
 
-        match self.source.chars().nth(self.current) {
-            Some(ch) => ch,
-            None => '\0',
-        }
+    fn peek(&self) -> char {
+        self.source[self.current..].chars().next().unwrap_or('\0')
     }
 
     fn matches(&mut self, expected: char) -> bool {
-        if self.is_end() {
-            return false;
-        }
+        if self.peek() == expected {
+            self.advance();
 
-        match self.source.chars().nth(self.current) {
-            Some(ch) => {
-                if ch == expected {
-                    self.current += 1;
-                    true
-                } else {
-                    false
-                }
-            }
-            None => false,
+            return true;
         }
+
+        false
     }
 
     fn add_token(&mut self, kind: TokenKind, literal: Option<LiteralKind>) {
@@ -199,17 +384,119 @@ impl Scanner {
             kind,
             lexeme: text.to_string(),
             literal,
+            span: self.span(),
         };
 
         self.tokens.push(token);
     }
 
+    /// Span of the token being scanned. Offsets are in bytes, columns in chars.
+    fn span(&self) -> Span {
+        Span::new(self.start, self.current, self.line, self.start_column + 1)
+    }
+
+    fn span_at_current(&self) -> Span {
+        Span::new(self.current, self.current, self.line, self.column + 1)
+    }
+
     fn advance(&mut self) -> Option<char> {
-        self.current += 1;
-        self.source.chars().nth(self.current - 1)
+        let ch = self.source[self.current..].chars().next()?;
+
+        self.current += ch.len_utf8();
+        self.column += 1;
+
+        Some(ch)
     }
 
     fn is_end(&self) -> bool {
         self.current >= self.source.len()
     }
 }
+
+fn superscript(c: char) -> Option<u32> {
+    match c {
+        '⁰' => Some(0),
+        '¹' => Some(1),
+        '²' => Some(2),
+        '³' => Some(3),
+        '⁴' => Some(4),
+        '⁵' => Some(5),
+        '⁶' => Some(6),
+        '⁷' => Some(7),
+        '⁸' => Some(8),
+        '⁹' => Some(9),
+        _ => None,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::tests::eval;
+
+    #[test]
+    fn number_literal_forms() {
+        for (src, value) in [
+            ("6.022e23", "602200000000000000000000"),
+            ("1.5e-3", "0.0015"),
+            ("0xff", "255"),
+            ("0b101", "5"),
+            ("0o17", "15"),
+            ("1_000", "1000"),
+            ("2e", "2*e"),
+        ] {
+            assert_eq!(eval(src), value, "{}", src);
+        }
+
+        let mut scanner = Scanner::new(String::from("2e"));
+        let kinds: Vec<TokenKind> = scanner
+            .scan()
+            .unwrap()
+            .iter()
+            .map(|token| token.kind)
+            .collect();
+
+        assert_eq!(
+            kinds,
+            [
+                TokenKind::Number,
+                TokenKind::Star,
+                TokenKind::Identifier,
+                TokenKind::Eof
+            ]
+        );
+    }
+
+    #[test]
+    fn scanning_collects_every_lex_error() {
+        let mut scanner = Scanner::new(String::from("1 + $\nx = 0b12\ny @ 2"));
+        let (tokens, errors) = scanner.scan_with_diagnostics();
+        let lines: Vec<usize> = errors.iter().map(|err| err.span().unwrap().line).collect();
+
+        assert_eq!(lines, [1, 2, 3]);
+        assert!(tokens.iter().any(|token| token.lexeme == "y"));
+        assert_eq!(tokens.last().unwrap().kind, TokenKind::Eof);
+    }
+
+    #[test]
+    fn superscripts_are_integer_exponents() {
+        let mut scanner = Scanner::new(String::from("x²³"));
+        let tokens = scanner.scan().unwrap();
+
+        assert_eq!(tokens[1].kind, TokenKind::Caret);
+        assert_eq!(
+            tokens[2].literal,
+            Some(LiteralKind::Integer(BigInt::from(23)))
+        );
+    }
+
+    #[test]
+    fn superscripts_match_caret() {
+        for (superscript, caret) in [("(1/3)²", "(1/3)^2"), ("2¹⁰⁰", "2^100"), ("3⁰", "3^0")]
+        {
+            assert_eq!(eval(superscript), eval(caret));
+        }
+
+        assert_eq!(eval("(1/3)²"), "1/9");
+    }
+}
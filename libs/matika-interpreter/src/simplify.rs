@@ -0,0 +1,1175 @@
+use std::cmp::Ordering;
+
+use num_bigint::BigInt;
+use num_integer::Integer;
+use num_rational::BigRational;
+use num_traits::{One, Signed, ToPrimitive, Zero};
+
+use crate::expr::Expr;
+use crate::stmt::Fnc;
+use crate::token::{LiteralKind, Span, Token, TokenKind};
+use crate::value::Value;
+
+/// Powers of sums are multiplied out by `expand` up to this exponent.
+const MAX_EXPANSION: usize = 32;
+
+/// `factor` only looks for rational roots of polynomials up to this degree,
+/// whose constant and leading coefficients are at most `MAX_DIVISOR_SEARCH`.
+const MAX_DEGREE: usize = 64;
+const MAX_DIVISOR_SEARCH: u64 = 1_000_000_000_000;
+
+/// Rewrites `expr` into an equivalent canonical form: constants are folded,
+/// like terms combined, repeated factors collected into powers and terms
+/// ordered by descending degree. Products of sums are left unexpanded.
+pub fn simplify(expr: &Expr) -> Expr {
+    rebuild(normalize(expr, false))
+}
+
+/// Simplifies `expr` with every product of sums multiplied out and integer
+/// powers of sums expanded, so `(a + b)^2` becomes `a^2 + 2a*b + b^2`.
+pub fn expand(expr: &Expr) -> Expr {
+    rebuild(normalize(expr, true))
+}
+
+/// Factors `expr`: the content and the powers common to all terms go in
+/// front, and what remains of a polynomial in one variable is split into a
+/// linear factor for each of its rational roots. Polynomials in several
+/// variables are split where they are a difference of two squares or a
+/// perfect square, like `a^2 - b^2` and `a^2 + 2a*b + b^2`. A product keeps
+/// the factors it has and each of them is factored on its own, and a sum that
+/// can't be split is left as it is rather than expanded.
+pub fn factor(expr: &Expr) -> Expr {
+    let Term {
+        coefficient,
+        factors,
+    } = match normalize(expr, false).as_slice() {
+        [term] => factor_term(term),
+        _ => factor_expanded(expr),
+    };
+
+    product(&coefficient, &factors)
+}
+
+/// The term with each factor that is an integer power of a sum factored.
+fn factor_term(term: &Term) -> Term {
+    let unfactored = Term {
+        coefficient: term.coefficient.clone(),
+        factors: vec![],
+    };
+
+    term.factors.iter().fold(unfactored, |factored, factor| {
+        let power = match factor.exponent.to_integer().abs().to_usize() {
+            Some(n) if factor.exponent.is_integer() && normalize(&factor.base, false).len() > 1 => {
+                let power = factor_expanded(&factor.base).power(n);
+
+                if factor.exponent.is_negative() {
+                    power.reciprocal()
+                } else {
+                    power
+                }
+            }
+            _ => Term {
+                coefficient: Value::integer(1),
+                factors: vec![factor.clone()],
+            },
+        };
+
+        factored.times(&power)
+    })
+}
+
+/// The expansion of `expr` factored, or `expr` simplified if factoring the
+/// expansion only gives it back.
+fn factor_expanded(expr: &Expr) -> Term {
+    let expanded = normalize(expr, true);
+    let expansion = rebuild(expanded.clone()).to_string();
+    let factored = factor_sum(expanded);
+
+    if product(&factored.coefficient, &factored.factors).to_string() == expansion {
+        monomial(normalize(expr, false))
+    } else {
+        factored
+    }
+}
+
+/// The factored `sum` as a single term.
+fn factor_sum(mut sum: Sum) -> Term {
+    match sum.len() {
+        0 => {
+            return Term {
+                coefficient: Value::integer(0),
+                factors: vec![],
+            }
+        }
+        1 => return sum.remove(0),
+        _ => (),
+    }
+
+    sum.sort_by(Term::order);
+
+    let common = Term {
+        coefficient: content(&sum),
+        factors: common_factors(&sum),
+    };
+
+    let rest: Sum = sum
+        .iter()
+        .map(|term| term.times(&common.reciprocal()))
+        .collect();
+
+    let Term {
+        mut coefficient,
+        mut factors,
+    } = common;
+
+    match polynomial(&rest) {
+        Some((var, coefficients)) => {
+            let (roots, quotient) = rational_roots(coefficients);
+
+            for (root, multiplicity) in roots {
+                let linear = vec![
+                    Term {
+                        coefficient: Value::integer(root.denom().clone()),
+                        factors: vec![Factor::new(var.clone(), BigRational::one())],
+                    },
+                    Term {
+                        coefficient: Value::integer(-root.numer()),
+                        factors: vec![],
+                    },
+                ];
+
+                factors.push(Factor::new(
+                    rebuild(linear),
+                    BigRational::from_integer(multiplicity.into()),
+                ));
+            }
+
+            match quotient.as_slice() {
+                [constant] => {
+                    coefficient = arithmetic(
+                        TokenKind::Star,
+                        &coefficient,
+                        &Value::Rational(constant.clone()),
+                    )
+                }
+                _ => factors.push(Factor::new(
+                    rebuild(from_polynomial(&var, &quotient)),
+                    BigRational::one(),
+                )),
+            }
+        }
+        None => match squares(&rest) {
+            Some(parts) => {
+                let mut factored = Term {
+                    coefficient,
+                    factors,
+                };
+
+                for (part, multiplicity) in parts {
+                    factored = factored.times(&factor_sum(part).power(multiplicity));
+                }
+
+                return factored;
+            }
+            None => factors.push(Factor::new(rebuild(rest), BigRational::one())),
+        },
+    }
+
+    factors.sort_by(Factor::order);
+
+    Term {
+        coefficient,
+        factors,
+    }
+}
+
+/// Splits a difference of squares `A^2 - B^2` into `A + B` and `A - B`, and a
+/// perfect square `A^2 ± 2A*B + B^2` into `A ± B` taken twice. The sum's
+/// leading term must be positive, as it is once its content is divided out.
+fn squares(sum: &[Term]) -> Option<Vec<(Sum, usize)>> {
+    match sum {
+        [left, right] => {
+            let a = left.square_root()?;
+            let b = right.scale(&Value::integer(-1)).square_root()?;
+
+            Some(vec![
+                (vec![a.clone(), b.clone()], 1),
+                (vec![a, b.scale(&Value::integer(-1))], 1),
+            ])
+        }
+        [_, _, _] => (0..3).find_map(|middle| {
+            let (i, j) = match middle {
+                0 => (1, 2),
+                1 => (0, 2),
+                _ => (0, 1),
+            };
+
+            let a = sum[i].square_root()?;
+            let b = sum[j].square_root()?;
+            let twice = a.times(&b).scale(&Value::integer(2));
+
+            if !sum[middle].like(&twice) {
+                return None;
+            }
+
+            let minus = twice.scale(&Value::integer(-1));
+
+            if sum[middle].coefficient.equals(&twice.coefficient) {
+                Some(vec![(vec![a, b], 2)])
+            } else if sum[middle].coefficient.equals(&minus.coefficient) {
+                Some(vec![(vec![a, b.scale(&Value::integer(-1))], 2)])
+            } else {
+                None
+            }
+        }),
+        _ => None,
+    }
+}
+
+/// The expanded `expr` as a polynomial in the name `var`, its coefficients
+/// listed from the constant term up. `None` unless `var` only appears raised
+/// to whole powers.
+pub fn coefficients(expr: &Expr, var: &str) -> Option<Vec<Expr>> {
+    let mut sums: Vec<Sum> = vec![];
+
+    for term in normalize(expr, true) {
+        let mut degree = 0;
+        let mut factors = vec![];
+
+        for factor in term.factors {
+            match &factor.base {
+                Expr::Variable { name, .. } if name.lexeme == var => {
+                    degree = factor
+                        .exponent
+                        .to_integer()
+                        .to_usize()
+                        .filter(|degree| factor.exponent.is_integer() && *degree <= MAX_DEGREE)?;
+                }
+                base if mentions(base, var) => return None,
+                _ => factors.push(factor),
+            }
+        }
+
+        if sums.len() <= degree {
+            sums.resize(degree + 1, vec![]);
+        }
+
+        sums[degree].push(Term {
+            coefficient: term.coefficient,
+            factors,
+        });
+    }
+
+    Some(sums.into_iter().map(rebuild).collect())
+}
+
+/// Whether the name `var` occurs in `expr` other than as a callee.
+pub fn mentions(expr: &Expr, var: &str) -> bool {
+    match expr {
+        Expr::Variable { name, .. } => name.lexeme == var,
+        Expr::Literal { .. } => false,
+        Expr::Grouping { expr, .. } => mentions(expr, var),
+        Expr::Unary { right, .. } => mentions(right, var),
+        Expr::Binary { left, right, .. } | Expr::Logical { left, right, .. } => {
+            mentions(left, var) || mentions(right, var)
+        }
+        Expr::Conditional {
+            condition,
+            then_branch,
+            else_branch,
+        } => [condition, then_branch, else_branch]
+            .iter()
+            .any(|expr| mentions(expr, var)),
+        Expr::Call { args, .. } => args.iter().any(|arg| mentions(arg, var)),
+        Expr::List { elements, .. } => elements.iter().any(|element| mentions(element, var)),
+        Expr::Index { object, index, .. } => mentions(object, var) || mentions(index, var),
+        Expr::Slice {
+            object, start, end, ..
+        } => {
+            mentions(object, var)
+                || start
+                    .iter()
+                    .chain(end.iter())
+                    .any(|bound| mentions(bound, var))
+        }
+        Expr::Lambda { fnc, .. } => mentions(&fnc.body, var),
+    }
+}
+
+/// The normal form arithmetic is simplified in, a sum of distinct monomials.
+/// The empty sum is zero.
+type Sum = Vec<Term>;
+
+/// `coefficient * base^exponent * ...`, with the factors sorted by `order`
+/// and no two sharing a base. The coefficient is a rational or a float.
+#[derive(Clone)]
+struct Term {
+    coefficient: Value,
+    factors: Vec<Factor>,
+}
+
+/// A base that is not simplified any further, identified by how it prints.
+#[derive(Clone)]
+struct Factor {
+    key: String,
+    base: Expr,
+    exponent: BigRational,
+}
+
+impl Factor {
+    fn new(base: Expr, exponent: BigRational) -> Self {
+        Self {
+            key: base.to_string(),
+            base,
+            exponent,
+        }
+    }
+
+    /// Variables come first, then calls, then powers, then everything else.
+    fn order(&self, other: &Self) -> Ordering {
+        let rank = |factor: &Factor| match &factor.base {
+            Expr::Variable { .. } => 0,
+            Expr::Call { .. } => 1,
+            Expr::Binary { op, .. } if op.kind == TokenKind::Caret => 2,
+            _ => 3,
+        };
+
+        rank(self)
+            .cmp(&rank(other))
+            .then_with(|| self.key.cmp(&other.key))
+    }
+}
+
+impl Term {
+    fn degree(&self) -> BigRational {
+        self.factors
+            .iter()
+            .fold(BigRational::zero(), |degree, factor| {
+                degree + &factor.exponent
+            })
+    }
+
+    /// Whether the terms differ in their coefficients only.
+    fn like(&self, other: &Self) -> bool {
+        self.factors.len() == other.factors.len()
+            && self
+                .factors
+                .iter()
+                .zip(&other.factors)
+                .all(|(factor, other)| factor.key == other.key && factor.exponent == other.exponent)
+    }
+
+    /// Higher degrees first and constants last, like terms next to each other.
+    fn order(&self, other: &Self) -> Ordering {
+        other
+            .degree()
+            .cmp(&self.degree())
+            .then_with(|| self.factors.is_empty().cmp(&other.factors.is_empty()))
+            .then_with(|| {
+                let keys = |term: &Self| {
+                    term.factors
+                        .iter()
+                        .map(|factor| factor.key.clone())
+                        .collect::<Vec<_>>()
+                };
+
+                keys(self).cmp(&keys(other))
+            })
+    }
+
+    fn reciprocal(&self) -> Self {
+        Self {
+            coefficient: arithmetic(TokenKind::Slash, &Value::integer(1), &self.coefficient),
+            factors: self
+                .factors
+                .iter()
+                .map(|factor| Factor {
+                    exponent: -&factor.exponent,
+                    ..factor.clone()
+                })
+                .collect(),
+        }
+    }
+
+    fn scale(&self, factor: &Value) -> Self {
+        Self {
+            coefficient: arithmetic(TokenKind::Star, &self.coefficient, factor),
+            factors: self.factors.clone(),
+        }
+    }
+
+    fn power(&self, n: usize) -> Self {
+        let exponent = BigRational::from_integer(n.into());
+
+        Self {
+            coefficient: arithmetic(
+                TokenKind::Caret,
+                &self.coefficient,
+                &Value::Rational(exponent.clone()),
+            ),
+            factors: self
+                .factors
+                .iter()
+                .map(|factor| Factor {
+                    exponent: &factor.exponent * &exponent,
+                    ..factor.clone()
+                })
+                .collect(),
+        }
+    }
+
+    /// The term whose square this is, for a positive rational coefficient that
+    /// is a square and factors raised to even powers.
+    fn square_root(&self) -> Option<Self> {
+        let ratio = match &self.coefficient {
+            Value::Rational(ratio) if ratio.is_positive() => ratio,
+            _ => return None,
+        };
+
+        let numer = ratio.numer().sqrt();
+        let denom = ratio.denom().sqrt();
+
+        if &(&numer * &numer) != ratio.numer() || &(&denom * &denom) != ratio.denom() {
+            return None;
+        }
+
+        let two = BigRational::from_integer(2.into());
+        let factors = self
+            .factors
+            .iter()
+            .map(|factor| {
+                let exponent = &factor.exponent / &two;
+
+                exponent.is_integer().then(|| Factor {
+                    exponent,
+                    ..factor.clone()
+                })
+            })
+            .collect::<Option<Vec<Factor>>>()?;
+
+        Some(Self {
+            coefficient: Value::Rational(BigRational::new(numer, denom)),
+            factors,
+        })
+    }
+
+    fn times(&self, other: &Self) -> Self {
+        let mut factors = self.factors.clone();
+
+        for factor in other.factors.iter() {
+            match factors.iter().position(|own| own.key == factor.key) {
+                Some(idx) => factors[idx].exponent += &factor.exponent,
+                None => factors.push(factor.clone()),
+            }
+        }
+
+        factors.retain(|factor| !factor.exponent.is_zero());
+        factors.sort_by(Factor::order);
+
+        Self {
+            coefficient: arithmetic(TokenKind::Star, &self.coefficient, &other.coefficient),
+            factors,
+        }
+    }
+}
+
+/// Brings `expr` into normal form. With `expand`, products of sums are
+/// distributed instead of kept as factors.
+fn normalize(expr: &Expr, expand: bool) -> Sum {
+    match expr {
+        Expr::Literal { value, .. } if value.number().is_some() => constant(Value::from(value)),
+        Expr::Grouping { expr, .. } => normalize(expr, expand),
+        Expr::Variable { .. } => atom(expr.clone()),
+        Expr::Unary { op, right } if op.kind == TokenKind::Minus => {
+            scale(normalize(right, expand), &Value::integer(-1))
+        }
+        Expr::Binary { left, op, right } => {
+            let left = || normalize(left, expand);
+            let right = || normalize(right, expand);
+
+            match op.kind {
+                TokenKind::Plus => add(left(), right()),
+                TokenKind::Minus => add(left(), scale(right(), &Value::integer(-1))),
+                TokenKind::Star => multiply(left(), right(), expand),
+                TokenKind::Slash => divide(left(), right(), expand),
+                TokenKind::Caret => power(left(), right(), expand),
+                _ => atom(children(expr, expand)),
+            }
+        }
+        Expr::Call { callee, args, .. } if args.len() == 1 && is_sqrt(callee) => power(
+            normalize(&args[0], expand),
+            constant(Value::Rational(BigRational::new(1.into(), 2.into()))),
+            expand,
+        ),
+        _ => atom(children(expr, expand)),
+    }
+}
+
+/// Simplifies the operands of an expression that is not itself arithmetic.
+fn children(expr: &Expr, expand: bool) -> Expr {
+    let operand = |expr: &Expr| rebuild(normalize(expr, expand));
+    let boxed = |expr: &Expr| Box::new(operand(expr));
+
+    match expr {
+        Expr::Binary { left, op, right } => Expr::Binary {
+            left: boxed(left),
+            op: op.clone(),
+            right: boxed(right),
+        },
+        Expr::Logical { left, op, right } => Expr::Logical {
+            left: boxed(left),
+            op: op.clone(),
+            right: boxed(right),
+        },
+        Expr::Unary { op, right } => Expr::Unary {
+            op: op.clone(),
+            right: boxed(right),
+        },
+        Expr::Conditional {
+            condition,
+            then_branch,
+            else_branch,
+        } => Expr::Conditional {
+            condition: boxed(condition),
+            then_branch: boxed(then_branch),
+            else_branch: boxed(else_branch),
+        },
+        Expr::Call { callee, args, span } => Expr::Call {
+            callee: callee.clone(),
+            args: args.iter().map(operand).collect(),
+            span: *span,
+        },
+        Expr::List { elements, span } => Expr::List {
+            elements: elements.iter().map(operand).collect(),
+            span: *span,
+        },
+        Expr::Index {
+            object,
+            index,
+            span,
+        } => Expr::Index {
+            object: boxed(object),
+            index: boxed(index),
+            span: *span,
+        },
+        Expr::Slice {
+            object,
+            start,
+            end,
+            span,
+        } => Expr::Slice {
+            object: boxed(object),
+            start: start.as_deref().map(boxed),
+            end: end.as_deref().map(boxed),
+            span: *span,
+        },
+        Expr::Lambda { fnc, span } => Expr::Lambda {
+            fnc: Box::new(Fnc {
+                body: operand(&fnc.body),
+                ..fnc.as_ref().clone()
+            }),
+            span: *span,
+        },
+        expr => expr.clone(),
+    }
+}
+
+fn constant(value: Value) -> Sum {
+    if is(&value, 0) {
+        vec![]
+    } else {
+        vec![Term {
+            coefficient: value,
+            factors: vec![],
+        }]
+    }
+}
+
+fn atom(base: Expr) -> Sum {
+    vec![Term {
+        coefficient: Value::integer(1),
+        factors: vec![Factor::new(base, BigRational::one())],
+    }]
+}
+
+fn add(mut sum: Sum, other: Sum) -> Sum {
+    for term in other {
+        match sum.iter().position(|own| own.like(&term)) {
+            Some(idx) => {
+                let coefficient =
+                    arithmetic(TokenKind::Plus, &sum[idx].coefficient, &term.coefficient);
+
+                if is(&coefficient, 0) {
+                    sum.remove(idx);
+                } else {
+                    sum[idx].coefficient = coefficient;
+                }
+            }
+            None => sum.push(term),
+        }
+    }
+
+    sum
+}
+
+fn scale(sum: Sum, factor: &Value) -> Sum {
+    if is(factor, 0) {
+        return vec![];
+    }
+
+    sum.into_iter()
+        .map(|term| Term {
+            coefficient: arithmetic(TokenKind::Star, &term.coefficient, factor),
+            ..term
+        })
+        .collect()
+}
+
+/// Constants are distributed over sums, other sums stay factors of their own
+/// unless expanding.
+fn multiply(left: Sum, right: Sum, expand: bool) -> Sum {
+    match (left.as_slice(), right.as_slice()) {
+        ([], _) | (_, []) => vec![],
+        _ if expand => left.iter().fold(vec![], |sum, term| {
+            add(sum, right.iter().map(|other| term.times(other)).collect())
+        }),
+        ([term], [other]) => vec![term.times(other)],
+        ([term], _) if term.factors.is_empty() => scale(right, &term.coefficient),
+        (_, [term]) if term.factors.is_empty() => scale(left, &term.coefficient),
+        _ => vec![monomial(left).times(&monomial(right))],
+    }
+}
+
+fn divide(left: Sum, right: Sum, expand: bool) -> Sum {
+    match right.as_slice() {
+        [] => atom(binary(TokenKind::Slash, rebuild(left), integer(0))),
+        [term] => multiply(left, vec![term.reciprocal()], expand),
+        _ => multiply(
+            left,
+            vec![Term {
+                coefficient: Value::integer(1),
+                factors: vec![Factor::new(rebuild(right), -BigRational::one())],
+            }],
+            expand,
+        ),
+    }
+}
+
+/// Powers with a constant rational exponent are collected into the exponents
+/// of the base's factors, any other power is a factor of its own.
+fn power(base: Sum, exponent: Sum, expand: bool) -> Sum {
+    let exponent = match exponent.as_slice() {
+        [] => BigRational::zero(),
+        [Term {
+            coefficient: Value::Rational(exponent),
+            factors,
+        }] if factors.is_empty() => exponent.clone(),
+        _ => return atom(binary(TokenKind::Caret, rebuild(base), rebuild(exponent))),
+    };
+
+    if exponent.is_zero() {
+        return constant(Value::integer(1));
+    }
+
+    match base.as_slice() {
+        [] if exponent.is_positive() => vec![],
+        [term] => {
+            let coefficient = term
+                .coefficient
+                .powf(Value::Rational(exponent.clone()))
+                .ok()
+                .filter(|coefficient| {
+                    exponent.is_integer() || matches!(coefficient, Value::Rational(_))
+                });
+
+            match coefficient {
+                Some(coefficient) => vec![Term {
+                    coefficient,
+                    factors: term
+                        .factors
+                        .iter()
+                        .map(|factor| Factor {
+                            exponent: &factor.exponent * &exponent,
+                            ..factor.clone()
+                        })
+                        .collect(),
+                }],
+                None => vec![Term {
+                    coefficient: Value::integer(1),
+                    factors: vec![Factor::new(rebuild(base), exponent)],
+                }],
+            }
+        }
+        _ => match exponent.to_integer().to_usize() {
+            Some(n) if expand && exponent.is_integer() && n <= MAX_EXPANSION => {
+                (1..n).fold(base.clone(), |power, _| multiply(power, base.clone(), true))
+            }
+            _ => vec![Term {
+                coefficient: Value::integer(1),
+                factors: vec![Factor::new(rebuild(base), exponent)],
+            }],
+        },
+    }
+}
+
+/// The sum as a single term, wrapping it in a factor unless it is one.
+fn monomial(mut sum: Sum) -> Term {
+    if sum.len() == 1 {
+        return sum.remove(0);
+    }
+
+    Term {
+        coefficient: Value::integer(1),
+        factors: vec![Factor::new(rebuild(sum), BigRational::one())],
+    }
+}
+
+/// The largest rational dividing every coefficient, negative when the leading
+/// term is, or 1 unless all coefficients are rational.
+fn content(sum: &[Term]) -> Value {
+    let mut numer = BigInt::zero();
+    let mut denom = BigInt::one();
+
+    for term in sum {
+        match &term.coefficient {
+            Value::Rational(ratio) => {
+                numer = numer.gcd(ratio.numer());
+                denom = denom.lcm(ratio.denom());
+            }
+            _ => return Value::integer(1),
+        }
+    }
+
+    let content = BigRational::new(numer, denom);
+
+    if is_negative(&sum[0].coefficient) {
+        Value::Rational(-content)
+    } else {
+        Value::Rational(content)
+    }
+}
+
+/// The factors every term has, each to the smallest power it appears in.
+fn common_factors(sum: &[Term]) -> Vec<Factor> {
+    sum[0]
+        .factors
+        .iter()
+        .filter_map(|factor| {
+            let exponent = sum
+                .iter()
+                .map(|term| {
+                    term.factors
+                        .iter()
+                        .find(|other| other.key == factor.key)
+                        .map(|other| other.exponent.clone())
+                })
+                .collect::<Option<Vec<BigRational>>>()?
+                .into_iter()
+                .min()?;
+
+            if exponent.is_positive() {
+                Some(Factor {
+                    exponent,
+                    ..factor.clone()
+                })
+            } else {
+                None
+            }
+        })
+        .collect()
+}
+
+/// The sum as a polynomial in its only variable, with rational coefficients
+/// listed from the constant term up.
+fn polynomial(sum: &[Term]) -> Option<(Expr, Vec<BigRational>)> {
+    let mut var: Option<&Factor> = None;
+    let mut coefficients = vec![];
+
+    for term in sum {
+        let coefficient = match &term.coefficient {
+            Value::Rational(ratio) => ratio,
+            _ => return None,
+        };
+
+        let degree = match term.factors.as_slice() {
+            [] => 0,
+            [factor]
+                if matches!(factor.base, Expr::Variable { .. })
+                    && factor.exponent.is_integer()
+                    && var.is_none_or(|var| var.key == factor.key) =>
+            {
+                var = Some(factor);
+
+                factor
+                    .exponent
+                    .to_integer()
+                    .to_usize()
+                    .filter(|degree| *degree <= MAX_DEGREE)?
+            }
+            _ => return None,
+        };
+
+        if coefficients.len() <= degree {
+            coefficients.resize(degree + 1, BigRational::zero());
+        }
+
+        coefficients[degree] += coefficient;
+    }
+
+    Some((var?.base.clone(), coefficients))
+}
+
+fn from_polynomial(var: &Expr, coefficients: &[BigRational]) -> Sum {
+    coefficients
+        .iter()
+        .enumerate()
+        .filter(|(_, coefficient)| !coefficient.is_zero())
+        .map(|(degree, coefficient)| Term {
+            coefficient: Value::Rational(coefficient.clone()),
+            factors: match degree {
+                0 => vec![],
+                degree => vec![Factor::new(
+                    var.clone(),
+                    BigRational::from_integer(degree.into()),
+                )],
+            },
+        })
+        .collect()
+}
+
+/// Divides out `x - p/q` for each rational root `p/q` of the integer
+/// polynomial, given from the constant term up. Returns the roots, largest
+/// first, with their multiplicities and the quotient. The quotient is scaled
+/// by `1/q` per root so that it stays an integer polynomial and the roots'
+/// factors are `q*x - p`.
+pub fn rational_roots(
+    mut coefficients: Vec<BigRational>,
+) -> (Vec<(BigRational, usize)>, Vec<BigRational>) {
+    let (first, last) = match (coefficients.first(), coefficients.last()) {
+        (Some(first), Some(last)) if coefficients.len() > 1 && !first.is_zero() => {
+            (first.to_integer().abs(), last.to_integer().abs())
+        }
+        _ => return (vec![], coefficients),
+    };
+
+    let mut candidates = vec![];
+
+    if let (Some(numers), Some(denoms)) = (divisors(&first), divisors(&last)) {
+        for numer in numers.iter() {
+            for denom in denoms.iter() {
+                let root = BigRational::new(numer.clone(), denom.clone());
+
+                if !candidates.contains(&root) {
+                    candidates.push(-&root);
+                    candidates.push(root);
+                }
+            }
+        }
+    }
+
+    candidates.sort_by(|a, b| b.cmp(a));
+
+    let mut roots = vec![];
+
+    for root in candidates {
+        let mut multiplicity = 0;
+
+        while coefficients.len() > 1 && evaluate(&coefficients, &root).is_zero() {
+            let scale = BigRational::from_integer(root.denom().clone());
+
+            coefficients = deflate(&coefficients, &root)
+                .into_iter()
+                .map(|coefficient| coefficient / &scale)
+                .collect();
+            multiplicity += 1;
+        }
+
+        if multiplicity > 0 {
+            roots.push((root, multiplicity));
+        }
+    }
+
+    (roots, coefficients)
+}
+
+fn evaluate(coefficients: &[BigRational], x: &BigRational) -> BigRational {
+    coefficients
+        .iter()
+        .rev()
+        .fold(BigRational::zero(), |value, coefficient| {
+            value * x + coefficient
+        })
+}
+
+/// Synthetic division by `x - root`, dropping the remainder.
+fn deflate(coefficients: &[BigRational], root: &BigRational) -> Vec<BigRational> {
+    let mut quotient = vec![BigRational::zero(); coefficients.len() - 1];
+    let mut carry = BigRational::zero();
+
+    for degree in (1..coefficients.len()).rev() {
+        carry = carry * root + &coefficients[degree];
+        quotient[degree - 1] = carry.clone();
+    }
+
+    quotient
+}
+
+/// The positive divisors of `n`, unless it is too large to try them all.
+fn divisors(n: &BigInt) -> Option<Vec<BigInt>> {
+    let n = n.to_u64().filter(|n| *n <= MAX_DIVISOR_SEARCH)?;
+    let mut divisors = vec![];
+    let mut d = 1;
+
+    while d * d <= n {
+        if n % d == 0 {
+            divisors.push(BigInt::from(d));
+
+            if d * d != n {
+                divisors.push(BigInt::from(n / d));
+            }
+        }
+
+        d += 1;
+    }
+
+    Some(divisors)
+}
+
+/// Prints the sum back as an expression, subtracting negative terms.
+fn rebuild(mut sum: Sum) -> Expr {
+    sum.sort_by(Term::order);
+
+    let mut expr = None;
+
+    for term in sum {
+        expr = Some(match expr {
+            None => product(&term.coefficient, &term.factors),
+            Some(left) if is_negative(&term.coefficient) => {
+                let magnitude = arithmetic(TokenKind::Star, &term.coefficient, &Value::integer(-1));
+
+                binary(TokenKind::Minus, left, product(&magnitude, &term.factors))
+            }
+            Some(left) => binary(
+                TokenKind::Plus,
+                left,
+                product(&term.coefficient, &term.factors),
+            ),
+        });
+    }
+
+    expr.unwrap_or_else(|| integer(0))
+}
+
+/// `coefficient * factors`, negative exponents and the coefficient's
+/// denominator going below a single division.
+fn product(coefficient: &Value, factors: &[Factor]) -> Expr {
+    let (numerator, denominator) = match coefficient {
+        Value::Rational(ratio) => (Value::integer(ratio.numer().clone()), ratio.denom().clone()),
+        value => (value.clone(), BigInt::one()),
+    };
+
+    let mut above = vec![];
+    let mut below = vec![];
+
+    for factor in factors {
+        if factor.exponent.is_positive() {
+            above.push(raise(&factor.base, &factor.exponent));
+        } else {
+            below.push(raise(&factor.base, &-&factor.exponent));
+        }
+    }
+
+    if above.is_empty() || !(is(&numerator, 1) || is(&numerator, -1)) {
+        above.insert(0, literal(numerator));
+    } else if is(&numerator, -1) {
+        let first = above.remove(0);
+
+        above.insert(0, negate(first));
+    }
+
+    if !denominator.is_one() {
+        below.insert(0, literal(Value::integer(denominator)));
+    }
+
+    let fold = |exprs: Vec<Expr>| {
+        exprs
+            .into_iter()
+            .reduce(|left, right| binary(TokenKind::Star, left, right))
+    };
+
+    match (fold(above), fold(below)) {
+        (Some(above), Some(below)) => binary(TokenKind::Slash, above, below),
+        (Some(above), None) => above,
+        (None, _) => integer(1),
+    }
+}
+
+fn raise(base: &Expr, exponent: &BigRational) -> Expr {
+    if exponent.is_one() {
+        base.clone()
+    } else if *exponent == BigRational::new(1.into(), 2.into()) {
+        Expr::Call {
+            callee: Box::new(Expr::Variable {
+                name: Token::synthetic(TokenKind::Identifier, "sqrt", Span::default()),
+                depth: None,
+            }),
+            args: vec![base.clone()],
+            span: Span::default(),
+        }
+    } else if exponent.is_integer() {
+        binary(
+            TokenKind::Caret,
+            base.clone(),
+            integer(exponent.to_integer()),
+        )
+    } else {
+        let exponent = binary(
+            TokenKind::Slash,
+            integer(exponent.numer().clone()),
+            integer(exponent.denom().clone()),
+        );
+
+        binary(TokenKind::Caret, base.clone(), exponent)
+    }
+}
+
+fn negate(expr: Expr) -> Expr {
+    Expr::Unary {
+        op: Token::synthetic(TokenKind::Minus, "-", Span::default()),
+        right: Box::new(expr),
+    }
+}
+
+fn binary(op: TokenKind, left: Expr, right: Expr) -> Expr {
+    Expr::Binary {
+        left: Box::new(left),
+        op: Token::synthetic(op, &op.to_string(), Span::default()),
+        right: Box::new(right),
+    }
+}
+
+fn integer(n: impl Into<BigInt>) -> Expr {
+    Expr::Literal {
+        value: LiteralKind::Integer(n.into()),
+        span: Span::default(),
+    }
+}
+
+fn literal(value: Value) -> Expr {
+    match value.as_integer() {
+        Some(integer) if !matches!(value, Value::Number(_)) => self::integer(integer),
+        _ => Expr::Literal {
+            value: LiteralKind::Number(value.as_f64().unwrap_or(f64::NAN)),
+            span: Span::default(),
+        },
+    }
+}
+
+fn is_sqrt(callee: &Expr) -> bool {
+    matches!(callee, Expr::Variable { name, depth: None } if name.lexeme == "sqrt")
+}
+
+fn is(value: &Value, n: i64) -> bool {
+    value.equals(&Value::integer(n))
+}
+
+fn is_negative(value: &Value) -> bool {
+    value.as_f64().is_some_and(|value| value < 0.0)
+}
+
+/// Coefficients are rationals or floats, whose arithmetic cannot fail.
+fn arithmetic(op: TokenKind, left: &Value, right: &Value) -> Value {
+    Value::binary(op, left.clone(), right.clone()).unwrap_or(Value::Number(f64::NAN))
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::tests::eval;
+
+    #[test]
+    fn factors_differences_of_squares() {
+        assert_eq!(eval("factor(a^2 - b^2)"), "(a + b)*(a - b)");
+        assert_eq!(eval("factor(b^2 - a^2)"), "-(a + b)*(a - b)");
+        assert_eq!(eval("factor(2a^2 - 2b^2)"), "2*(a + b)*(a - b)");
+        assert_eq!(eval("factor(4x^2*y^2 - 9)"), "(2x*y + 3)*(2x*y - 3)");
+        assert_eq!(eval("factor(a^4 - b^4)"), "(a + b)*(a - b)*(a^2 + b^2)");
+        assert_eq!(eval("factor(a^2*c - b^2*c)"), "c*(a + b)*(a - b)");
+    }
+
+    #[test]
+    fn factors_perfect_squares() {
+        assert_eq!(eval("factor(a^2 + 2a*b + b^2)"), "(a + b)^2");
+        assert_eq!(eval("factor(a^2 - 2a*b + b^2)"), "(a - b)^2");
+        assert_eq!(eval("factor(9a^2 + 12a*b + 4b^2)"), "(3a + 2b)^2");
+        assert_eq!(eval("factor(a^2*b^2 - 2a*b + 1)"), "(a*b - 1)^2");
+        assert_eq!(eval("factor(expand((a + b)^2))"), "(a + b)^2");
+    }
+
+    #[test]
+    fn leaves_irreducible_sums() {
+        assert_eq!(eval("factor(a^2 + b^2)"), "a^2 + b^2");
+        assert_eq!(eval("factor(a^2 + a*b + b^2)"), "a^2 + a*b + b^2");
+        assert_eq!(eval("factor((a+b)^2*(a-b))"), "(a + b)^2*(a - b)");
+        assert_eq!(eval("factor((a+b)^2 + c)"), "(a + b)^2 + c");
+    }
+
+    /// Sums that factor along each path `factor` takes: rational roots, the
+    /// closed forms, differences of squares and perfect squares, and none, and
+    /// products factored one factor at a time.
+    const SUMS: &[&str] = &[
+        "x^2 - 5x + 6",
+        "x^3 - x",
+        "2x^2 + 4x + 2",
+        "x^4 - 1",
+        "x^4 - 5x^2 + 4",
+        "6x^3 - 11x^2 + 6x - 1",
+        "x^2 - 2",
+        "x^2 + x + 1",
+        "x/2 + x^2/3",
+        "a^2 - b^2",
+        "a^4 - b^4",
+        "9a^2 + 12a*b + 4b^2",
+        "a^2*c - b^2*c",
+        "a^2 + a*b + b^2",
+        "(x + 1)^3 - (x - 1)^3",
+        "(a + b)^2 * (a - b)",
+    ];
+
+    fn apply(fnc: &str, src: &str) -> String {
+        eval(&format!("{}({})", fnc, src))
+    }
+
+    #[test]
+    fn expanding_a_factorization_gives_the_expansion() {
+        for sum in SUMS {
+            assert_eq!(
+                apply("expand", &apply("factor", sum)),
+                apply("expand", sum),
+                "{}",
+                sum
+            );
+        }
+    }
+
+    #[test]
+    fn rewrites_are_idempotent() {
+        for sum in SUMS {
+            for fnc in ["simplify", "expand", "factor"] {
+                let once = apply(fnc, sum);
+
+                assert_eq!(apply(fnc, &once), once, "{}({})", fnc, sum);
+            }
+        }
+    }
+
+    #[test]
+    fn rewrites_print_as_they_evaluate() {
+        for sum in SUMS {
+            for fnc in ["simplify", "expand"] {
+                let printed = apply(fnc, sum);
+
+                assert_eq!(eval(&printed), printed, "{}({})", fnc, sum);
+            }
+        }
+    }
+}
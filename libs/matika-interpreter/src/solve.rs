@@ -0,0 +1,1095 @@
+use std::cmp::Ordering;
+use std::mem::swap;
+use std::rc::Rc;
+
+use num_bigint::BigInt;
+use num_integer::Integer;
+use num_rational::BigRational;
+use num_traits::{One, Signed, Zero};
+
+use crate::callable::Callable;
+use crate::complex::Complex;
+use crate::error::MatikaError;
+use crate::expr::Expr;
+use crate::functions;
+use crate::matrix::Matrix;
+use crate::simplify::{coefficients, mentions, rational_roots, simplify};
+use crate::stmt::{Fnc, Pattern};
+use crate::symbolic;
+use crate::token::{Span, Token, TokenKind};
+use crate::value::Value;
+use crate::Interpreter;
+
+/// Equations that are not polynomials are solved numerically, looking for
+/// roots this far either side of zero.
+const SEARCH_BOUND: f64 = 100.0;
+
+/// How many intervals the search range is sampled in. Roots closer together
+/// than an interval can be missed.
+const SAMPLES: usize = 10_000;
+
+const MAX_ITERATIONS: usize = 100;
+
+/// Iterations stop once a root moves less than this, relative to its size.
+const TOLERANCE: f64 = 1e-12;
+
+/// Numeric roots this close to each other, relative to their size, count as
+/// one. Float methods only find multiple roots to a few digits.
+const SAME_ROOT: f64 = 1e-6;
+
+/// Rounding error smaller than this, relative to a root's size, is dropped:
+/// an imaginary part makes the root real and a fractional part an integer.
+const SNAP: f64 = 1e-9;
+
+/// What `solve` looks for the zeros of.
+enum Target {
+    /// An expression in the name solved for.
+    Expr(Value, String),
+    /// A function that does not evaluate on unbound names, only on numbers.
+    Function(Rc<dyn Callable>),
+}
+
+impl Target {
+    /// The target's value at `x`, or NaN where it has no real value.
+    fn at(&self, interpreter: &mut Interpreter, x: f64) -> Result<f64, MatikaError> {
+        let y = match self {
+            Target::Expr(value, var) => {
+                symbolic::substitute(interpreter, value, var, Value::Number(x))?
+            }
+            Target::Function(fnc) => fnc.call(interpreter, vec![Value::Number(x)])?,
+        };
+
+        Ok(y.as_f64().unwrap_or(f64::NAN))
+    }
+}
+
+/// The real solutions of an equation, an expression equal to zero or a
+/// function equal to zero, in ascending order.
+pub fn solve(
+    interpreter: &mut Interpreter,
+    target: &Value,
+    var: Option<&Value>,
+) -> Result<Value, MatikaError> {
+    let roots = zeros(interpreter, "solve", target, var)?;
+
+    Ok(Value::List(
+        roots
+            .into_iter()
+            .filter(|root| !matches!(root, Value::Complex(_)))
+            .collect(),
+    ))
+}
+
+/// Like `solve`, but keeps the complex solutions too, after the real ones.
+pub fn roots(
+    interpreter: &mut Interpreter,
+    target: &Value,
+    var: Option<&Value>,
+) -> Result<Value, MatikaError> {
+    Ok(Value::List(zeros(interpreter, "roots", target, var)?))
+}
+
+/// `solve(A, b)`, the solution of `A*x = b` for a matrix of coefficients.
+pub fn matrix(matrix: &Matrix, b: Option<&Value>) -> Result<Value, MatikaError> {
+    let b = match b {
+        Some(b) => b.as_vector("solve")?,
+        None => {
+            return Err(MatikaError::type_error(
+                "solve expects a right-hand side after the matrix",
+            ))
+        }
+    };
+
+    matrix
+        .solve(&b)
+        .map(|x| Value::List(x.into_iter().map(Value::Number).collect()))
+}
+
+/// `solve([eq1, eq2, ...], [x, y, ...])` for a system of linear equations,
+/// by Gauss-Jordan elimination. Coefficients may be expressions over other
+/// names, which are assumed nonzero when chosen as pivots. Without a list of
+/// names, every name in the equations is solved for, alphabetically.
+pub fn system(
+    interpreter: &Interpreter,
+    equations: &[Value],
+    vars: Option<&Value>,
+) -> Result<Value, MatikaError> {
+    let exprs = equations
+        .iter()
+        .map(|equation| difference("solve", equation))
+        .collect::<Result<Vec<Expr>, MatikaError>>()?;
+
+    let vars = match vars {
+        Some(Value::List(vars)) => vars
+            .iter()
+            .map(|var| match var {
+                Value::Unbound(name) => Ok(name.clone()),
+                var => Err(MatikaError::type_error(format!(
+                    "solve expects unbound names to solve for, got {}{}",
+                    var,
+                    functions::shadowing(&interpreter.environment, var)
+                ))),
+            })
+            .collect::<Result<Vec<String>, MatikaError>>()?,
+        Some(value) => {
+            return Err(MatikaError::type_error(format!(
+                "solve expects a list of names to solve a system for, got {}",
+                value.type_name()
+            )))
+        }
+        None => {
+            let mut vars = vec![];
+
+            for expr in exprs.iter() {
+                names(expr, &mut vars);
+            }
+
+            vars.sort();
+            vars
+        }
+    };
+
+    if exprs.len() != vars.len() {
+        return Err(MatikaError::type_error(format!(
+            "solve needs as many equations as names to solve for, got {} and {}",
+            exprs.len(),
+            vars.len()
+        )));
+    }
+
+    let nonlinear = |expr: &Expr| {
+        MatikaError::type_error(format!(
+            "cannot solve the system, {} == 0 is not linear in {}",
+            expr,
+            vars.join(", ")
+        ))
+    };
+
+    let mut rows = vec![];
+
+    for expr in exprs.iter() {
+        let mut row = vec![];
+        let mut rest = symbolic::from_expr(expr.clone());
+
+        for var in vars.iter() {
+            let coefficient = match coefficients(expr, var).as_deref() {
+                Some([]) | Some([_]) => Value::integer(0),
+                Some([_, coefficient]) if !vars.iter().any(|var| mentions(coefficient, var)) => {
+                    symbolic::from_expr(coefficient.clone())
+                }
+                _ => return Err(nonlinear(expr)),
+            };
+
+            let term = Value::binary(
+                TokenKind::Star,
+                coefficient.clone(),
+                Value::Unbound(var.clone()),
+            )?;
+
+            rest = Value::binary(TokenKind::Minus, rest, term)?;
+            row.push(coefficient);
+        }
+
+        let constant = symbolic::to_expr(&rest)?;
+
+        if vars.iter().any(|var| mentions(&constant, var)) {
+            return Err(nonlinear(expr));
+        }
+
+        row.push((-rest)?);
+        rows.push(row);
+    }
+
+    eliminate(rows).map(Value::List)
+}
+
+/// Reduces the augmented rows to the identity, leaving the solution in the
+/// last column. Numeric pivots are chosen by size, expressions only when no
+/// number is left.
+fn eliminate(mut rows: Vec<Vec<Value>>) -> Result<Vec<Value>, MatikaError> {
+    let n = rows.len();
+    let magnitude = |value: &Value| value.as_complex().map_or(0.0, Complex::abs);
+
+    for col in 0..n {
+        let pivot = (col..n)
+            .filter(|&row| !is_zero(&rows[row][col]))
+            .max_by(|&a, &b| {
+                magnitude(&rows[a][col])
+                    .partial_cmp(&magnitude(&rows[b][col]))
+                    .unwrap_or(Ordering::Equal)
+            })
+            .ok_or_else(|| MatikaError::type_error("the system has no unique solution"))?;
+
+        rows.swap(col, pivot);
+
+        let pivot = rows[col][col].clone();
+
+        for entry in rows[col].iter_mut() {
+            *entry = Value::binary(TokenKind::Slash, entry.clone(), pivot.clone())?;
+        }
+
+        for row in 0..n {
+            if row == col || is_zero(&rows[row][col]) {
+                continue;
+            }
+
+            let factor = rows[row][col].clone();
+            let pivot_row = rows[col].clone();
+
+            for (entry, pivot) in rows[row].iter_mut().zip(pivot_row) {
+                let scaled = Value::binary(TokenKind::Star, factor.clone(), pivot)?;
+
+                *entry = Value::binary(TokenKind::Minus, entry.clone(), scaled)?;
+            }
+        }
+    }
+
+    Ok(rows.into_iter().map(|mut row| row.remove(n)).collect())
+}
+
+fn is_zero(value: &Value) -> bool {
+    match value {
+        Value::Rational(ratio) => ratio.is_zero(),
+        value => value.as_complex().is_some_and(|z| z.abs() <= TOLERANCE),
+    }
+}
+
+/// The solutions of `target` for `var`, real ones first in ascending order,
+/// then complex ones. Polynomials are solved exactly where their roots are
+/// rational, in closed form up to degree four and numerically beyond that.
+/// Anything else is searched for real roots numerically.
+fn zeros(
+    interpreter: &mut Interpreter,
+    name: &str,
+    target: &Value,
+    var: Option<&Value>,
+) -> Result<Vec<Value>, MatikaError> {
+    let (expr, var) = match target {
+        Value::Function(fnc) => {
+            if !fnc.accepts(1) {
+                return Err(MatikaError::type_error(format!(
+                    "cannot solve {}, it does not take a single argument",
+                    fnc.label()
+                )));
+            }
+
+            // The name only stands for the argument, so a name bound in the
+            // session, passed as its value, gives way to the parameter's own.
+            let var = match (var, fnc.as_function().map(|function| &function.clauses[0])) {
+                (Some(Value::Unbound(var)), _) => var.clone(),
+                (_, Some(Fnc { params, .. })) => match params.as_slice() {
+                    [Pattern::Binding(param)] => param.lexeme.clone(),
+                    _ => String::from("x"),
+                },
+                _ => String::from("x"),
+            };
+
+            match fnc
+                .call(interpreter, vec![Value::Unbound(var.clone())])
+                .and_then(|value| symbolic::to_expr(&value))
+            {
+                Ok(expr) => (expr, var),
+                Err(_) => {
+                    let target = Target::Function(fnc.clone());
+
+                    return search(interpreter, &target).map(sorted);
+                }
+            }
+        }
+        target => {
+            let var = variable(interpreter, name, var)?;
+            let expr = difference(name, target)?;
+            let var = match var {
+                Some(var) => var,
+                None => unknown(name, &expr)?,
+            };
+
+            (expr, var)
+        }
+    };
+
+    let roots = match coefficients(&expr, &var) {
+        Some(coefficients) => polynomial(name, &expr, &var, coefficients)?,
+        None => {
+            let mut others = vec![];
+
+            names(&expr, &mut others);
+            others.retain(|other| *other != var);
+
+            if !others.is_empty() {
+                return Err(MatikaError::type_error(format!(
+                    "{} cannot solve {} == 0 for {}, it depends on {} too",
+                    name,
+                    expr,
+                    var,
+                    others.join(", ")
+                )));
+            }
+
+            search(interpreter, &Target::Expr(symbolic::from_expr(expr), var))?
+        }
+    };
+
+    Ok(sorted(roots))
+}
+
+/// The name to solve for, given as `var`, with an error naming the binding
+/// that shadows it when it was bound in the session.
+fn variable(
+    interpreter: &Interpreter,
+    name: &str,
+    var: Option<&Value>,
+) -> Result<Option<String>, MatikaError> {
+    match var {
+        None => Ok(None),
+        Some(Value::Unbound(var)) => Ok(Some(var.clone())),
+        Some(value) => Err(MatikaError::type_error(format!(
+            "{} expects an unbound name to solve for, got {}{}",
+            name,
+            value,
+            functions::shadowing(&interpreter.environment, value)
+        ))),
+    }
+}
+
+/// The equation as an expression equal to zero.
+fn difference(name: &str, target: &Value) -> Result<Expr, MatikaError> {
+    match target {
+        Value::Equation(left, right) => Ok(simplify(&Expr::Binary {
+            left: Box::new(left.as_ref().clone()),
+            op: Token::synthetic(TokenKind::Minus, "-", Span::default()),
+            right: Box::new(right.as_ref().clone()),
+        })),
+        Value::Unbound(_) | Value::Symbolic(_) | Value::Rational(_) | Value::Number(_) => {
+            symbolic::to_expr(target)
+        }
+        target => Err(MatikaError::type_error(format!(
+            "{} expects an equation, an expression or a function, got {}",
+            name,
+            target.type_name()
+        ))),
+    }
+}
+
+/// The one name in `expr`, when it is not given.
+fn unknown(name: &str, expr: &Expr) -> Result<String, MatikaError> {
+    let mut vars = vec![];
+
+    names(expr, &mut vars);
+
+    match vars.len() {
+        0 => Err(MatikaError::type_error(format!(
+            "{} has no name to solve {} == 0 for, names bound in the session stand for their values",
+            name, expr
+        ))),
+        1 => Ok(vars.remove(0)),
+        _ => Err(MatikaError::type_error(format!(
+            "{} needs the name to solve for, {} == 0 has several: {}",
+            name,
+            expr,
+            vars.join(", ")
+        ))),
+    }
+}
+
+/// Collects the names `expr` mentions, other than callees, in order of first
+/// appearance.
+fn names(expr: &Expr, vars: &mut Vec<String>) {
+    match expr {
+        Expr::Variable { name, .. } if !vars.contains(&name.lexeme) => {
+            vars.push(name.lexeme.clone());
+        }
+        Expr::Grouping { expr, .. } => names(expr, vars),
+        Expr::Unary { right, .. } => names(right, vars),
+        Expr::Binary { left, right, .. } => {
+            names(left, vars);
+            names(right, vars);
+        }
+        Expr::Call { args, .. } => {
+            for arg in args {
+                names(arg, vars);
+            }
+        }
+        _ => (),
+    }
+}
+
+fn polynomial(
+    name: &str,
+    expr: &Expr,
+    var: &str,
+    coefficients: Vec<Expr>,
+) -> Result<Vec<Value>, MatikaError> {
+    let coefficients: Vec<Value> = coefficients.into_iter().map(symbolic::from_expr).collect();
+
+    match coefficients.len() {
+        0 => {
+            return Err(MatikaError::type_error(format!(
+                "{} cannot solve an equation that holds for every {}",
+                name, var
+            )))
+        }
+        1 => return Ok(vec![]),
+        _ => (),
+    }
+
+    let rationals: Option<Vec<BigRational>> = coefficients
+        .iter()
+        .map(|coefficient| match coefficient {
+            Value::Rational(ratio) => Some(ratio.clone()),
+            _ => None,
+        })
+        .collect();
+
+    if let Some(rationals) = rationals {
+        return exact(rationals);
+    }
+
+    let reals: Option<Vec<f64>> = coefficients.iter().map(Value::as_f64).collect();
+
+    match reals {
+        Some(reals) if reals.len() > 3 => Ok(numeric(&reals)),
+        _ if coefficients.len() <= 3 => closed(&coefficients),
+        _ => Err(MatikaError::type_error(format!(
+            "{} cannot solve {} == 0 for {}, its coefficients are not all real numbers",
+            name, expr, var
+        ))),
+    }
+}
+
+/// Roots of a polynomial with rational coefficients: the rational ones
+/// exactly, the rest from the quotient left after dividing those out.
+fn exact(coefficients: Vec<BigRational>) -> Result<Vec<Value>, MatikaError> {
+    let scale = coefficients.iter().fold(BigInt::one(), |lcm, coefficient| {
+        lcm.lcm(coefficient.denom())
+    });
+    let scale = BigRational::from_integer(scale);
+    let mut coefficients: Vec<BigRational> = coefficients
+        .into_iter()
+        .map(|coefficient| coefficient * &scale)
+        .collect();
+    let mut roots = vec![];
+
+    let zeros = coefficients.iter().take_while(|c| c.is_zero()).count();
+
+    if zeros > 0 {
+        roots.push(Value::integer(0));
+        coefficients.drain(..zeros);
+    }
+
+    let (rationals, quotient) = rational_roots(coefficients);
+
+    roots.extend(rationals.into_iter().map(|(root, _)| Value::Rational(root)));
+
+    match quotient.len() {
+        0 | 1 => (),
+        2 | 3 => {
+            let quotient: Vec<Value> = quotient.into_iter().map(Value::Rational).collect();
+
+            roots.extend(closed(&quotient)?);
+        }
+        _ => {
+            let quotient: Vec<f64> = quotient
+                .into_iter()
+                .map(|coefficient| Value::Rational(coefficient).as_f64().unwrap_or(f64::NAN))
+                .collect();
+
+            roots.extend(numeric(&quotient));
+        }
+    }
+
+    Ok(roots)
+}
+
+/// Linear and quadratic roots by formula, in value arithmetic so that they
+/// stay exact, become complex or become expressions as the coefficients ask.
+/// Other degrees are an error.
+fn closed(coefficients: &[Value]) -> Result<Vec<Value>, MatikaError> {
+    let op = |op: TokenKind, left: Value, right: Value| Value::binary(op, left, right);
+
+    match coefficients {
+        [c, b] => Ok(vec![op(TokenKind::Slash, (-c.clone())?, b.clone())?]),
+        [c, b, a] => {
+            let discriminant = op(
+                TokenKind::Minus,
+                op(TokenKind::Star, b.clone(), b.clone())?,
+                op(
+                    TokenKind::Star,
+                    Value::integer(4),
+                    op(TokenKind::Star, a.clone(), c.clone())?,
+                )?,
+            )?;
+            let denominator = op(TokenKind::Star, Value::integer(2), a.clone())?;
+            let b = (-b.clone())?;
+
+            if is_zero(&discriminant) {
+                return Ok(vec![op(TokenKind::Slash, b, denominator)?]);
+            }
+
+            let half = BigRational::new(BigInt::one(), BigInt::from(2));
+            let root = match &discriminant {
+                Value::Rational(ratio) if ratio.is_negative() => op(
+                    TokenKind::Star,
+                    op(
+                        TokenKind::Caret,
+                        Value::Rational(-ratio),
+                        Value::Rational(half),
+                    )?,
+                    Value::Complex(Complex::I),
+                )?,
+                Value::Number(_) | Value::Complex(_) => {
+                    Value::complex(discriminant.as_complex().unwrap_or_default().sqrt())
+                }
+                _ => op(TokenKind::Caret, discriminant, Value::Rational(half))?,
+            };
+
+            Ok(vec![
+                op(
+                    TokenKind::Slash,
+                    op(TokenKind::Minus, b.clone(), root.clone())?,
+                    denominator.clone(),
+                )?,
+                op(TokenKind::Slash, op(TokenKind::Plus, b, root)?, denominator)?,
+            ])
+        }
+        _ => Err(MatikaError::type_error(format!(
+            "there is no closed form for the roots of {} coefficients, only of two or three",
+            coefficients.len()
+        ))),
+    }
+}
+
+/// Roots of a real polynomial of degree three or more: Cardano's and
+/// Ferrari's formulas up to degree four, Durand-Kerner iteration beyond,
+/// each root polished with Newton's method.
+fn numeric(coefficients: &[f64]) -> Vec<Value> {
+    let lead = coefficients[coefficients.len() - 1];
+    let monic: Vec<Complex> = coefficients
+        .iter()
+        .map(|coefficient| Complex::from(coefficient / lead))
+        .collect();
+
+    let roots = match monic.as_slice() {
+        [c, b, a, _] => cubic(*a, *b, *c).to_vec(),
+        [d, c, b, a, _] => quartic(*a, *b, *c, *d).to_vec(),
+        _ => durand_kerner(&monic),
+    };
+
+    roots
+        .into_iter()
+        .map(|root| Value::complex(snap(newton(&monic, root))))
+        .collect()
+}
+
+/// The roots of `x^3 + a*x^2 + b*x + c`.
+fn cubic(a: Complex, b: Complex, c: Complex) -> [Complex; 3] {
+    let third = Complex::from(1.0 / 3.0);
+    let shift = a * third;
+    let p = b - a * shift;
+    let q = Complex::from(2.0) * shift * shift * shift - shift * b + c;
+    let half = Complex::from(-0.5) * q;
+    let root = (half * half + p * p * p / Complex::from(27.0)).sqrt();
+    let u = if (half + root).abs() >= (half - root).abs() {
+        half + root
+    } else {
+        half - root
+    };
+    let u = Complex::from_polar(u.abs().cbrt(), u.arg() / 3.0);
+    let turn = Complex::from_polar(1.0, 2.0 * std::f64::consts::PI / 3.0);
+    let mut roots = [Complex::default(); 3];
+    let mut w = u;
+
+    for root in roots.iter_mut() {
+        let t = if w.abs() == 0.0 {
+            w
+        } else {
+            w - p / (Complex::from(3.0) * w)
+        };
+
+        *root = t - shift;
+        w = w * turn;
+    }
+
+    roots
+}
+
+/// The roots of `x^4 + a*x^3 + b*x^2 + c*x + d`, by reducing the depressed
+/// quartic to two quadratics through a root of its resolvent cubic.
+fn quartic(a: Complex, b: Complex, c: Complex, d: Complex) -> [Complex; 4] {
+    let shift = a / Complex::from(4.0);
+    let a2 = a * a;
+    let p = b - Complex::from(3.0 / 8.0) * a2;
+    let q = a2 * a / Complex::from(8.0) - a * b / Complex::from(2.0) + c;
+    let r = Complex::from(-3.0 / 256.0) * a2 * a2 + a2 * b / Complex::from(16.0)
+        - a * c / Complex::from(4.0)
+        + d;
+    let two = Complex::from(2.0);
+
+    let ys = if q.abs() <= TOLERANCE {
+        let root = (p * p - Complex::from(4.0) * r).sqrt();
+        let (z1, z2) = ((-p + root) / two, (-p - root) / two);
+
+        [z1.sqrt(), -z1.sqrt(), z2.sqrt(), -z2.sqrt()]
+    } else {
+        let m = cubic(
+            p,
+            (p * p / two - two * r) / two,
+            -(q * q) / Complex::from(8.0),
+        )
+        .iter()
+        .copied()
+        .fold(Complex::default(), |best, m| {
+            if m.abs() > best.abs() {
+                m
+            } else {
+                best
+            }
+        });
+        let s = (two * m).sqrt();
+        let plus = (-(two * p + two * m + two * q / s)).sqrt();
+        let minus = (-(two * p + two * m - two * q / s)).sqrt();
+
+        [
+            (s + plus) / two,
+            (s - plus) / two,
+            (-s + minus) / two,
+            (-s - minus) / two,
+        ]
+    };
+
+    [ys[0] - shift, ys[1] - shift, ys[2] - shift, ys[3] - shift]
+}
+
+/// All roots of a monic polynomial at once, each refined against the others.
+fn durand_kerner(monic: &[Complex]) -> Vec<Complex> {
+    let degree = monic.len() - 1;
+    let seed = Complex::new(0.4, 0.9);
+    let mut roots = vec![Complex::from(1.0); degree];
+
+    for k in 1..degree {
+        roots[k] = roots[k - 1] * seed;
+    }
+
+    for _ in 0..MAX_ITERATIONS * 10 {
+        let mut moved: f64 = 0.0;
+
+        for i in 0..degree {
+            let mut denominator = Complex::from(1.0);
+
+            for j in 0..degree {
+                if i != j {
+                    denominator = denominator * (roots[i] - roots[j]);
+                }
+            }
+
+            let step = horner(monic, roots[i]).0 / denominator;
+
+            roots[i] = roots[i] - step;
+            moved = moved.max(step.abs() / (1.0 + roots[i].abs()));
+        }
+
+        if moved <= TOLERANCE {
+            break;
+        }
+    }
+
+    roots
+}
+
+/// Newton's method from `z`, keeping the best point found.
+fn newton(coefficients: &[Complex], mut z: Complex) -> Complex {
+    let mut residual = horner(coefficients, z).0.abs();
+
+    for _ in 0..MAX_ITERATIONS {
+        let (value, slope) = horner(coefficients, z);
+
+        if slope.abs() == 0.0 {
+            break;
+        }
+
+        let next = z - value / slope;
+        let next_residual = horner(coefficients, next).0.abs();
+
+        if next_residual.partial_cmp(&residual) != Some(Ordering::Less) {
+            break;
+        }
+
+        let step = (next - z).abs();
+
+        z = next;
+        residual = next_residual;
+
+        if step <= TOLERANCE * (1.0 + z.abs()) {
+            break;
+        }
+    }
+
+    z
+}
+
+/// The polynomial and its derivative at `z`.
+fn horner(coefficients: &[Complex], z: Complex) -> (Complex, Complex) {
+    coefficients.iter().rev().fold(
+        (Complex::default(), Complex::default()),
+        |(value, slope), coefficient| (value * z + *coefficient, slope * z + value),
+    )
+}
+
+fn snap(z: Complex) -> Complex {
+    let scale = 1.0 + z.abs();
+    let re = if (z.re - z.re.round()).abs() <= SNAP * scale {
+        z.re.round()
+    } else {
+        z.re
+    };
+    let im = if z.im.abs() <= SNAP * scale {
+        0.0
+    } else {
+        z.im
+    };
+
+    Complex::new(re, im)
+}
+
+/// Real roots in `[-SEARCH_BOUND, SEARCH_BOUND]`. Sign changes between
+/// samples are narrowed down with Brent's method, and minima of `|f|` that
+/// touch zero without crossing it are found with Newton's method.
+fn search(interpreter: &mut Interpreter, target: &Target) -> Result<Vec<Value>, MatikaError> {
+    let width = 2.0 * SEARCH_BOUND / SAMPLES as f64;
+    let mut samples = Vec::with_capacity(SAMPLES + 1);
+
+    for i in 0..=SAMPLES {
+        let x = -SEARCH_BOUND + i as f64 * width;
+
+        samples.push((x, target.at(interpreter, x)?));
+    }
+
+    let mut roots = vec![];
+
+    for i in 0..=SAMPLES {
+        let (x, y) = samples[i];
+
+        if y == 0.0 {
+            roots.push(x);
+            continue;
+        }
+
+        let next = match samples.get(i + 1) {
+            Some(next) if next.1 != 0.0 => *next,
+            _ => continue,
+        };
+
+        if crosses(y, next.1) {
+            if let Some(root) = brent(interpreter, target, (x, y), next)? {
+                roots.push(root);
+            }
+        } else if i > 0 && touches(samples[i - 1].1, y, next.1) {
+            if let Some(root) = touching(interpreter, target, x, width)? {
+                roots.push(root);
+            }
+        }
+    }
+
+    Ok(roots
+        .into_iter()
+        .map(|root| Value::Number(snap(Complex::from(root)).re))
+        .collect())
+}
+
+fn crosses(a: f64, b: f64) -> bool {
+    a.is_finite() && b.is_finite() && a.signum() != b.signum()
+}
+
+/// Whether `y` is a local minimum of `|f|` between samples of the same sign.
+fn touches(before: f64, y: f64, after: f64) -> bool {
+    before.is_finite()
+        && after.is_finite()
+        && !crosses(before, y)
+        && y.abs() < before.abs()
+        && y.abs() <= after.abs()
+}
+
+/// Brent's method on a bracketing interval. Returns `None` when the sign
+/// change turns out to be a pole rather than a root.
+fn brent(
+    interpreter: &mut Interpreter,
+    target: &Target,
+    (mut a, mut fa): (f64, f64),
+    (mut b, mut fb): (f64, f64),
+) -> Result<Option<f64>, MatikaError> {
+    let bound = fa.abs().min(fb.abs());
+
+    if fa.abs() < fb.abs() {
+        swap(&mut a, &mut b);
+        swap(&mut fa, &mut fb);
+    }
+
+    let (mut c, mut fc) = (a, fa);
+    let mut d = c;
+    let mut bisected = true;
+
+    for _ in 0..MAX_ITERATIONS {
+        if fb == 0.0 || (b - a).abs() <= TOLERANCE * (1.0 + b.abs()) {
+            break;
+        }
+
+        let interpolated = if fa != fc && fb != fc {
+            a * fb * fc / ((fa - fb) * (fa - fc))
+                + b * fa * fc / ((fb - fa) * (fb - fc))
+                + c * fa * fb / ((fc - fa) * (fc - fb))
+        } else {
+            b - fb * (b - a) / (fb - fa)
+        };
+
+        let quarter = (3.0 * a + b) / 4.0;
+        let slow = if bisected {
+            (interpolated - b).abs() >= (b - c).abs() / 2.0
+        } else {
+            (interpolated - b).abs() >= (c - d).abs() / 2.0
+        };
+        let outside = interpolated <= quarter.min(b) || interpolated >= quarter.max(b);
+
+        let s = if outside || slow || !interpolated.is_finite() {
+            (a + b) / 2.0
+        } else {
+            interpolated
+        };
+
+        bisected = s != interpolated;
+
+        let fs = target.at(interpreter, s)?;
+
+        if fs.is_nan() {
+            return Ok(None);
+        }
+
+        d = c;
+        c = b;
+        fc = fb;
+
+        if crosses(fa, fs) {
+            b = s;
+            fb = fs;
+        } else {
+            a = s;
+            fa = fs;
+        }
+
+        if fa.abs() < fb.abs() {
+            swap(&mut a, &mut b);
+            swap(&mut fa, &mut fb);
+        }
+    }
+
+    Ok(if fb.abs() <= bound { Some(b) } else { None })
+}
+
+/// Newton's method from a sample where `|f|` dips towards zero, with a
+/// numeric derivative. Accepts the point only if it stays within a sample of
+/// where it started and `f` vanishes there.
+fn touching(
+    interpreter: &mut Interpreter,
+    target: &Target,
+    start: f64,
+    width: f64,
+) -> Result<Option<f64>, MatikaError> {
+    let mut x = start;
+
+    for _ in 0..MAX_ITERATIONS {
+        let y = target.at(interpreter, x)?;
+
+        if y == 0.0 {
+            break;
+        }
+
+        let h = 1e-6 * (1.0 + x.abs());
+        let slope = (target.at(interpreter, x + h)? - target.at(interpreter, x - h)?) / (2.0 * h);
+        let next = x - y / slope;
+
+        if !next.is_finite() || (next - start).abs() > width {
+            return Ok(None);
+        }
+
+        let step = (next - x).abs();
+
+        x = next;
+
+        if step <= TOLERANCE * (1.0 + x.abs()) {
+            break;
+        }
+    }
+
+    Ok(if target.at(interpreter, x)?.abs() <= SNAP {
+        Some(x)
+    } else {
+        None
+    })
+}
+
+/// Drops repeated roots and orders the rest: real roots ascending, then
+/// complex ones by real and imaginary part. Expressions keep their order,
+/// after the numbers.
+fn sorted(roots: Vec<Value>) -> Vec<Value> {
+    let mut unique: Vec<Value> = vec![];
+
+    for root in roots {
+        let repeated = unique
+            .iter()
+            .any(|other| match (root.as_complex(), other.as_complex()) {
+                (Some(a), Some(b)) => (a - b).abs() <= SAME_ROOT * (1.0 + a.abs()),
+                _ => root.equals(other),
+            });
+
+        if !repeated {
+            unique.push(root);
+        }
+    }
+
+    let key = |value: &Value| value.as_complex().map(|z| (z.im != 0.0, z.re, z.im));
+
+    unique.sort_by(|a, b| match (key(a), key(b)) {
+        (Some(a), Some(b)) => a.partial_cmp(&b).unwrap_or(Ordering::Equal),
+        (Some(_), None) => Ordering::Less,
+        (None, Some(_)) => Ordering::Greater,
+        (None, None) => Ordering::Equal,
+    });
+
+    unique
+}
+
+#[cfg(test)]
+mod tests {
+    use super::closed;
+    use crate::complex::Complex;
+    use crate::tests::{eval, session, value};
+    use crate::Value;
+
+    #[test]
+    fn functions_solve_for_their_parameter_when_it_is_bound() {
+        let solved = |line| session(&["x = 0.5", "g(x) = x^2 - 4", line]);
+
+        assert_eq!(solved("solve(g, x)").unwrap(), "[-2, 2]");
+        assert_eq!(solved("roots(y -> y^2 + 1, x)").unwrap(), "[-i, i]");
+        assert_eq!(solved("solve(g)").unwrap(), "[-2, 2]");
+    }
+
+    #[test]
+    fn bound_names_are_reported() {
+        for line in [
+            "solve(x^2 - 4 == 0, x)",
+            "roots(x^2 - 4, x)",
+            "solve([x + y == 1, x - y == 0], [x, y])",
+        ] {
+            let err = session(&["x = 0.5", line]).unwrap_err();
+
+            assert!(err.contains("got 0.5, the value of x"), "{}", err);
+        }
+
+        let err = session(&["x = 0.5", "solve(x^2 - 4)"]).unwrap_err();
+
+        assert!(err.contains("no name to solve"), "{}", err);
+    }
+
+    #[test]
+    fn closed_forms_reject_other_degrees() {
+        let coefficients = |n: i64| (1..=n).map(Value::integer).collect::<Vec<_>>();
+
+        assert!(closed(&coefficients(1)).is_err());
+        assert!(closed(&coefficients(4)).is_err());
+        assert_eq!(closed(&coefficients(2)).unwrap().len(), 1);
+        assert_eq!(closed(&coefficients(3)).unwrap().len(), 2);
+    }
+
+    /// The roots `roots(src)` finds, as complex numbers.
+    fn complex_roots(src: &str) -> Vec<Complex> {
+        match value(&format!("roots({})", src)) {
+            Value::List(roots) => roots
+                .iter()
+                .map(|root| root.as_complex().unwrap())
+                .collect(),
+            value => panic!("expected a list, got {}", value),
+        }
+    }
+
+    /// Checks that `roots` finds `count` roots of the polynomial with the
+    /// given coefficients, lowest degree first, and that each is a root.
+    fn check_roots(coefficients: &[f64], count: usize) {
+        let src = coefficients
+            .iter()
+            .enumerate()
+            .map(|(power, coefficient)| format!("({})*x^{}", coefficient, power))
+            .collect::<Vec<_>>()
+            .join(" + ");
+        let roots = complex_roots(&src);
+
+        assert_eq!(roots.len(), count, "{}", src);
+
+        for root in roots {
+            let value = coefficients
+                .iter()
+                .rev()
+                .fold(Complex::default(), |value, coefficient| {
+                    value * root + Complex::new(*coefficient, 0.0)
+                });
+
+            assert!(value.abs() < 1e-9, "{} at {} is {}", src, root, value);
+        }
+    }
+
+    #[test]
+    fn exact_polynomial_roots() {
+        assert_eq!(eval("solve(2x - 3)"), "[3/2]");
+        assert_eq!(eval("solve(x^2 - 5x + 6 == 0)"), "[2, 3]");
+        assert_eq!(eval("roots(x^2 + 1)"), "[-i, i]");
+        assert_eq!(eval("solve(x^3 - 6x^2 + 11x - 6)"), "[1, 2, 3]");
+        assert_eq!(eval("solve(x^4 - 5x^2 + 4)"), "[-2, -1, 1, 2]");
+        assert_eq!(
+            eval("solve(x^5 - 15x^4 + 85x^3 - 225x^2 + 274x - 120)"),
+            "[1, 2, 3, 4, 5]"
+        );
+    }
+
+    #[test]
+    fn roots_of_degrees_one_to_five() {
+        check_roots(&[-3.0, 2.0], 1);
+        check_roots(&[1.0, 1.0, 1.0], 2);
+        check_roots(&[-2.0, 0.0, 1.0], 2);
+        check_roots(&[-1.0, 0.0, 0.0, 1.0], 3);
+        check_roots(&[1.0, -2.0, 0.5, 3.0], 3);
+        check_roots(&[1.0, 0.0, 0.0, 0.0, 1.0], 4);
+        check_roots(&[-2.0, 1.0, 3.0, -1.0, 1.0], 4);
+        check_roots(&[-1.0, -1.0, 0.0, 0.0, 0.0, 1.0], 5);
+        check_roots(&[-1.0, 0.0, 0.0, 0.0, 0.0, 1.0], 5);
+    }
+
+    #[test]
+    fn linear_systems() {
+        assert_eq!(eval("solve([x + y == 3, x - y == 1], [x, y])"), "[2, 1]");
+        assert_eq!(
+            eval("solve([x + y + z == 6, x - y == 0, 2z == 6])"),
+            "[3/2, 3/2, 3]"
+        );
+        assert_eq!(
+            eval("solve([a*x + y == 1, x - y == 0], [x, y])"),
+            "[1/(a + 1), 1/(a + 1)]"
+        );
+        assert_eq!(eval("solve([[2, 1], [1, 3]], [3, 5])"), "[0.8, 1.4]");
+    }
+
+    #[test]
+    fn singular_and_nonlinear_systems() {
+        for src in [
+            "solve([x + y == 1, 2x + 2y == 2], [x, y])",
+            "solve([x + y == 1, x + y == 2], [x, y])",
+            "solve([[1, 2], [2, 4]], [1, 2])",
+        ] {
+            let err = session(&[src]).unwrap_err();
+
+            assert!(err.contains("no unique solution"), "{}: {}", src, err);
+        }
+
+        let err = session(&["solve([x^2 + y == 1, x - y == 0], [x, y])"]).unwrap_err();
+
+        assert!(err.contains("not linear"), "{}", err);
+    }
+}
@@ -1,22 +1,121 @@
-use crate::token::Token;
+use std::fmt;
+
+use crate::token::{LiteralKind, Span, Token, TokenKind};
 use crate::{
     visitor::{Acceptor, Visitor},
     Expr,
 };
 
-#[derive(Debug)]
-pub struct Variable {
-    pub name: Token,
-    pub initializer: Expr,
+#[derive(Debug, Clone)]
+pub enum Pattern {
+    Binding(Token),
+    Literal { value: LiteralKind, span: Span },
+}
+
+impl Pattern {
+    /// Two patterns have the same shape when they accept exactly the same arguments.
+    pub fn same_shape(&self, other: &Pattern) -> bool {
+        match (self, other) {
+            (Pattern::Binding(_), Pattern::Binding(_)) => true,
+            (Pattern::Literal { value, .. }, Pattern::Literal { value: other, .. }) => {
+                value == other
+            }
+            _ => false,
+        }
+    }
+
+    pub fn subsumes(&self, other: &Pattern) -> bool {
+        matches!(self, Pattern::Binding(_)) || self.same_shape(other)
+    }
 }
 
 #[derive(Debug, Clone)]
 pub struct Fnc {
     pub name: Token,
-    pub params: Vec<Token>,
+    pub params: Vec<Pattern>,
     pub body: Expr,
 }
 
+impl Fnc {
+    /// `(x) -> name(x)` for a builtin `name` taking `arity` arguments, so it can
+    /// be handled like a user function.
+    pub fn call(name: &str, arity: u64) -> Self {
+        let params: Vec<Token> = ["x", "y", "z"]
+            .iter()
+            .map(|param| param.to_string())
+            .chain((3..arity).map(|idx| format!("x{}", idx)))
+            .take(arity as usize)
+            .map(|param| Token::synthetic(TokenKind::Identifier, &param, Span::default()))
+            .collect();
+
+        Fnc {
+            name: Token::synthetic(TokenKind::Identifier, name, Span::default()),
+            body: Expr::Call {
+                callee: Box::new(Expr::Variable {
+                    name: Token::synthetic(TokenKind::Identifier, name, Span::default()),
+                    depth: None,
+                }),
+                args: params
+                    .iter()
+                    .map(|param| Expr::Variable {
+                        name: param.clone(),
+                        depth: None,
+                    })
+                    .collect(),
+                span: Span::default(),
+            },
+            params: params.into_iter().map(Pattern::Binding).collect(),
+        }
+    }
+
+    /// The clause as it is defined, `name(x, y) = body`.
+    pub fn definition(&self) -> String {
+        format!(
+            "{}({}) = {}",
+            self.name.lexeme,
+            self.param_names().join(", "),
+            self.body
+        )
+    }
+
+    fn param_names(&self) -> Vec<String> {
+        self.params
+            .iter()
+            .map(|param| match param {
+                Pattern::Binding(token) => token.lexeme.clone(),
+                Pattern::Literal { value, .. } => value.to_string(),
+            })
+            .collect()
+    }
+
+    pub fn same_shape(&self, other: &Fnc) -> bool {
+        self.params
+            .iter()
+            .zip(other.params.iter())
+            .all(|(param, other)| param.same_shape(other))
+    }
+
+    pub fn subsumes(&self, other: &Fnc) -> bool {
+        self.params
+            .iter()
+            .zip(other.params.iter())
+            .all(|(param, other)| param.subsumes(other))
+    }
+}
+
+/// Prints the clause in lambda form, `(x, y) -> body`.
+impl fmt::Display for Fnc {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let params = self.param_names();
+
+        if params.len() == 1 {
+            write!(f, "{} -> {}", params[0], self.body)
+        } else {
+            write!(f, "({}) -> {}", params.join(", "), self.body)
+        }
+    }
+}
+
 #[derive(Debug, Clone)]
 pub enum Stmt {
     Print(Expr),
@@ -25,6 +124,17 @@ pub enum Stmt {
     Variable { name: Token, initializer: Expr },
 }
 
+impl Stmt {
+    pub fn span(&self) -> Span {
+        match self {
+            Stmt::Print(expr) => expr.span(),
+            Stmt::Expression(expr) => expr.span(),
+            Stmt::Function(fnc) => fnc.name.span.to(fnc.body.span()),
+            Stmt::Variable { name, initializer } => name.span.to(initializer.span()),
+        }
+    }
+}
+
 impl Acceptor<Stmt> for Stmt {
     fn accept<V: Visitor<Stmt>>(&self, mut visitor: V) -> V::Result {
         visitor.visit(self)
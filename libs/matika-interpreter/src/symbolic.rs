@@ -0,0 +1,162 @@
+use std::rc::Rc;
+
+use crate::environment::Environment;
+use crate::error::MatikaError;
+use crate::expr::Expr;
+use crate::simplify::simplify;
+use crate::token::{LiteralKind, Span, Token, TokenKind};
+use crate::value::Value;
+use crate::Interpreter;
+
+/// Arithmetic on unbound names and expressions over them: the operation is
+/// recorded as an expression and simplified, so `2a + 3a` is `5a`.
+pub fn binary(op: TokenKind, left: &Value, right: &Value) -> Result<Value, MatikaError> {
+    let expr = Expr::Binary {
+        left: Box::new(to_expr(left)?),
+        op: Token::synthetic(op, &op.to_string(), Span::default()),
+        right: Box::new(to_expr(right)?),
+    };
+
+    Ok(from_expr(simplify(&expr)))
+}
+
+/// `left == right` as an equation, each side simplified.
+pub fn equation(left: &Value, right: &Value) -> Result<Value, MatikaError> {
+    Ok(Value::Equation(
+        Rc::new(simplify(&to_expr(left)?)),
+        Rc::new(simplify(&to_expr(right)?)),
+    ))
+}
+
+pub fn negate(value: &Value) -> Result<Value, MatikaError> {
+    binary(TokenKind::Star, &Value::integer(-1), value)
+}
+
+/// `name(arg)` for a builtin applied to an expression.
+pub fn call(name: &str, arg: &Value) -> Result<Value, MatikaError> {
+    let expr = Expr::Call {
+        callee: Box::new(variable(name)),
+        args: vec![to_expr(arg)?],
+        span: Span::default(),
+    };
+
+    Ok(from_expr(simplify(&expr)))
+}
+
+/// Replaces the unbound name `symbol` in `value` by `replacement`, evaluating
+/// what becomes computable. An equation whose sides become numbers turns into
+/// whether they are equal.
+pub fn substitute(
+    interpreter: &mut Interpreter,
+    value: &Value,
+    symbol: &str,
+    replacement: Value,
+) -> Result<Value, MatikaError> {
+    let env = Environment::from_enclosing(interpreter.environment.clone());
+
+    env.define(symbol.to_string(), replacement);
+
+    match value {
+        Value::Equation(left, right) => {
+            let left = interpreter.evaluate_inner(left, env.clone())?;
+            let right = interpreter.evaluate_inner(right, env)?;
+
+            Value::binary(TokenKind::EqualEqual, left, right)
+        }
+        value => interpreter.evaluate_inner(&to_expr(value)?, env),
+    }
+}
+
+/// The value as an expression, for the numbers and symbols expressions are
+/// made of.
+pub fn to_expr(value: &Value) -> Result<Expr, MatikaError> {
+    let literal = |value: LiteralKind| Expr::Literal {
+        value,
+        span: Span::default(),
+    };
+
+    match value {
+        Value::Unbound(name) => Ok(variable(name)),
+        Value::Symbolic(expr) => Ok(expr.as_ref().clone()),
+        Value::Rational(ratio) if ratio.is_integer() => {
+            Ok(literal(LiteralKind::Integer(ratio.to_integer())))
+        }
+        Value::Rational(ratio) => Ok(Expr::Binary {
+            left: Box::new(literal(LiteralKind::Integer(ratio.numer().clone()))),
+            op: Token::synthetic(TokenKind::Slash, "/", Span::default()),
+            right: Box::new(literal(LiteralKind::Integer(ratio.denom().clone()))),
+        }),
+        Value::Number(number) => Ok(literal(LiteralKind::Number(*number))),
+        value => Err(MatikaError::type_error(format!(
+            "cannot use {} in an expression",
+            value
+        ))),
+    }
+}
+
+/// Wraps `expr` as a value, which is a plain number or name when the
+/// expression is just that.
+pub fn from_expr(expr: Expr) -> Value {
+    match &expr {
+        Expr::Variable { name, .. } => return Value::Unbound(name.lexeme.clone()),
+        Expr::Grouping { expr, .. } => return from_expr(expr.as_ref().clone()),
+        _ => (),
+    }
+
+    match constant(&expr) {
+        Some(value) => value,
+        None => Value::Symbolic(Rc::new(expr)),
+    }
+}
+
+/// Folds an expression made of numbers only.
+fn constant(expr: &Expr) -> Option<Value> {
+    match expr {
+        Expr::Literal { value, .. } if value.number().is_some() => Some(Value::from(value)),
+        Expr::Grouping { expr, .. } => constant(expr),
+        Expr::Unary { op, right } if op.kind == TokenKind::Minus => (-constant(right)?).ok(),
+        Expr::Binary { left, op, right } => {
+            Value::binary(op.kind, constant(left)?, constant(right)?).ok()
+        }
+        _ => None,
+    }
+}
+
+fn variable(name: &str) -> Expr {
+    Expr::Variable {
+        name: Token::synthetic(TokenKind::Identifier, name, Span::default()),
+        depth: None,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::tests::{eval, session};
+
+    #[test]
+    fn unbound_names_combine() {
+        assert_eq!(eval("2a + 3a"), "5a");
+        assert_eq!(eval("2a - 2a"), "0");
+        assert_eq!(eval("a*b*a"), "a^2*b");
+        assert_eq!(eval("[a, 2a] + [a, a]"), "[2a, 3a]");
+        assert_eq!(eval("sin(a)"), "sin(a)");
+        assert_eq!(eval("a + 1 == 2"), "a + 1 == 2");
+    }
+
+    #[test]
+    fn substitution_evaluates_what_it_can() {
+        assert_eq!(eval("subs(a^2 + 1, a, 2)"), "5");
+        assert_eq!(eval("subs(a*b, a, b)"), "b^2");
+        assert_eq!(eval("subs(sin(a), a, 0)"), "0");
+        assert_eq!(eval("subs(a == 2, a, 2)"), "true");
+        assert_eq!(eval("subs(a == 2, a, 3)"), "false");
+    }
+
+    #[test]
+    fn user_functions_apply_to_expressions() {
+        assert_eq!(
+            session(&["f(t) = t^2 - 1", "f(a + 1)"]).unwrap(),
+            "(a + 1)^2 - 1"
+        );
+    }
+}
@@ -1,18 +1,32 @@
 use std::fmt;
 
-#[derive(Debug, PartialEq, Clone)]
+use num_bigint::BigInt;
+use num_traits::ToPrimitive;
+
+#[derive(Debug, PartialEq, Eq, Clone, Copy)]
 pub enum TokenKind {
     LeftParen,
     RightParen,
+    LeftBracket,
+    RightBracket,
+    Colon,
     Dot,
     Minus,
     Plus,
+    PlusPlus,
+    Arrow,
+    Compose,
     Slash,
     Star,
     Caret,
+    Root,
     Comma,
+    Semicolon,
+    Newline,
     Bang,
+    BangEqual,
     Equal,
+    EqualEqual,
     Greater,
     GreaterEqual,
     Less,
@@ -21,25 +35,118 @@ pub enum TokenKind {
     Number,
     Eof,
     Print,
+    And,
+    Or,
+    Not,
+    True,
+    False,
+    If,
+    Else,
 }
 
 impl fmt::Display for TokenKind {
     fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
-        match self {
-            _ => write!(f, "{}", self),
-        }
+        let text = match self {
+            TokenKind::LeftParen => "(",
+            TokenKind::RightParen => ")",
+            TokenKind::LeftBracket => "[",
+            TokenKind::RightBracket => "]",
+            TokenKind::Colon => ":",
+            TokenKind::Dot => ".",
+            TokenKind::Minus => "-",
+            TokenKind::Plus => "+",
+            TokenKind::PlusPlus => "++",
+            TokenKind::Arrow => "->",
+            TokenKind::Compose => "∘",
+            TokenKind::Slash => "/",
+            TokenKind::Star => "*",
+            TokenKind::Caret => "^",
+            TokenKind::Root => "√",
+            TokenKind::Comma => ",",
+            TokenKind::Semicolon => ";",
+            TokenKind::Newline => "newline",
+            TokenKind::Bang => "!",
+            TokenKind::BangEqual => "!=",
+            TokenKind::Equal => "=",
+            TokenKind::EqualEqual => "==",
+            TokenKind::Greater => ">",
+            TokenKind::GreaterEqual => ">=",
+            TokenKind::Less => "<",
+            TokenKind::LessEqual => "<=",
+            TokenKind::Identifier => "identifier",
+            TokenKind::Number => "number",
+            TokenKind::Eof => "end of input",
+            TokenKind::Print => "print",
+            TokenKind::And => "and",
+            TokenKind::Or => "or",
+            TokenKind::Not => "not",
+            TokenKind::True => "true",
+            TokenKind::False => "false",
+            TokenKind::If => "if",
+            TokenKind::Else => "else",
+        };
+
+        write!(f, "{}", text)
     }
 }
 
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, PartialEq)]
 pub enum LiteralKind {
     Number(f64),
+    Integer(BigInt),
+    Bool(bool),
+}
+
+impl LiteralKind {
+    pub fn number(&self) -> Option<f64> {
+        match self {
+            LiteralKind::Number(number) => Some(*number),
+            LiteralKind::Integer(integer) => integer.to_f64(),
+            LiteralKind::Bool(_) => None,
+        }
+    }
+
+    pub fn negate(&self) -> Option<Self> {
+        match self {
+            LiteralKind::Number(number) => Some(LiteralKind::Number(-number)),
+            LiteralKind::Integer(integer) => Some(LiteralKind::Integer(-integer)),
+            LiteralKind::Bool(_) => None,
+        }
+    }
 }
 
 impl fmt::Display for LiteralKind {
     fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
         match self {
             LiteralKind::Number(num) => write!(f, "{}", num),
+            LiteralKind::Integer(integer) => write!(f, "{}", integer),
+            LiteralKind::Bool(value) => write!(f, "{}", value),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Default)]
+pub struct Span {
+    pub start: usize,
+    pub end: usize,
+    pub line: usize,
+    pub column: usize,
+}
+
+impl Span {
+    pub fn new(start: usize, end: usize, line: usize, column: usize) -> Self {
+        Self {
+            start,
+            end,
+            line,
+            column,
+        }
+    }
+
+    pub fn to(self, other: Span) -> Self {
+        Self {
+            end: self.end.max(other.end),
+            ..self
         }
     }
 }
@@ -49,22 +156,29 @@ pub struct Token {
     pub kind: TokenKind,
     pub lexeme: String,
     pub literal: Option<LiteralKind>,
+    pub span: Span,
 }
 
 impl Token {
-    pub fn eof() -> Self {
+    pub fn eof(span: Span) -> Self {
         Self {
             kind: TokenKind::Eof,
             lexeme: "".into(),
             literal: None,
+            span,
         }
     }
 
-    pub fn star() -> Self {
+    pub fn star(span: Span) -> Self {
+        Self::synthetic(TokenKind::Star, "*", span)
+    }
+
+    pub fn synthetic(kind: TokenKind, lexeme: &str, span: Span) -> Self {
         Self {
-            kind: TokenKind::Star,
-            lexeme: "*".into(),
+            kind,
+            lexeme: lexeme.into(),
             literal: None,
+            span,
         }
     }
 }
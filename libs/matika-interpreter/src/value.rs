@@ -1,24 +1,589 @@
+use std::cmp::Ordering;
 use std::rc::Rc;
 use std::{fmt, ops};
 
+use num_bigint::BigInt;
+use num_rational::BigRational;
+use num_traits::{FromPrimitive, ToPrimitive, Zero};
+
 use crate::callable::Callable;
+use crate::complex::Complex;
+use crate::error::MatikaError;
+use crate::expr::Expr;
+use crate::functions::{Composition, Inverse, Pointwise};
+use crate::matrix::Matrix;
+use crate::symbolic;
+use crate::token::{LiteralKind, TokenKind};
 
 #[derive(Debug, Clone)]
 pub enum Value {
     Unbound(String),
     Number(f64),
-    List(Vec<f64>),
+    Rational(BigRational),
+    Complex(Complex),
+    Bool(bool),
+    List(Vec<Value>),
+    Matrix(Matrix),
     Function(Rc<dyn Callable>),
+    /// An expression over unbound names, kept simplified.
+    Symbolic(Rc<Expr>),
+    /// `left == right` where either side is symbolic, as taken by `solve`.
+    Equation(Rc<Expr>, Rc<Expr>),
 }
 
 impl Value {
-    pub fn powf(&self, other: Self) -> Self {
+    /// Wraps `z`, falling back to a plain number when it has no imaginary part.
+    pub fn complex(z: Complex) -> Self {
+        if z.im == 0.0 {
+            Value::Number(z.re)
+        } else {
+            Value::Complex(z)
+        }
+    }
+
+    pub fn integer(integer: impl Into<BigInt>) -> Self {
+        Value::Rational(BigRational::from_integer(integer.into()))
+    }
+
+    /// The value as an exact integer, accepting integral floats too.
+    pub fn as_integer(&self) -> Option<BigInt> {
+        match self {
+            Value::Rational(ratio) if ratio.is_integer() => Some(ratio.to_integer()),
+            Value::Number(number) if number.fract() == 0.0 => BigInt::from_f64(*number),
+            _ => None,
+        }
+    }
+
+    /// The value as a float, for real numbers only.
+    pub fn as_f64(&self) -> Option<f64> {
+        match self {
+            Value::Number(number) => Some(*number),
+            Value::Rational(ratio) => ratio.to_f64(),
+            _ => None,
+        }
+    }
+
+    pub fn as_complex(&self) -> Option<Complex> {
+        match self {
+            Value::Complex(z) => Some(*z),
+            _ => self.as_f64().map(Complex::from),
+        }
+    }
+
+    /// A matrix, or a list of equally long rows of real numbers as one.
+    pub fn to_matrix(&self) -> Option<Matrix> {
+        match self {
+            Value::Matrix(matrix) => Some(matrix.clone()),
+            Value::List(values) => values
+                .iter()
+                .map(|row| match row {
+                    Value::List(row) => row.iter().map(Value::as_f64).collect(),
+                    _ => None,
+                })
+                .collect::<Option<Vec<Vec<f64>>>>()
+                .and_then(|rows| Matrix::from_rows(rows).ok()),
+            _ => None,
+        }
+    }
+
+    /// The value as a matrix; a plain list of numbers is a single row.
+    pub fn as_matrix(&self, name: &str) -> Result<Matrix, MatikaError> {
+        if let Some(matrix) = self.to_matrix() {
+            return Ok(matrix);
+        }
+
+        match self {
+            Value::List(_) => self
+                .as_vector(name)
+                .and_then(|vector| Matrix::from_rows(vec![vector])),
+            _ => Err(MatikaError::type_error(format!(
+                "{} expects a matrix, got {}",
+                name,
+                self.type_name()
+            ))),
+        }
+    }
+
+    /// The value as a vector of floats, from a list of real numbers or a
+    /// matrix with a single row or column.
+    pub fn as_vector(&self, name: &str) -> Result<Vec<f64>, MatikaError> {
+        let vector = match self {
+            Value::List(values) => values.iter().map(Value::as_f64).collect(),
+            Value::Matrix(matrix) if matrix.rows() == 1 || matrix.cols() == 1 => {
+                Some(matrix.data().to_vec())
+            }
+            _ => None,
+        };
+
+        vector.ok_or_else(|| {
+            MatikaError::type_error(format!(
+                "{} expects a vector of numbers, got {}",
+                name,
+                self.type_name()
+            ))
+        })
+    }
+
+    /// Drops exactness, turning rationals into floats.
+    pub fn approx(self) -> Self {
         match self {
-            Value::Number(number) => match other {
-                Value::Number(other) => Value::Number(number.powf(other)),
-                _ => panic!(),
+            Value::Rational(ratio) => Value::Number(ratio.to_f64().unwrap_or(f64::NAN)),
+            Value::List(values) => Value::List(values.into_iter().map(Value::approx).collect()),
+            value => value,
+        }
+    }
+
+    pub fn powf(&self, other: Self) -> Result<Self, MatikaError> {
+        if let (Value::Rational(base), Value::Rational(exponent)) = (self, &other) {
+            if let Some(power) = exact_pow(base, exponent) {
+                return Ok(Value::Rational(power));
+            }
+        }
+
+        match (self.as_f64(), other.as_f64()) {
+            // A negative base with a fractional exponent has no real power.
+            (Some(base), Some(exponent)) if base >= 0.0 || exponent.fract() == 0.0 => {
+                Ok(Value::Number(base.powf(exponent)))
+            }
+            _ => self.complex_op("^", &other, Complex::powc),
+        }
+    }
+
+    /// Rationals stay exact when `exact` has a result, mixing in a float
+    /// falls back to `real`, and complex operands promote everything.
+    fn arithmetic(
+        &self,
+        symbol: &str,
+        other: &Value,
+        exact: fn(&BigRational, &BigRational) -> Option<BigRational>,
+        real: fn(f64, f64) -> f64,
+        complex: fn(Complex, Complex) -> Complex,
+    ) -> Result<Self, MatikaError> {
+        if let (Value::Rational(left), Value::Rational(right)) = (self, other) {
+            if let Some(result) = exact(left, right) {
+                return Ok(Value::Rational(result));
+            }
+        }
+
+        match (self.as_f64(), other.as_f64()) {
+            (Some(left), Some(right)) => Ok(Value::Number(real(left, right))),
+            _ => self.complex_op(symbol, other, complex),
+        }
+    }
+
+    /// Applies `op` to two numeric values, promoting reals to complex.
+    fn complex_op(
+        &self,
+        symbol: &str,
+        other: &Value,
+        op: fn(Complex, Complex) -> Complex,
+    ) -> Result<Self, MatikaError> {
+        match (self.as_complex(), other.as_complex()) {
+            (Some(left), Some(right)) => Ok(Value::complex(op(left, right))),
+            _ => Err(Value::unsupported(symbol, self, other)),
+        }
+    }
+
+    pub fn unary(op: TokenKind, right: Self) -> Result<Self, MatikaError> {
+        match op {
+            TokenKind::Minus => -right,
+            TokenKind::Bang | TokenKind::Not => !right,
+            _ => Err(MatikaError::type_error(format!(
+                "unsupported unary operator '{}'",
+                op
+            ))),
+        }
+    }
+
+    pub fn binary(op: TokenKind, left: Self, right: Self) -> Result<Self, MatikaError> {
+        let elementwise = matches!(
+            op,
+            TokenKind::Plus
+                | TokenKind::Minus
+                | TokenKind::Star
+                | TokenKind::Slash
+                | TokenKind::Caret
+        );
+
+        if let Value::Function(fnc) = &left {
+            if op == TokenKind::Caret && right.equals(&Value::integer(-1)) {
+                return Inverse::of(fnc.clone());
+            }
+        }
+
+        if op == TokenKind::Compose {
+            return Composition::of(left, right);
+        }
+
+        if elementwise && (left.is_function() || right.is_function()) {
+            return Pointwise::of(op, left, right);
+        }
+
+        if elementwise {
+            if let Some((matrix_left, matrix_right)) = Value::matrix_operands(op, &left, &right) {
+                // Lists that took part as matrices come back as lists of rows,
+                // printing and comparing like the results of broadcasting.
+                return match Value::matrix_op(op, matrix_left, matrix_right)? {
+                    Value::Matrix(matrix) if !left.is_matrix() && !right.is_matrix() => {
+                        Ok(Value::List(Value::rows(&matrix)))
+                    }
+                    value => Ok(value),
+                };
+            }
+        }
+
+        if elementwise && (left.is_list() || right.is_list()) {
+            return Value::broadcast(op, left, right);
+        }
+
+        if elementwise && (left.is_symbolic() || right.is_symbolic()) {
+            return symbolic::binary(op, &left, &right);
+        }
+
+        match op {
+            TokenKind::PlusPlus => left.concat(right),
+            TokenKind::Plus => left + right,
+            TokenKind::Minus => left - right,
+            TokenKind::Star => left * right,
+            TokenKind::Slash => left / right,
+            TokenKind::Caret => left.powf(right),
+            TokenKind::EqualEqual if left.is_symbolic() || right.is_symbolic() => {
+                symbolic::equation(&left, &right)
+            }
+            TokenKind::EqualEqual => Ok(Value::Bool(left.equals(&right))),
+            TokenKind::BangEqual => Ok(Value::Bool(!left.equals(&right))),
+            TokenKind::Greater => left
+                .compare(&right)
+                .map(|ordering| Value::Bool(ordering == Ordering::Greater)),
+            TokenKind::GreaterEqual => left
+                .compare(&right)
+                .map(|ordering| Value::Bool(ordering != Ordering::Less)),
+            TokenKind::Less => left
+                .compare(&right)
+                .map(|ordering| Value::Bool(ordering == Ordering::Less)),
+            TokenKind::LessEqual => left
+                .compare(&right)
+                .map(|ordering| Value::Bool(ordering != Ordering::Greater)),
+            _ => Err(MatikaError::type_error(format!(
+                "unsupported binary operator '{}'",
+                op
+            ))),
+        }
+    }
+
+    /// Applies `op` element by element, repeating a scalar operand across the
+    /// list, so `[1, 2] * 2` is `[2, 4]`.
+    fn broadcast(op: TokenKind, left: Self, right: Self) -> Result<Self, MatikaError> {
+        let values: Result<Vec<Value>, MatikaError> = match (left, right) {
+            (Value::List(left), Value::List(right)) => {
+                if left.len() != right.len() {
+                    return Err(MatikaError::type_error(format!(
+                        "cannot broadcast lists of length {} and {}",
+                        left.len(),
+                        right.len()
+                    )));
+                }
+
+                left.into_iter()
+                    .zip(right)
+                    .map(|(left, right)| Value::binary(op, left, right))
+                    .collect()
+            }
+            (Value::List(left), right) => left
+                .into_iter()
+                .map(|left| Value::binary(op, left, right.clone()))
+                .collect(),
+            (left, Value::List(right)) => right
+                .into_iter()
+                .map(|right| Value::binary(op, left.clone(), right))
+                .collect(),
+            (left, right) => return Value::binary(op, left, right),
+        };
+
+        values.map(Value::List)
+    }
+
+    /// The operands of `op` with matrix semantics, if any. Nested lists only
+    /// count as matrices where that differs from broadcasting them: `*` with a
+    /// matrix or vector on the other side is the matrix product, and `^` is a
+    /// matrix power. Otherwise they stay lists, keeping their entries exact.
+    fn matrix_operands(op: TokenKind, left: &Value, right: &Value) -> Option<(Value, Value)> {
+        let matrix = |value: &Value| value.to_matrix().map(Value::Matrix);
+        let vector = |value: &Value| match value {
+            Value::List(values) => values.iter().all(|value| value.as_f64().is_some()),
+            _ => false,
+        };
+
+        if left.is_matrix() || right.is_matrix() {
+            return Some((
+                matrix(left).unwrap_or_else(|| left.clone()),
+                matrix(right).unwrap_or_else(|| right.clone()),
+            ));
+        }
+
+        match op {
+            TokenKind::Star => match (matrix(left), matrix(right)) {
+                (Some(left), Some(right)) => Some((left, right)),
+                (Some(left), None) if vector(right) => Some((left, right.clone())),
+                (None, Some(right)) if vector(left) => Some((left.clone(), right)),
+                _ => None,
             },
-            _ => panic!(),
+            TokenKind::Caret => matrix(left).map(|left| (left, right.clone())),
+            _ => None,
+        }
+    }
+
+    /// Matrix arithmetic: `*` is the matrix product, also against a vector on
+    /// either side, `^` takes integer powers, and scalars apply to every entry.
+    fn matrix_op(op: TokenKind, left: Self, right: Self) -> Result<Self, MatikaError> {
+        let vector =
+            |values: Vec<f64>| Value::List(values.into_iter().map(Value::Number).collect());
+
+        match (op, &left, &right) {
+            (TokenKind::Plus, Value::Matrix(a), Value::Matrix(b)) => {
+                a.zip("+", b, |a, b| a + b).map(Value::Matrix)
+            }
+            (TokenKind::Minus, Value::Matrix(a), Value::Matrix(b)) => {
+                a.zip("-", b, |a, b| a - b).map(Value::Matrix)
+            }
+            (TokenKind::Star, Value::Matrix(a), Value::Matrix(b)) => a.mul(b).map(Value::Matrix),
+            (TokenKind::Star, Value::Matrix(a), Value::List(_)) => {
+                a.mul_vector(&right.as_vector("*")?).map(vector)
+            }
+            (TokenKind::Star, Value::List(_), Value::Matrix(b)) => {
+                b.transpose().mul_vector(&left.as_vector("*")?).map(vector)
+            }
+            (TokenKind::Caret, Value::Matrix(a), exponent) => {
+                match exponent.as_integer().and_then(|n| n.to_i64()) {
+                    Some(n) => a.powi(n).map(Value::Matrix),
+                    None => Err(MatikaError::type_error(format!(
+                        "matrix powers must be integers, got {}",
+                        exponent
+                    ))),
+                }
+            }
+            (_, Value::Matrix(a), scalar) if scalar.as_f64().is_some() => {
+                let x = scalar.as_f64().unwrap_or(f64::NAN);
+
+                match op {
+                    TokenKind::Plus => Ok(Value::Matrix(a.map(|a| a + x))),
+                    TokenKind::Minus => Ok(Value::Matrix(a.map(|a| a - x))),
+                    TokenKind::Star => Ok(Value::Matrix(a.map(|a| a * x))),
+                    TokenKind::Slash => Ok(Value::Matrix(a.map(|a| a / x))),
+                    _ => Err(Value::unsupported(&op.to_string(), &left, &right)),
+                }
+            }
+            (_, scalar, Value::Matrix(b)) if scalar.as_f64().is_some() => {
+                let x = scalar.as_f64().unwrap_or(f64::NAN);
+
+                match op {
+                    TokenKind::Plus => Ok(Value::Matrix(b.map(|b| x + b))),
+                    TokenKind::Minus => Ok(Value::Matrix(b.map(|b| x - b))),
+                    TokenKind::Star => Ok(Value::Matrix(b.map(|b| x * b))),
+                    _ => Err(Value::unsupported(&op.to_string(), &left, &right)),
+                }
+            }
+            _ => Err(Value::unsupported(&op.to_string(), &left, &right)),
+        }
+    }
+
+    /// The rows of a matrix as lists.
+    pub fn rows(matrix: &Matrix) -> Vec<Value> {
+        (0..matrix.rows())
+            .map(|i| Value::List(matrix.row(i).iter().map(|x| Value::Number(*x)).collect()))
+            .collect()
+    }
+
+    /// `xs ++ ys`; a matrix is concatenated as the list of its rows.
+    fn concat(self, other: Self) -> Result<Self, MatikaError> {
+        match (self, other) {
+            (Value::List(mut values), Value::List(other)) => {
+                values.extend(other);
+
+                Ok(Value::List(values))
+            }
+            (Value::Matrix(matrix), other) => Value::List(Value::rows(&matrix)).concat(other),
+            (left, Value::Matrix(matrix)) => left.concat(Value::List(Value::rows(&matrix))),
+            (left, right) => Err(Value::unsupported("++", &left, &right)),
+        }
+    }
+
+    /// `xs[i]`; negative indices count from the end. Indexing a matrix picks a row.
+    pub fn index(&self, index: &Value) -> Result<Self, MatikaError> {
+        if let Value::Matrix(matrix) = self {
+            return Value::List(Value::rows(matrix)).index(index);
+        }
+
+        let values = self.as_list("index")?;
+        let offset = Value::offset(index, values.len())?;
+
+        if offset < 0 || offset as usize >= values.len() {
+            return Err(MatikaError::type_error(format!(
+                "index {} is out of range for a list of length {}",
+                index,
+                values.len()
+            )));
+        }
+
+        Ok(values[offset as usize].clone())
+    }
+
+    /// `xs[start:end]`; bounds are clamped to the list like in Python.
+    pub fn slice(&self, start: Option<&Value>, end: Option<&Value>) -> Result<Self, MatikaError> {
+        if let Value::Matrix(matrix) = self {
+            let rows = Value::List(Value::rows(matrix)).slice(start, end)?;
+
+            return Ok(rows.to_matrix().map(Value::Matrix).unwrap_or(rows));
+        }
+
+        let values = self.as_list("slice")?;
+        let len = values.len() as i64;
+
+        let clamp = |bound: Option<&Value>, default: i64| match bound {
+            Some(bound) => Value::offset(bound, values.len()).map(|offset| offset.clamp(0, len)),
+            None => Ok(default),
+        };
+
+        let start = clamp(start, 0)? as usize;
+        let end = clamp(end, len)? as usize;
+
+        Ok(Value::List(
+            values
+                .get(start..end.max(start))
+                .unwrap_or_default()
+                .to_vec(),
+        ))
+    }
+
+    fn offset(index: &Value, len: usize) -> Result<i64, MatikaError> {
+        let offset = index
+            .as_integer()
+            .and_then(|index| index.to_i64())
+            .ok_or_else(|| {
+                MatikaError::type_error(format!("list indices must be integers, got {}", index))
+            })?;
+
+        Ok(if offset < 0 {
+            offset + len as i64
+        } else {
+            offset
+        })
+    }
+
+    fn as_list(&self, action: &str) -> Result<&Vec<Value>, MatikaError> {
+        match self {
+            Value::List(values) => Ok(values),
+            _ => Err(MatikaError::type_error(format!(
+                "cannot {} {}",
+                action,
+                self.type_name()
+            ))),
+        }
+    }
+
+    pub fn is_list(&self) -> bool {
+        matches!(self, Value::List(_))
+    }
+
+    pub fn is_matrix(&self) -> bool {
+        matches!(self, Value::Matrix(_))
+    }
+
+    pub fn is_function(&self) -> bool {
+        matches!(self, Value::Function(_))
+    }
+
+    /// Unbound names and expressions over them.
+    pub fn is_symbolic(&self) -> bool {
+        matches!(self, Value::Unbound(_) | Value::Symbolic(_))
+    }
+
+    pub fn compare(&self, other: &Self) -> Result<Ordering, MatikaError> {
+        if let (Value::Rational(left), Value::Rational(right)) = (self, other) {
+            return Ok(left.cmp(right));
+        }
+
+        match (self.as_f64(), other.as_f64()) {
+            (Some(number), Some(other)) => number.partial_cmp(&other).ok_or_else(|| {
+                MatikaError::type_error(format!("cannot compare {} and {}", number, other))
+            }),
+            _ => Err(MatikaError::type_error(format!(
+                "cannot compare {} and {}",
+                self.type_name(),
+                other.type_name()
+            ))),
+        }
+    }
+
+    pub fn equals(&self, other: &Self) -> bool {
+        match (self, other) {
+            (Value::Unbound(name), Value::Unbound(other)) => name == other,
+            (Value::Rational(ratio), Value::Rational(other)) => ratio == other,
+            (Value::Number(_), Value::Rational(_)) | (Value::Rational(_), Value::Number(_)) => {
+                self.as_f64() == other.as_f64()
+            }
+            (Value::Number(number), Value::Number(other)) => number == other,
+            (Value::Complex(z), Value::Complex(other)) => z == other,
+            (Value::Bool(value), Value::Bool(other)) => value == other,
+            (Value::List(values), Value::List(other)) => {
+                values.len() == other.len()
+                    && values
+                        .iter()
+                        .zip(other)
+                        .all(|(value, other)| value.equals(other))
+            }
+            (Value::Matrix(matrix), Value::Matrix(other)) => matrix == other,
+            (Value::Matrix(matrix), list @ Value::List(_))
+            | (list @ Value::List(_), Value::Matrix(matrix)) => {
+                list.to_matrix().as_ref() == Some(matrix)
+            }
+            (Value::Function(fnc), Value::Function(other)) => Rc::ptr_eq(fnc, other),
+            (Value::Symbolic(_), Value::Symbolic(_))
+            | (Value::Equation(..), Value::Equation(..)) => self.to_string() == other.to_string(),
+            _ => false,
+        }
+    }
+
+    pub fn as_bool(&self) -> Result<bool, MatikaError> {
+        match self {
+            Value::Bool(value) => Ok(*value),
+            _ => Err(MatikaError::type_error(format!(
+                "expected a boolean, got {}",
+                self.type_name()
+            ))),
+        }
+    }
+
+    pub fn type_name(&self) -> &'static str {
+        match self {
+            Value::Unbound(_) => "unbound name",
+            Value::Number(_) | Value::Rational(_) => "number",
+            Value::Complex(_) => "complex number",
+            Value::Bool(_) => "boolean",
+            Value::List(_) => "list",
+            Value::Matrix(_) => "matrix",
+            Value::Function(_) => "function",
+            Value::Symbolic(_) => "expression",
+            Value::Equation(..) => "equation",
+        }
+    }
+
+    fn unsupported(op: &str, left: &Value, right: &Value) -> MatikaError {
+        MatikaError::type_error(format!(
+            "cannot apply '{}' to {} and {}",
+            op,
+            left.type_name(),
+            right.type_name()
+        ))
+    }
+}
+
+impl From<&LiteralKind> for Value {
+    fn from(literal: &LiteralKind) -> Self {
+        match literal {
+            LiteralKind::Number(number) => Value::Number(*number),
+            LiteralKind::Integer(integer) => Value::integer(integer.clone()),
+            LiteralKind::Bool(value) => Value::Bool(*value),
         }
     }
 }
@@ -27,6 +592,9 @@ impl From<Value> for f64 {
     fn from(value: Value) -> Self {
         match value {
             Value::Number(num) => num,
+            Value::Rational(ratio) => ratio.to_f64().unwrap_or(f64::NAN),
+            Value::Complex(z) => z.re,
+            Value::Bool(true) => 1.0,
             _ => 0.0,
         }
     }
@@ -37,78 +605,189 @@ impl fmt::Display for Value {
         match self {
             Value::Unbound(ident) => write!(f, "{}", ident),
             Value::Number(number) => write!(f, "{}", number),
+            Value::Rational(ratio) => write!(f, "{}", ratio),
+            Value::Complex(z) => write!(f, "{}", z),
+            Value::Bool(value) => write!(f, "{}", value),
             Value::Function(fnc) => write!(f, "{}", fnc),
-            Value::List(numbers) => {
-                write!(f, "{:?}", numbers)
-            }
+            Value::Matrix(matrix) => write!(f, "{}", matrix),
+            Value::Symbolic(expr) => write!(f, "{}", expr),
+            Value::Equation(left, right) => write!(f, "{} == {}", left, right),
+            Value::List(values) => write!(
+                f,
+                "[{}]",
+                values
+                    .iter()
+                    .map(|value| value.to_string())
+                    .collect::<Vec<String>>()
+                    .join(", ")
+            ),
         }
     }
 }
 
 impl ops::Neg for Value {
-    type Output = Self;
+    type Output = Result<Self, MatikaError>;
 
-    fn neg(self) -> Self {
-        if let Value::Number(number) = self {
-            return Value::Number(-number);
+    fn neg(self) -> Self::Output {
+        match self {
+            Value::Number(number) => return Ok(Value::Number(-number)),
+            Value::Rational(ratio) => return Ok(Value::Rational(-ratio)),
+            Value::List(values) => {
+                return values
+                    .into_iter()
+                    .map(|value| -value)
+                    .collect::<Result<Vec<Value>, MatikaError>>()
+                    .map(Value::List)
+            }
+            Value::Complex(z) => return Ok(Value::Complex(-z)),
+            Value::Matrix(matrix) => return Ok(Value::Matrix(matrix.map(|x| -x))),
+            Value::Unbound(_) | Value::Symbolic(_) => return symbolic::negate(&self),
+            Value::Function(_) => return Pointwise::of(TokenKind::Star, Value::integer(-1), self),
+            _ => (),
         }
 
-        panic!()
+        Err(MatikaError::type_error(format!(
+            "cannot negate {}",
+            self.type_name()
+        )))
+    }
+}
+
+impl ops::Not for Value {
+    type Output = Result<Self, MatikaError>;
+
+    fn not(self) -> Self::Output {
+        Ok(Value::Bool(!self.as_bool()?))
     }
 }
 
 impl ops::Add for Value {
-    type Output = Self;
+    type Output = Result<Self, MatikaError>;
 
-    fn add(self, other: Self) -> Self {
-        match self {
-            Value::Number(number) => match other {
-                Value::Number(other) => Value::Number(number + other),
-                _ => panic!(),
-            },
-            _ => panic!(),
-        }
+    fn add(self, other: Self) -> Self::Output {
+        self.arithmetic("+", &other, |a, b| Some(a + b), |a, b| a + b, ops::Add::add)
     }
 }
 
 impl ops::Sub for Value {
-    type Output = Self;
+    type Output = Result<Self, MatikaError>;
 
-    fn sub(self, other: Self) -> Self {
-        match self {
-            Value::Number(number) => match other {
-                Value::Number(other) => Value::Number(number - other),
-                _ => panic!(),
-            },
-            _ => panic!(),
-        }
+    fn sub(self, other: Self) -> Self::Output {
+        self.arithmetic("-", &other, |a, b| Some(a - b), |a, b| a - b, ops::Sub::sub)
     }
 }
 
 impl ops::Mul for Value {
-    type Output = Self;
+    type Output = Result<Self, MatikaError>;
 
-    fn mul(self, other: Self) -> Self {
-        match self {
-            Value::Number(number) => match other {
-                Value::Number(other) => Value::Number(number * other),
-                _ => panic!(),
-            },
-            _ => panic!(),
-        }
+    fn mul(self, other: Self) -> Self::Output {
+        self.arithmetic("*", &other, |a, b| Some(a * b), |a, b| a * b, ops::Mul::mul)
     }
 }
 
 impl ops::Div for Value {
-    type Output = Self;
+    type Output = Result<Self, MatikaError>;
 
-    fn div(self, other: Self) -> Self {
-        match self {
-            Value::Number(number) => match other {
-                Value::Number(other) => Value::Number(number / other),
-                _ => panic!(),
-            },
-            _ => panic!(),
-        }
+    fn div(self, other: Self) -> Self::Output {
+        self.arithmetic(
+            "/",
+            &other,
+            |a, b| if b.is_zero() { None } else { Some(a / b) },
+            |a, b| a / b,
+            ops::Div::div,
+        )
+    }
+}
+
+const MAX_EXACT_BITS: u64 = 1 << 20;
+
+/// Integer powers of rationals, as long as the result stays a reasonable size.
+/// Anything else, including `0^-1`, is left to floating point.
+fn exact_pow(base: &BigRational, exponent: &BigRational) -> Option<BigRational> {
+    if !exponent.is_integer() {
+        return None;
+    }
+
+    let exponent = exponent.to_integer().to_i32()?;
+    let bits = base.numer().bits().max(base.denom().bits());
+
+    if bits.saturating_mul(u64::from(exponent.unsigned_abs())) > MAX_EXACT_BITS
+        || (base.is_zero() && exponent.is_negative())
+    {
+        return None;
+    }
+
+    Some(num_traits::pow::Pow::pow(base, exponent))
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::tests::{eval, session};
+    use crate::Matika;
+
+    #[test]
+    fn fractions_stay_exact() {
+        assert_eq!(eval("1/3 + 1/6 == 1/2"), "true");
+        assert_eq!(eval("1/3 + 1/6"), "1/2");
+        assert_eq!(eval("2^100"), "1267650600228229401496703205376");
+        assert_eq!(eval("float(1/3)"), "0.3333333333333333");
+        assert_eq!(eval("0.1 + 0.2 == 3/10"), "false");
+    }
+
+    #[test]
+    fn approx_mode_prints_floats() {
+        let mut matika = Matika::new();
+        let mut eval = |approx: bool, src: &str| {
+            matika.set_approx(approx);
+            matika.eval(src.to_string()).unwrap().to_string()
+        };
+
+        assert_eq!(eval(false, "1/4"), "1/4");
+        assert_eq!(eval(true, "1/4"), "0.25");
+        assert_eq!(eval(true, "1/3 + 1/6 == 1/2"), "true");
+        assert_eq!(eval(false, "1/4"), "1/4");
+    }
+
+    #[test]
+    fn nested_lists_stay_lists() {
+        assert_eq!(eval("map(r -> r, [[1,2],[3,4]])"), "[[1, 2], [3, 4]]");
+        assert_eq!(eval("[[1,2],[3,4]] ++ [[5,6]]"), "[[1, 2], [3, 4], [5, 6]]");
+        assert_eq!(eval("[[1/3, 2], [3, 4]]"), "[[1/3, 2], [3, 4]]");
+        assert_eq!(eval("[[1/3, 2], [3, 4]] * 3"), "[[1, 6], [9, 12]]");
+        assert_eq!(eval("[[1,2],[3,4]] + [[1,1],[1,1]]"), "[[2, 3], [4, 5]]");
+    }
+
+    #[test]
+    fn nested_lists_multiply_as_matrices() {
+        assert_eq!(
+            eval("[[1,2],[3,4]] * [[5,6],[7,8]]"),
+            "[[19, 22], [43, 50]]"
+        );
+        assert_eq!(eval("[[1,2],[3,4]] * [1,1]"), "[3, 7]");
+        assert_eq!(eval("[1,1] * [[1,2],[3,4]]"), "[4, 6]");
+        assert_eq!(eval("[1,2] * [3,4]"), "[3, 8]");
+        assert_eq!(eval("[[1,1],[0,1]] ^ 3"), "[[1, 3], [0, 1]]");
+        assert_eq!(eval("solve([[2,0],[0,4]], [2,2])"), "[1, 0.5]");
+    }
+
+    #[test]
+    fn matrix_products_compare_and_print_as_lists() {
+        let eval = |line| session(&["M = [[1,2],[3,4]]", line]).unwrap();
+
+        assert_eq!(eval("M * M == [[7,10],[15,22]]"), "true");
+        assert_eq!(eval("M * M == M + M"), "false");
+        assert_eq!(eval("inv([[2,0],[0,4]]) == [[1/2,0],[0,1/4]]"), "true");
+        assert_eq!(eval("transpose(M) == [[1,3],[2,4]]"), "true");
+        assert_eq!(eval("M * M"), "[[7, 10], [15, 22]]");
+        assert_eq!(eval("M * 2"), "[[2, 4], [6, 8]]");
+        assert_eq!(eval("M + M"), "[[2, 4], [6, 8]]");
+    }
+
+    #[test]
+    fn matrices_are_lists_of_rows() {
+        assert_eq!(eval("map(r -> sum(r), inv([[2,0],[0,4]]))"), "[0.5, 0.25]");
+        assert_eq!(eval("len(transpose([[1,2,3]]))"), "3");
+        assert_eq!(eval("transpose([[1,2]]) ++ [[3]]"), "[[1], [2], [3]]");
+        assert_eq!(eval("transpose([[1,2]])[1]"), "[2]");
     }
 }
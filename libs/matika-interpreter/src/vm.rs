@@ -0,0 +1,380 @@
+use std::rc::Rc;
+
+use crate::chunk::{Chunk, Op};
+use crate::environment::Environment;
+use crate::error::MatikaError;
+use crate::functions::Function;
+use crate::interpreter::{Interpreter, MAX_CALL_DEPTH};
+use crate::token::Span;
+use crate::value::Value;
+
+struct Frame {
+    chunk: Rc<Chunk>,
+    ip: usize,
+    base: usize,
+    env: Environment,
+}
+
+/// Stack machine running `Chunk`s produced by the `Compiler`. Arguments of the
+/// running user function sit on the stack right above their callee, so a call
+/// only pushes a frame instead of building a new environment.
+pub struct Vm<'a> {
+    interpreter: &'a mut Interpreter,
+    stack: Vec<Value>,
+    frames: Vec<Frame>,
+    depth: usize,
+}
+
+impl<'a> Vm<'a> {
+    pub fn new(interpreter: &'a mut Interpreter) -> Self {
+        let depth = interpreter.depth;
+
+        Self {
+            interpreter,
+            depth,
+            stack: vec![],
+            frames: vec![],
+        }
+    }
+
+    pub fn run_script(mut self, chunk: Chunk) -> Result<Value, MatikaError> {
+        let env = self.interpreter.environment.clone();
+
+        // Scripts get an empty callee slot so every frame has the same layout.
+        self.stack.push(Value::Number(0.0));
+        self.frames.push(Frame {
+            chunk: Rc::new(chunk),
+            ip: 0,
+            base: 1,
+            env,
+        });
+
+        self.run()
+    }
+
+    pub fn call(mut self, function: &Function, args: Vec<Value>) -> Result<Value, MatikaError> {
+        let argc = args.len();
+
+        self.stack.push(Value::Number(0.0));
+        self.stack.extend(args);
+        self.enter(function, argc)?;
+
+        self.run()
+    }
+
+    fn enter(&mut self, function: &Function, argc: usize) -> Result<(), MatikaError> {
+        if self.interpreter.depth >= MAX_CALL_DEPTH {
+            return Err(MatikaError::recursion_limit(MAX_CALL_DEPTH));
+        }
+
+        let base = self.stack.len() - argc;
+        let clause = function.select(&self.stack[base..])?;
+
+        self.interpreter.depth += 1;
+
+        self.frames.push(Frame {
+            chunk: function.chunk(clause),
+            ip: 0,
+            base,
+            env: function.closure.clone(),
+        });
+
+        Ok(())
+    }
+
+    fn run(&mut self) -> Result<Value, MatikaError> {
+        loop {
+            match self.step() {
+                Ok(Some(value)) => return Ok(value),
+                Ok(None) => (),
+                Err(err) => return Err(self.locate(err)),
+            }
+        }
+    }
+
+    /// Errors raised inside a called function are reported at the call site in
+    /// the outermost frame, whose source is the one being evaluated.
+    fn locate(&self, err: MatikaError) -> MatikaError {
+        match (self.frames.first(), self.frames.last()) {
+            (Some(outer), _) if self.frames.len() > 1 => err.relocate(Self::span(outer)),
+            (_, Some(frame)) => err.at(Self::span(frame)),
+            _ => err,
+        }
+    }
+
+    fn span(frame: &Frame) -> Span {
+        frame.chunk.spans[frame.ip.saturating_sub(1)]
+    }
+
+    fn step(&mut self) -> Result<Option<Value>, MatikaError> {
+        let current = self.frames.len() - 1;
+        let frame = &mut self.frames[current];
+        let op = frame.chunk.code[frame.ip];
+
+        frame.ip += 1;
+
+        match op {
+            Op::Constant(idx) => {
+                let value = frame.chunk.constants[idx].clone();
+
+                self.stack.push(value);
+            }
+            Op::GetLocal(slot) => {
+                let value = self.stack[frame.base + slot].clone();
+
+                self.stack.push(value);
+            }
+            Op::GetName(idx) => {
+                let name = &frame.chunk.names[idx];
+                let value = frame
+                    .env
+                    .get(name)
+                    .unwrap_or_else(|| Value::Unbound(name.clone()));
+
+                self.stack.push(value);
+            }
+            Op::DefineName(idx) => {
+                let name = frame.chunk.names[idx].clone();
+                let env = frame.env.clone();
+                let value = self.pop();
+
+                env.define(name, value);
+            }
+            Op::DefineFunction(idx) => Function::define(&frame.env, &frame.chunk.functions[idx]),
+            Op::Lambda(idx) => {
+                // Parameters live on the stack, so the ones a lambda may refer
+                // to are copied into its closure like the tree walker's scope.
+                let env = if frame.chunk.locals.is_empty() {
+                    frame.env.clone()
+                } else {
+                    let env = Environment::from_enclosing(frame.env.clone());
+
+                    for (slot, local) in frame.chunk.locals.iter().enumerate() {
+                        if let Some(name) = local {
+                            env.define(name.clone(), self.stack[frame.base + slot].clone());
+                        }
+                    }
+
+                    env
+                };
+                let lambda = Function::lambda(frame.chunk.functions[idx].clone(), env);
+
+                self.stack.push(Value::Function(Rc::new(lambda)));
+            }
+            Op::Unary(kind) => {
+                let right = self.pop();
+
+                self.stack.push(Value::unary(kind, right)?);
+            }
+            Op::Binary(kind) => {
+                let right = self.pop();
+                let left = self.pop();
+
+                self.stack.push(Value::binary(kind, left, right)?);
+            }
+            Op::AssertBool => {
+                self.peek().as_bool()?;
+            }
+            Op::Dup => {
+                let value = self.peek().clone();
+
+                self.stack.push(value);
+            }
+            Op::Pop => {
+                self.pop();
+            }
+            Op::Jump(target) => frame.ip = target,
+            Op::JumpIfFalse(target) => {
+                if !self.pop().as_bool()? {
+                    self.frames[current].ip = target;
+                }
+            }
+            Op::JumpIfTrue(target) => {
+                if self.pop().as_bool()? {
+                    self.frames[current].ip = target;
+                }
+            }
+            Op::Call(argc) => self.call_value(argc)?,
+            Op::List(len) => {
+                let values = self.stack.split_off(self.stack.len() - len);
+
+                self.stack.push(Value::List(values));
+            }
+            Op::Index => {
+                let index = self.pop();
+                let object = self.pop();
+
+                self.stack.push(object.index(&index)?);
+            }
+            Op::Slice(has_start, has_end) => {
+                let end = if has_end { Some(self.pop()) } else { None };
+                let start = if has_start { Some(self.pop()) } else { None };
+                let object = self.pop();
+
+                self.stack.push(object.slice(start.as_ref(), end.as_ref())?);
+            }
+            Op::Print => println!("{}", self.pop()),
+            Op::Return => {
+                let result = self.pop();
+
+                if let Some(frame) = self.frames.pop() {
+                    self.stack.truncate(frame.base - 1);
+                }
+
+                if self.frames.is_empty() {
+                    return Ok(Some(result));
+                }
+
+                self.interpreter.depth -= 1;
+
+                self.stack.push(result);
+            }
+        }
+
+        Ok(None)
+    }
+
+    fn call_value(&mut self, argc: usize) -> Result<(), MatikaError> {
+        let slot = self.stack.len() - argc - 1;
+
+        match self.stack[slot].clone() {
+            Value::Function(fnc) => {
+                if !fnc.accepts(argc) {
+                    return Err(MatikaError::Arity {
+                        callee: fnc.label(),
+                        expected: fnc.arity(),
+                        got: argc,
+                        span: None,
+                    });
+                }
+
+                if let Some(function) = fnc.as_function() {
+                    return self.enter(function, argc);
+                }
+
+                let args = self.stack.split_off(slot + 1);
+                let span = self.frames.last().map(Self::span).unwrap_or_default();
+
+                self.stack.pop();
+
+                let result = fnc
+                    .call(self.interpreter, args)
+                    .map_err(|err| err.relocate(span))?;
+
+                self.stack.push(result);
+
+                Ok(())
+            }
+            Value::Unbound(name) => Err(MatikaError::UnknownFunction {
+                name,
+                suggestion: None,
+                span: None,
+            }),
+            value => Err(MatikaError::NotCallable {
+                name: value.to_string(),
+                span: None,
+            }),
+        }
+    }
+
+    fn pop(&mut self) -> Value {
+        self.stack.pop().expect("vm stack underflow")
+    }
+
+    fn peek(&self) -> &Value {
+        self.stack.last().expect("vm stack underflow")
+    }
+}
+
+impl<'a> Drop for Vm<'a> {
+    // Frames left behind by an error, and the outermost frame, which returns
+    // without unwinding its depth, are all released here.
+    fn drop(&mut self) {
+        self.interpreter.depth = self.depth;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{Backend, Matika};
+
+    /// Runs `lines` in one session on each backend and checks that every line
+    /// gives the same value, or the same error at the same place.
+    fn same_on_both(lines: &[&str]) {
+        let mut walker = Matika::new().with_backend(Backend::TreeWalker);
+        let mut vm = Matika::new().with_backend(Backend::Vm);
+
+        for line in lines {
+            let expected = walker.eval(line.to_string()).map(|value| value.to_string());
+            let got = vm.eval(line.to_string()).map(|value| value.to_string());
+
+            assert_eq!(got, expected, "{}", line);
+        }
+    }
+
+    #[test]
+    fn arithmetic_and_logic() {
+        same_on_both(&[
+            "1 + 2*3 - 4/6",
+            "-2^2",
+            "2^0.5",
+            "i^2",
+            "1/0",
+            "true and 1 < 2 or false",
+            "not true",
+            "1 if 2 > 3 else 4",
+            "x^2 + 2x + 1",
+            "1 < [1]",
+        ]);
+    }
+
+    #[test]
+    fn functions_and_closures() {
+        same_on_both(&[
+            "fact(0) = 1",
+            "fact(n) = n * fact(n - 1)",
+            "fact(10)",
+            "fib(n) = n if n < 2 else fib(n - 1) + fib(n - 2)",
+            "fib(15)",
+            "make(a) = x -> x + a",
+            "make(2)(3)",
+            "add = make(10)",
+            "add(fact(3))",
+            "(x -> x)(1, 2)",
+            "x -> x + z",
+            "compose(sin, cos)(0)",
+            "sum(k -> fact(k), 1, 5)",
+            "map(x -> make(x)(x), [1, 2, 3])",
+            "undefined(1)",
+            "3(1)",
+        ]);
+    }
+
+    #[test]
+    fn lists_and_indexing() {
+        same_on_both(&[
+            "v = [1, 2, 3]",
+            "v[1:]",
+            "v[-1]",
+            "v[5]",
+            "v + [1, 1, 1]",
+            "len(v)",
+            "[[1, 2], [3, 4]] * [[1, 0], [0, 1]]",
+            "[[1, 2], [3, 4]][0]",
+            "filter(x -> x > 1, v)",
+        ]);
+    }
+
+    #[test]
+    fn redefinitions_are_seen_by_both() {
+        same_on_both(&[
+            "a = 2",
+            "f(x) = a*x",
+            "f(3)",
+            "a = 5",
+            "f(3)",
+            "f(0) = 100",
+            "f(0) + f(1)",
+        ]);
+    }
+}
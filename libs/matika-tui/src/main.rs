@@ -1,5 +1,6 @@
 use matika_interpreter::{Plotter, Value, Matika};
 use std::collections::HashSet;
+use std::{env, fs};
 use textplots::{Chart, Plot, Shape};
 use rustyline::error::ReadlineError;
 use rustyline::completion::{Completer, FilenameCompleter, Pair};
@@ -92,7 +93,33 @@ fn hints() -> HashSet<CommandHint> {
 
     set.insert(CommandHint::new("pi()", "pi()"));
     set.insert(CommandHint::new("sin()", "sin("));
+    set.insert(CommandHint::new("sqrt()", "sqrt("));
+    set.insert(CommandHint::new("exp()", "exp("));
+    set.insert(CommandHint::new("ln()", "ln("));
+    set.insert(CommandHint::new("abs()", "abs("));
     set.insert(CommandHint::new("factors()", "factors("));
+    set.insert(CommandHint::new("factorial()", "factorial("));
+    set.insert(CommandHint::new("len()", "len("));
+    set.insert(CommandHint::new("det()", "det("));
+    set.insert(CommandHint::new("inv()", "inv("));
+    set.insert(CommandHint::new("transpose()", "transpose("));
+    set.insert(CommandHint::new("solve()", "solve("));
+    set.insert(CommandHint::new("eigenvalues()", "eigenvalues("));
+    set.insert(CommandHint::new("map()", "map("));
+    set.insert(CommandHint::new("filter()", "filter("));
+    set.insert(CommandHint::new("reduce()", "reduce("));
+    set.insert(CommandHint::new("sum()", "sum("));
+    set.insert(CommandHint::new("prod()", "prod("));
+    set.insert(CommandHint::new("range()", "range("));
+    set.insert(CommandHint::new("compose()", "compose("));
+    set.insert(CommandHint::new("derivative()", "derivative("));
+    set.insert(CommandHint::new("simplify()", "simplify("));
+    set.insert(CommandHint::new("expand()", "expand("));
+    set.insert(CommandHint::new("factor()", "factor("));
+    set.insert(CommandHint::new("subs()", "subs("));
+    set.insert(CommandHint::new("roots()", "roots("));
+    set.insert(CommandHint::new("integrate()", "integrate("));
+    set.insert(CommandHint::new("quad()", "quad("));
     set.insert(CommandHint::new("plot()", "plot("));
 
     set
@@ -215,6 +242,35 @@ impl Runner {
         }
     }
 
+    fn run_file(&mut self, path: &str) {
+        let source = match fs::read_to_string(path) {
+            Ok(source) => source,
+            Err(err) => {
+                println!("{}: {}", path, err);
+                return;
+            }
+        };
+
+        let result = self.matika.eval_script(source.clone());
+
+        self.print_warnings(&source);
+
+        match result {
+            Ok(result) => println!("{}", result),
+            Err(errors) => {
+                for err in errors {
+                    println!("\x1b[1;31m{}\x1b[0m", err.render(&source));
+                }
+            }
+        }
+    }
+
+    fn print_warnings(&self, source: &str) {
+        for warning in self.matika.warnings() {
+            println!("\x1b[1;33m{}\x1b[0m", warning.render(source));
+        }
+    }
+
     fn run_prompt(&mut self) {
         if self.editor.load_history("history.txt").is_err() {
             println!("No previous history.");
@@ -223,9 +279,14 @@ impl Runner {
         loop {
             match self.read_line() {
                 Ok(line) => {
-                    let result = self.matika.eval(line);
+                    let result = self.matika.eval(line.clone());
+
+                    self.print_warnings(&line);
 
-                    println!("{}", result);
+                    match result {
+                        Ok(result) => println!("{}", result),
+                        Err(err) => println!("\x1b[1;31m{}\x1b[0m", err.render(&line)),
+                    }
                 }
                 Err(err) => {
                     println!("{}", err);
@@ -242,7 +303,7 @@ impl Runner {
 
         self.editor.helper_mut().expect("No helper").colored_prompt = format!("\x1b[1;32m{}\x1b[0m", prompt);
 
-        let readline = self.editor.readline(&prompt);
+        let readline = self.editor.readline(prompt);
 
         match readline {
             Ok(line) => {
@@ -266,5 +327,9 @@ impl Runner {
 fn main() {
     let mut runner = Runner::new();
 
+    if let Some(path) = env::args().nth(1) {
+        runner.run_file(&path);
+    }
+
     runner.run_prompt();
 }
@@ -1,20 +1,138 @@
-use matika_interpreter::Matika as Libmatika;
+use matika_interpreter::{Matika as Libmatika, MatikaError};
 use wasm_bindgen::prelude::*;
 
 #[wasm_bindgen]
 pub struct Matika {
     interpreter: Libmatika,
+    source: String,
+}
+
+#[wasm_bindgen]
+pub struct Diagnostic {
+    message: String,
+    rendered: String,
+    line: usize,
+    column: usize,
+    start: usize,
+    end: usize,
+}
+
+impl Diagnostic {
+    fn new(err: MatikaError, source: &str) -> Self {
+        let span = err.span().unwrap_or_default();
+
+        Self {
+            message: err.to_string(),
+            rendered: err.render(source),
+            line: span.line,
+            column: span.column,
+            start: span.start,
+            end: span.end,
+        }
+    }
+}
+
+#[wasm_bindgen]
+impl Diagnostic {
+    #[wasm_bindgen(getter)]
+    pub fn message(&self) -> String {
+        self.message.clone()
+    }
+
+    #[wasm_bindgen(getter)]
+    pub fn rendered(&self) -> String {
+        self.rendered.clone()
+    }
+
+    #[wasm_bindgen(getter)]
+    pub fn line(&self) -> usize {
+        self.line
+    }
+
+    #[wasm_bindgen(getter)]
+    pub fn column(&self) -> usize {
+        self.column
+    }
+
+    #[wasm_bindgen(getter)]
+    pub fn start(&self) -> usize {
+        self.start
+    }
+
+    #[wasm_bindgen(getter)]
+    pub fn end(&self) -> usize {
+        self.end
+    }
+}
+
+/// Result of `evalComplex`; real results have a zero imaginary part.
+#[wasm_bindgen]
+pub struct Complex {
+    re: f64,
+    im: f64,
+}
+
+#[wasm_bindgen]
+impl Complex {
+    #[wasm_bindgen(getter)]
+    pub fn re(&self) -> f64 {
+        self.re
+    }
+
+    #[wasm_bindgen(getter)]
+    pub fn im(&self) -> f64 {
+        self.im
+    }
+}
+
+impl Default for Matika {
+    fn default() -> Self {
+        Self::new()
+    }
 }
 
 #[wasm_bindgen]
 impl Matika {
     #[wasm_bindgen(constructor)]
     pub fn new() -> Self {
-        Self { interpreter: Libmatika::new() }
+        Self {
+            interpreter: Libmatika::new(),
+            source: String::new(),
+        }
     }
 
     #[wasm_bindgen]
-    pub fn eval(&mut self, txt: String) -> f64 {
-        self.interpreter.eval(txt).into()
+    pub fn eval(&mut self, txt: String) -> Result<f64, Diagnostic> {
+        self.source = txt.clone();
+
+        self.interpreter
+            .eval(txt.clone())
+            .map(|value| value.into())
+            .map_err(|err| Diagnostic::new(err, &txt))
+    }
+
+    /// Like `eval`, but keeps the imaginary part of complex results.
+    #[wasm_bindgen(js_name = evalComplex)]
+    pub fn eval_complex(&mut self, txt: String) -> Result<Complex, Diagnostic> {
+        self.source = txt.clone();
+
+        let value = self
+            .interpreter
+            .eval(txt.clone())
+            .map_err(|err| Diagnostic::new(err, &txt))?;
+
+        let z = value.as_complex().unwrap_or_default();
+
+        Ok(Complex { re: z.re, im: z.im })
     }
-}
\ No newline at end of file
+
+    /// Rendered warnings from the last `eval`.
+    #[wasm_bindgen]
+    pub fn warnings(&self) -> Box<[JsValue]> {
+        self.interpreter
+            .warnings()
+            .iter()
+            .map(|warning| JsValue::from_str(&warning.render(&self.source)))
+            .collect()
+    }
+}